@@ -0,0 +1,106 @@
+use std::error;
+use std::fmt;
+
+use serde::{Serialize, Serializer};
+
+/// Errors of the chisel binary. Each kind of error exits with its own code, so that scripts can
+/// tell a broken configuration from a module which merely failed validation.
+#[derive(Clone, Debug, PartialEq)]
+pub enum ChiselError {
+    /// The command line, the configuration file or the options of a module are invalid.
+    Config(String),
+    /// A file could not be read or written.
+    Io(String),
+    /// A wasm binary could not be decoded or encoded.
+    Decode(String),
+    /// A validator found the binary malformed.
+    Validation(String),
+    /// A translator or creator failed.
+    Translation(String),
+}
+
+impl ChiselError {
+    /// Returns the exit code of the binary for this error. Success exits with 0.
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            ChiselError::Validation(_) => 1,
+            ChiselError::Translation(_) => 2,
+            ChiselError::Config(_) => 3,
+            ChiselError::Io(_) => 4,
+            ChiselError::Decode(_) => 5,
+        }
+    }
+
+    pub fn message(&self) -> &str {
+        match self {
+            ChiselError::Config(message)
+            | ChiselError::Io(message)
+            | ChiselError::Decode(message)
+            | ChiselError::Validation(message)
+            | ChiselError::Translation(message) => message,
+        }
+    }
+
+    /// Returns an error of the same kind with another message, e.g. to summarize several errors.
+    pub fn with_message(&self, message: String) -> Self {
+        match self {
+            ChiselError::Config(_) => ChiselError::Config(message),
+            ChiselError::Io(_) => ChiselError::Io(message),
+            ChiselError::Decode(_) => ChiselError::Decode(message),
+            ChiselError::Validation(_) => ChiselError::Validation(message),
+            ChiselError::Translation(_) => ChiselError::Translation(message),
+        }
+    }
+}
+
+impl fmt::Display for ChiselError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.message())
+    }
+}
+
+impl error::Error for ChiselError {}
+
+/// Errors are reported as their message in JSON output.
+impl Serialize for ChiselError {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.serialize_str(self.message())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn exit_codes() {
+        let errors = [
+            ChiselError::Validation("malformed".to_string()),
+            ChiselError::Translation("failed".to_string()),
+            ChiselError::Config("invalid".to_string()),
+            ChiselError::Io("unreadable".to_string()),
+            ChiselError::Decode("undecodable".to_string()),
+        ];
+        let codes: Vec<i32> = errors.iter().map(ChiselError::exit_code).collect();
+        assert_eq!(codes, vec![1, 2, 3, 4, 5]);
+    }
+
+    #[test]
+    fn with_message() {
+        let error = ChiselError::Io("Failed to open a.wasm".to_string());
+        let summary = error.with_message("Run failed: 2 failing ruleset(s).".to_string());
+        assert_eq!(
+            summary,
+            ChiselError::Io("Run failed: 2 failing ruleset(s).".to_string())
+        );
+        assert_eq!(summary.exit_code(), error.exit_code());
+        assert_eq!(summary.to_string(), "Run failed: 2 failing ruleset(s).");
+        assert_eq!(
+            serde_json::to_string(&error).unwrap(),
+            "\"Failed to open a.wasm\""
+        );
+    }
+}
@@ -3,36 +3,60 @@ extern crate parity_wasm;
 #[macro_use]
 extern crate clap;
 extern crate serde;
+#[macro_use]
 extern crate serde_derive;
+extern crate serde_json;
 extern crate serde_yaml;
 
-use std::fs::{read, read_to_string};
+mod error;
+
+use std::fs::{read, read_to_string, write};
+use std::io::{stdin, stdout, Read, Write};
 use std::process;
+use std::time::Instant;
 
 use libchisel::{
-    checkstartfunc::*, deployer::*, remapimports::*, remapstart::*, repack::*, trimexports::*,
-    trimstartfunc::*, verifyexports::*, verifyimports::*,
+    auditimports::*,
+    callgraph::CallGraph,
+    checkfloats::*,
+    checklimits::*,
+    checkstartfunc::*,
+    deployer::*,
+    diff::ModuleDiff,
+    dropsection::*,
+    limitmemory::*,
+    limitstack::*,
+    metergas::*,
+    remapimports::*,
+    remapstart::*,
+    renameexports::*,
+    repack::*,
+    report::{Finding, ValidationReport},
+    softfloat::*,
+    trimexports::*,
+    trimfunctions::*,
+    trimstartfunc::*,
+    verifyexports::*,
+    verifyimports::*,
 };
 
 use clap::{App, Arg, ArgMatches, SubCommand};
+use error::ChiselError;
 use libchisel::*;
-use parity_wasm::elements::{deserialize_buffer, serialize_to_file, Module, Serialize};
-use serde_yaml::Value;
+use parity_wasm::elements::{deserialize_buffer, serialize, Module};
+use serde_yaml::{Mapping, Value};
 
 // Error messages
 static ERR_NO_SUBCOMMAND: &'static str = "No subcommand provided.";
-static ERR_FAILED_OPEN_CONFIG: &'static str = "Failed to open configuration file.";
-static ERR_FAILED_OPEN_BINARY: &'static str = "Failed to open wasm binary.";
 static ERR_FAILED_PARSE_CONFIG: &'static str = "Failed to parse configuration file.";
 static ERR_CONFIG_INVALID: &'static str = "Config is invalid.";
 static ERR_CONFIG_MISSING_FILE: &'static str = "Config missing file path to chisel.";
-static ERR_INPUT_FILE_TYPE_MISMATCH: &'static str = "Entry 'file' does not map to a string.";
+static ERR_RULESET_TYPE_MISMATCH: &'static str =
+    "A ruleset does not map a name to a key-value map of modules.";
 static ERR_MODULE_TYPE_MISMATCH: &'static str =
-    "A module configuration does not point to a key-value map. Perhaps an option field is missing?";
-static ERR_PRESET_TYPE_MISMATCH: &'static str =
-    "A field 'preset' belonging to a module is not a string";
-static ERR_DESERIALIZE_MODULE: &'static str = "Failed to deserialize the wasm binary.";
-static ERR_MISSING_PRESET: &'static str = "Module configuration missing preset.";
+    "A module configuration does not point to a key-value map of options.";
+static ERR_MODULE_NOT_FOUND: &'static str = "Module Not Found";
+static ERR_INVALID_OPTION: &'static str = "Module options must be given as KEY=VALUE.";
 
 // Other constants
 static DEFAULT_CONFIG_PATH: &'static str = "chisel.yml";
@@ -46,62 +70,51 @@ struct ChiselContext {
     modules: Vec<ModuleContext>,
 }
 
+/// Module configuration. Options are passed to the module as-is, so each module defines and
+/// checks its own options.
 struct ModuleContext {
     module_name: String,
-    preset: String,
-}
-
-/// Helper to get a field from a config mapping. Assumes that the Value is a Mapping.
-fn get_field(yaml: &Value, key: &str) -> Result<String, &'static str> {
-    if let Some(path) = yaml
-        .as_mapping()
-        .unwrap()
-        .get(&Value::String(String::from(key)))
-    {
-        if path.is_string() {
-            Ok(String::from(path.as_str().unwrap()))
-        } else {
-            Err(ERR_INPUT_FILE_TYPE_MISMATCH)
-        }
-    } else {
-        Err(ERR_CONFIG_MISSING_FILE)
+    options: Value,
+}
+
+/// Helper to get a string field from a ruleset. Returns None if the field is missing.
+fn get_field(ruleset: &Mapping, key: &str) -> Result<Option<String>, ChiselError> {
+    match ruleset.get(&Value::String(String::from(key))) {
+        Some(Value::String(value)) => Ok(Some(value.clone())),
+        Some(_) => Err(ChiselError::Config(format!(
+            "Entry '{}' does not map to a string.",
+            key
+        ))),
+        None => Ok(None),
     }
 }
 
 impl ChiselContext {
-    fn from_ruleset(ruleset: &Value) -> Result<Vec<Self>, &'static str> {
+    fn from_ruleset(ruleset: &Value) -> Result<Vec<Self>, ChiselError> {
         if let Value::Mapping(rules) = ruleset {
             let mut ret: Vec<ChiselContext> = vec![];
 
-            for (name, mut config) in rules.iter().filter(|(left, right)| match (left, right) {
-                (Value::String(_s), Value::Mapping(_m)) => true,
-                _ => false,
-            }) {
-                let filepath = get_field(config, "file")?;
-
-                let outfilepath = if let Ok(out) = get_field(config, "output") {
-                    Some(out)
-                } else {
-                    None
+            for (name, config) in rules.iter() {
+                let (name, config) = match (name, config) {
+                    (Value::String(name), Value::Mapping(config)) => (name, config),
+                    _ => return Err(ChiselError::Config(ERR_RULESET_TYPE_MISMATCH.to_string())),
                 };
+                let filepath = get_field(config, "file")?
+                    .ok_or_else(|| ChiselError::Config(ERR_CONFIG_MISSING_FILE.to_string()))?;
+                let outfilepath = get_field(config, "output")?;
 
-                // Parse all valid module entries. Unwrap is ok here because we
-                // established earlier that config is a Mapping.
-                let mut config_clone = config.as_mapping().unwrap().clone();
-                // Remove "file" and "output" so we don't interpret it as a module.
-                // TODO: use mappings to avoid the need for this
-                config_clone.remove(&Value::String(String::from("file")));
-                config_clone.remove(&Value::String(String::from("output")));
-
+                // Parse all valid module entries in the order they are listed.
                 let mut module_confs: Vec<ModuleContext> = vec![];
-                let mut config_itr = config_clone.iter();
-                // Read modules while there are still modules left.
-                while let Some(module) = config_itr.next() {
-                    module_confs.push(ModuleContext::from_yaml(module)?);
+                for module in config.iter() {
+                    // Skip "file" and "output" so we don't interpret it as a module.
+                    match module.0.as_str() {
+                        Some("file") | Some("output") => continue,
+                        _ => module_confs.push(ModuleContext::from_yaml(module)?),
+                    }
                 }
 
                 ret.push(ChiselContext {
-                    ruleset_name: name.as_str().unwrap().into(),
+                    ruleset_name: name.clone(),
                     file: filepath,
                     outfile: outfilepath,
                     modules: module_confs,
@@ -110,7 +123,7 @@ impl ChiselContext {
 
             Ok(ret)
         } else {
-            Err(ERR_CONFIG_INVALID)
+            Err(ChiselError::Config(ERR_CONFIG_INVALID.to_string()))
         }
     }
 
@@ -132,214 +145,647 @@ impl ChiselContext {
 }
 
 impl ModuleContext {
-    fn from_yaml(yaml: (&Value, &Value)) -> Result<Self, &'static str> {
+    fn from_yaml(yaml: (&Value, &Value)) -> Result<Self, ChiselError> {
         match yaml {
-            (Value::String(name), Value::Mapping(flags)) => Ok(ModuleContext {
+            (Value::String(name), Value::Mapping(_)) => Ok(ModuleContext {
                 module_name: name.clone(),
-                preset: if let Some(pset) = flags.get(&Value::String(String::from("preset"))) {
-                    // Check that the value to which "preset" resolves is a String. If not, return an error.
-                    if pset.is_string() {
-                        String::from(pset.as_str().unwrap())
-                    } else {
-                        return Err(ERR_PRESET_TYPE_MISMATCH);
-                    }
-                } else {
-                    return Err(ERR_MISSING_PRESET);
-                },
+                options: yaml.1.clone(),
+            }),
+            // A module without options, e.g. `repack:`.
+            (Value::String(name), Value::Null) => Ok(ModuleContext {
+                module_name: name.clone(),
+                options: Value::Mapping(Mapping::new()),
             }),
-            _ => Err(ERR_MODULE_TYPE_MISMATCH),
+            _ => Err(ChiselError::Config(ERR_MODULE_TYPE_MISMATCH.to_string())),
         }
     }
 
-    fn fields(&self) -> (&String, &String) {
-        (&self.module_name, &self.preset)
+    fn fields(&self) -> (&String, &Value) {
+        (&self.module_name, &self.options)
     }
 }
 
-fn err_exit(msg: &str) -> ! {
-    println!("{}: {}", crate_name!(), msg);
-    process::exit(-1);
+/// Kind of a module, as reported in the results.
+#[derive(Clone, Copy, Debug, Serialize)]
+#[serde(rename_all = "snake_case")]
+enum ModuleKind {
+    Validator,
+    Translator,
+    Creator,
 }
 
-fn yaml_configure(yaml: &String) -> Result<Vec<ChiselContext>, &'static str> {
-    if let Ok(rulesets) = serde_yaml::from_str::<Value>(yaml.as_str()) {
+/// Outcome of running a module.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+enum ModuleStatus {
+    /// A validator found no errors.
+    Ok,
+    /// A validator found errors.
+    Malformed,
+    /// A translator or creator changed the module.
+    Translated,
+    /// A translator found nothing to change.
+    Unchanged,
+    /// A translator was not run by `chisel verify`.
+    Skipped,
+    /// The module could not be configured or run, or does not exist.
+    Failed,
+}
+
+/// Results of a single module, printed as text or JSON.
+#[derive(Debug, Serialize)]
+struct ModuleResult {
+    name: String,
+    preset: Option<String>,
+    /// Unknown modules have no kind.
+    kind: Option<ModuleKind>,
+    status: ModuleStatus,
+    /// Reason of the failure, if the module failed.
+    error: Option<ChiselError>,
+    findings: Vec<Finding>,
+    /// Time spent configuring and running the module, in milliseconds.
+    time_ms: f64,
+}
+
+/// Results of a ruleset, printed as one JSON document with `--format json`.
+#[derive(Debug, Serialize)]
+struct RulesetResult<'a> {
+    ruleset: &'a str,
+    input: &'a str,
+    /// File written to, if the module was changed and not in a dry run.
+    output: Option<&'a str>,
+    success: bool,
+    /// Error which stopped the ruleset, such as an input file which could not be read. Failures
+    /// of modules are reported with each module.
+    error: Option<ChiselError>,
+    modules: Vec<ModuleResult>,
+    /// Changes which would have been written, in a dry run.
+    changes: Option<ModuleDiff>,
+}
+
+impl<'a> RulesetResult<'a> {
+    fn new(context: &'a ChiselContext) -> Self {
+        RulesetResult {
+            ruleset: context.name(),
+            input: context.file(),
+            output: None,
+            success: false,
+            error: None,
+            modules: Vec::new(),
+            changes: None,
+        }
+    }
+}
+
+impl ModuleResult {
+    /// Returns the error of a failed module, or of a validator which found the binary malformed.
+    fn failure(&self) -> Option<ChiselError> {
+        match self.status {
+            ModuleStatus::Malformed => {
+                Some(ChiselError::Validation(format!("{}: Malformed", self.name)))
+            }
+            ModuleStatus::Failed => self
+                .error
+                .as_ref()
+                .map(|e| e.with_message(format!("{}: {}", self.name, e))),
+            _ => None,
+        }
+    }
+
+    fn print(&self) {
+        let status = match self.status {
+            ModuleStatus::Ok => "OK",
+            ModuleStatus::Malformed => "Malformed",
+            ModuleStatus::Translated => "Translated",
+            ModuleStatus::Unchanged => "Already OK; not translated",
+            ModuleStatus::Skipped => "Skipped",
+            ModuleStatus::Failed => self.error.as_ref().map_or("Failed", ChiselError::message),
+        };
+        println!("\t{}: {}", self.name, status);
+        for finding in self.findings.iter() {
+            println!("\t\t{}", finding);
+        }
+    }
+}
+
+fn yaml_configure(yaml: &str) -> Result<Vec<ChiselContext>, ChiselError> {
+    if let Ok(rulesets) = serde_yaml::from_str::<Value>(yaml) {
         ChiselContext::from_ruleset(&rulesets)
     } else {
-        Err(ERR_FAILED_PARSE_CONFIG)
+        Err(ChiselError::Config(ERR_FAILED_PARSE_CONFIG.to_string()))
+    }
+}
+
+/// Reads the rulesets of a configuration file.
+fn read_config(path: &str) -> Result<Vec<ChiselContext>, ChiselError> {
+    match read_to_string(path) {
+        Ok(conf) => yaml_configure(&conf),
+        Err(e) => Err(ChiselError::Io(format!(
+            "Failed to open configuration file {}: {}",
+            path, e
+        ))),
+    }
+}
+
+fn decode_module(buffer: &[u8]) -> Result<Module, ChiselError> {
+    deserialize_buffer::<Module>(buffer)
+        .map_err(|e| ChiselError::Decode(format!("Failed to deserialize the wasm binary: {}", e)))
+}
+
+fn encode_module(module: Module) -> Result<Vec<u8>, ChiselError> {
+    serialize(module)
+        .map_err(|e| ChiselError::Decode(format!("Failed to serialize the wasm binary: {}", e)))
+}
+
+/// Reads and decodes a wasm binary.
+fn read_module(path: &str) -> Result<Module, ChiselError> {
+    match read(path) {
+        Ok(buffer) => decode_module(&buffer),
+        Err(e) => Err(ChiselError::Io(format!(
+            "Failed to open wasm binary {}: {}",
+            path, e
+        ))),
     }
 }
 
+fn write_output(path: &str, output: &[u8]) -> Result<(), ChiselError> {
+    write(path, output)
+        .map_err(|e| ChiselError::Io(format!("Failed to write output file {}: {}", path, e)))
+}
+
 /// Helper that tries both translation methods in the case that a module cannot implement one of them.
-fn translate_module<T>(module: &mut Module, translator: T) -> Result<bool, &'static str>
+fn translate_module<T>(module: &mut Module, translator: T) -> Result<bool, ChiselError>
 where
     T: ModuleTranslator,
 {
     // NOTE: The module must return an Err (in the case of failure) without mutating the module or nasty stuff happens.
     if let Ok(ret) = translator.translate_inplace(module) {
         Ok(ret)
-    } else if let Ok(new_module) = translator.translate(module) {
-        if new_module.is_some() {
-            *module = new_module.unwrap();
-            Ok(true)
-        } else {
-            Ok(false)
-        }
     } else {
-        Err("Module translation failed")
+        match translator.translate(module) {
+            Ok(Some(new_module)) => {
+                *module = new_module;
+                Ok(true)
+            }
+            Ok(None) => Ok(false),
+            Err(e) => Err(ChiselError::Translation(format!(
+                "Module translation failed: {}",
+                e
+            ))),
+        }
     }
 }
 
-fn execute_module(context: &ModuleContext, module: &mut Module) -> bool {
-    let (conf_name, conf_preset) = context.fields();
-    let preset = conf_preset.clone();
+/// Helper that runs a validator and keeps its report so that the findings can be printed.
+fn validate_module<T>(
+    module: &Module,
+    validator: T,
+    report: &mut Option<ValidationReport>,
+) -> Result<bool, ChiselError>
+where
+    T: ModuleValidator,
+{
+    match validator.report(module) {
+        Ok(ret) => {
+            let is_valid = ret.is_valid();
+            *report = Some(ret);
+            Ok(is_valid)
+        }
+        Err(e) => Err(ChiselError::Validation(format!("Validation failed: {}", e))),
+    }
+}
 
-    let mut is_translator = false; // Flag representing if the module is a translator
-    let name = conf_name.as_str();
+/// Helper that constructs a module from its options.
+fn configure<T: ModuleConfig>(options: &Value) -> Result<T, ChiselError> {
+    T::with_config(options).map_err(|e| ChiselError::Config(e.to_string()))
+}
+
+/// Runs a validator module. Returns None if there is no validator with the given name.
+fn execute_validator(
+    name: &str,
+    options: &Value,
+    module: &Module,
+    report: &mut Option<ValidationReport>,
+) -> Option<Result<bool, ChiselError>> {
     let ret = match name {
         "verifyexports" => {
-            if let Ok(chisel) = VerifyExports::with_preset(&preset) {
-                Ok(chisel.validate(module).unwrap_or(false))
-            } else {
-                Err("verifyexports: Invalid preset")
-            }
+            configure::<VerifyExports>(options).and_then(|c| validate_module(module, c, report))
         }
         "verifyimports" => {
-            if let Ok(chisel) = VerifyImports::with_preset(&preset) {
-                Ok(chisel.validate(module).unwrap_or(false))
-            } else {
-                Err("verifyimports: Invalid preset")
-            }
+            configure::<VerifyImports>(options).and_then(|c| validate_module(module, c, report))
         }
-        "checkstartfunc" => {
-            // NOTE: checkstartfunc takes a bool for configuration. false by default for now.
-            let chisel = CheckStartFunc::new(false);
-            let ret = chisel.validate(module).unwrap_or(false);
-            Ok(ret)
+        "auditimports" => {
+            configure::<AuditImports>(options).and_then(|c| validate_module(module, c, report))
         }
-        "trimexports" => {
-            is_translator = true;
-
-            if let Ok(chisel) = TrimExports::with_preset(&preset) {
-                translate_module(module, chisel)
-            } else {
-                Err("trimexports: Invalid preset")
-            }
+        "checkfloats" => {
+            configure::<CheckFloats>(options).and_then(|c| validate_module(module, c, report))
         }
-        "trimstartfunc" => {
-            is_translator = true;
-            if let Ok(chisel) = TrimStartFunc::with_preset(&preset) {
-                translate_module(module, chisel)
-            } else {
-                Err("trimstartfunc: Invalid preset")
-            }
+        "checkmemory" => {
+            configure::<LimitMemory>(options).and_then(|c| validate_module(module, c, report))
         }
-        "remapimports" => {
-            is_translator = true;
-            if let Ok(chisel) = RemapImports::with_preset(&preset) {
-                translate_module(module, chisel)
-            } else {
-                Err("remapimports: Invalid preset")
-            }
+        "checklimits" => {
+            configure::<CheckLimits>(options).and_then(|c| validate_module(module, c, report))
         }
-        "remapstart" => {
-            is_translator = true;
-            if let Ok(chisel) = RemapStart::with_preset(&preset) {
-                translate_module(module, chisel)
-            } else {
-                Err("remapimports: Invalid preset")
-            }
+        "checkstartfunc" => {
+            configure::<CheckStartFunc>(options).and_then(|c| validate_module(module, c, report))
         }
-        "deployer" => {
-            is_translator = true;
-            let mut payload = Vec::new();
-            module.clone().serialize(&mut payload).unwrap(); // This should not fail, but perhaps check anyway?
+        _ => return None,
+    };
+    Some(ret)
+}
 
-            if let Ok(chisel) = Deployer::with_preset(&preset, &payload) {
-                let new_module = chisel.create().unwrap();
+/// Runs a translator or creator module on a module, given its options.
+type Translator = fn(&Value, &mut Module) -> Result<bool, ChiselError>;
+
+/// Helper that constructs and runs a translator.
+fn run_translator<T>(options: &Value, module: &mut Module) -> Result<bool, ChiselError>
+where
+    T: ModuleConfig + ModuleTranslator,
+{
+    configure::<T>(options).and_then(|c| translate_module(module, c))
+}
+
+/// Replaces the module by a deployer whose payload is the module.
+fn run_deployer(options: &Value, module: &mut Module) -> Result<bool, ChiselError> {
+    let payload = encode_module(module.clone())?;
+    match LimitedDeployer::with_config(options, &payload) {
+        Ok(chisel) => match chisel.create() {
+            Ok(new_module) => {
                 *module = new_module;
                 Ok(true)
-            } else {
-                Err("deployer: Invalid preset")
             }
-        }
-        "repack" => translate_module(module, Repack::new()),
-        _ => Err("Module Not Found"),
+            Err(e) => Err(ChiselError::Translation(e.to_string())),
+        },
+        Err(e) => Err(ChiselError::Config(e.to_string())),
+    }
+}
+
+/// Returns the translator or creator module with the given name, if any.
+fn find_translator(name: &str) -> Option<Translator> {
+    let ret: Translator = match name {
+        "trimexports" => run_translator::<TrimExports>,
+        "trimfunctions" => run_translator::<TrimFunctions>,
+        "trimstartfunc" => run_translator::<TrimStartFunc>,
+        "remapimports" => run_translator::<RemapImports>,
+        "remapstart" => run_translator::<RemapStart>,
+        "renameexports" => run_translator::<RenameExports>,
+        "limitmemory" => run_translator::<LimitMemory>,
+        "limitstack" => run_translator::<LimitStack>,
+        "metergas" => run_translator::<MeterGas>,
+        "softfloat" => run_translator::<SoftFloat>,
+        "dropsection" => run_translator::<DropSection>,
+        "deployer" => run_deployer,
+        "repack" => run_translator::<Repack>,
+        _ => return None,
     };
+    Some(ret)
+}
+
+/// Runs a translator or creator module.
+fn execute_translator(
+    name: &str,
+    options: &Value,
+    module: &mut Module,
+) -> Result<bool, ChiselError> {
+    match find_translator(name) {
+        Some(translator) => translator(options, module),
+        None => Err(ChiselError::Config(ERR_MODULE_NOT_FOUND.to_string())),
+    }
+}
+
+/// Runs a module. With `validate_only`, translators and creators are skipped, so that the module
+/// is never modified.
+fn execute_module(
+    context: &ModuleContext,
+    module: &mut Module,
+    validate_only: bool,
+) -> ModuleResult {
+    let (conf_name, options) = context.fields();
+    let name = conf_name.as_str();
+    let started = Instant::now();
 
-    let module_status_msg = if let Ok(result) = ret {
-        match (result, is_translator) {
-            (true, true) => "Translated",
-            (true, false) => "OK",
-            (false, true) => "Already OK; not translated",
-            (false, false) => "Malformed",
+    let mut kind = None;
+    let mut report: Option<ValidationReport> = None; // Findings of a validator module
+    let status = match execute_validator(name, options, module, &mut report) {
+        Some(ret) => {
+            kind = Some(ModuleKind::Validator);
+            ret.map(|valid| {
+                if valid {
+                    ModuleStatus::Ok
+                } else {
+                    ModuleStatus::Malformed
+                }
+            })
+        }
+        None if find_translator(name).is_none() => {
+            Err(ChiselError::Config(ERR_MODULE_NOT_FOUND.to_string()))
+        }
+        None => {
+            kind = Some(if name == "deployer" {
+                ModuleKind::Creator
+            } else {
+                ModuleKind::Translator
+            });
+            if validate_only {
+                Ok(ModuleStatus::Skipped)
+            } else {
+                execute_translator(name, options, module).map(|changed| {
+                    if changed {
+                        ModuleStatus::Translated
+                    } else {
+                        ModuleStatus::Unchanged
+                    }
+                })
+            }
         }
-    } else {
-        ret.unwrap_err()
     };
-    println!("\t{}: {}", name, module_status_msg);
 
-    if let Ok(result) = ret {
-        if !result && is_translator {
-            true
-        } else {
-            result
+    ModuleResult {
+        name: name.to_string(),
+        preset: options
+            .get("preset")
+            .and_then(Value::as_str)
+            .map(str::to_string),
+        kind,
+        status: *status.as_ref().unwrap_or(&ModuleStatus::Failed),
+        error: status.err(),
+        findings: report.map_or(Vec::new(), |report| report.findings().to_vec()),
+        time_ms: started.elapsed().as_secs_f64() * 1000.0,
+    }
+}
+
+/// Runs a ruleset. With `dry_run`, nothing is written, and the changes which would have been
+/// made to the module are printed instead. With `json`, the results are printed as a single JSON
+/// document once the ruleset is done, even if it failed. Returns the error which stopped the
+/// ruleset or the first failure of a module, if any.
+fn chisel_execute(context: &ChiselContext, dry_run: bool, json: bool) -> Result<(), ChiselError> {
+    let (ruleset, result) = run_ruleset(context, dry_run, json);
+
+    // The document is printed even if the ruleset failed, so that the failure is reported.
+    if json {
+        match serde_json::to_string(&ruleset) {
+            Ok(document) => println!("{}", document),
+            Err(e) => return Err(ChiselError::Io(e.to_string())),
         }
-    } else {
-        false
     }
+    result
 }
 
-fn chisel_execute(context: &ChiselContext) -> Result<bool, &'static str> {
-    if let Ok(buffer) = read(context.file()) {
-        if let Ok(mut module) = deserialize_buffer::<Module>(&buffer) {
-            let original = module.clone();
-            println!("Ruleset {}:", context.name());
-            let chisel_results = context
-                .get_modules()
-                .iter()
-                .map(|ctx| execute_module(ctx, &mut module))
-                .fold(true, |b, e| e & b);
+/// Runs a ruleset and returns its results, along with the error which stopped the ruleset or the
+/// first failure of a module, if any.
+fn run_ruleset(
+    context: &ChiselContext,
+    dry_run: bool,
+    json: bool,
+) -> (RulesetResult<'_>, Result<(), ChiselError>) {
+    let mut ruleset = RulesetResult::new(context);
+    let result = match execute_ruleset(context, dry_run, json, &mut ruleset) {
+        Ok(()) => match ruleset.modules.iter().find_map(ModuleResult::failure) {
+            Some(e) => Err(e),
+            None => Ok(()),
+        },
+        Err(e) => {
+            ruleset.error = Some(e.clone());
+            Err(e)
+        }
+    };
+    ruleset.success = result.is_ok();
+    (ruleset, result)
+}
+
+/// Runs the modules of a ruleset and writes the result, recording both in `ruleset`. Only errors
+/// stopping the ruleset are returned; failures of modules are recorded with each module.
+fn execute_ruleset<'a>(
+    context: &'a ChiselContext,
+    dry_run: bool,
+    json: bool,
+    ruleset: &mut RulesetResult<'a>,
+) -> Result<(), ChiselError> {
+    let mut module = read_module(context.file())?;
+    let original = module.clone();
+    if !json {
+        println!("Ruleset {}:", context.name());
+    }
+    for ctx in context.get_modules() {
+        let result = execute_module(ctx, &mut module, false);
+        if !json {
+            result.print();
+        }
+        ruleset.modules.push(result);
+    }
 
-            // If the module was mutated, serialize to file.
-            if original != module {
-                if let Some(path) = context.outfile() {
+    if dry_run {
+        if original == module {
+            if !json {
+                println!("Dry run: no changes");
+            }
+        } else {
+            match ModuleDiff::new(&original, &module) {
+                Ok(diff) => {
+                    if !json {
+                        println!("Dry run: changes not written");
+                        for line in diff.to_string().lines() {
+                            println!("\t{}", line);
+                        }
+                    }
+                    ruleset.changes = Some(diff);
+                }
+                Err(e) => {
+                    return Err(ChiselError::Translation(format!(
+                        "Dry run: failed to compare the modules: {}",
+                        e
+                    )))
+                }
+            }
+        }
+    } else if original != module {
+        // If the module was mutated, serialize to file.
+        let path = match context.outfile() {
+            Some(path) => {
+                if !json {
                     println!("Writing to file: {}", path);
-                    serialize_to_file(path, module).unwrap();
-                } else {
+                }
+                path
+            }
+            None => {
+                if !json {
                     println!("No output file specified; writing in place");
-                    serialize_to_file(context.file(), module).unwrap();
                 }
+                context.file()
             }
-            Ok(chisel_results)
-        } else {
-            Err(ERR_DESERIALIZE_MODULE)
+        };
+        write_output(path, &encode_module(module)?)?;
+        ruleset.output = Some(path);
+    }
+    Ok(())
+}
+
+/// Runs the validators of a ruleset against a wasm binary, which is never written. With
+/// `translate`, the translators and creators run in memory first, so that each validator checks
+/// the module as `chisel run` would leave it at that point. Returns the first failure of a module,
+/// if any.
+fn chisel_verify(context: &ChiselContext, path: &str, translate: bool) -> Result<(), ChiselError> {
+    let mut module = read_module(path)?;
+    println!("Ruleset {} ({}):", context.name(), path);
+    let mut failure = None;
+    for ctx in context.get_modules() {
+        let result = execute_module(ctx, &mut module, !translate);
+        result.print();
+        failure = failure.or_else(|| result.failure());
+    }
+    match failure {
+        Some(e) => Err(e),
+        None => Ok(()),
+    }
+}
+
+/// Summarizes the failures of several rulesets as an error of the kind of the first failure.
+fn summarize_failures(failures: Vec<ChiselError>, summary: &str) -> Result<(), ChiselError> {
+    match failures.first() {
+        Some(first) => Err(first.with_message(format!(
+            "{}: {} failing ruleset(s).",
+            summary,
+            failures.len()
+        ))),
+        None => Ok(()),
+    }
+}
+
+fn chisel_subcommand_run(args: &ArgMatches) -> Result<(), ChiselError> {
+    let config_path = args.value_of("CONFIG").unwrap_or(DEFAULT_CONFIG_PATH);
+    let ctxs = read_config(config_path)?;
+    let dry_run = args.is_present("DRY_RUN");
+    let json = args.value_of("FORMAT") == Some("json");
+
+    // Every ruleset is run, even if an earlier one failed.
+    let mut failures = Vec::new();
+    for ctx in ctxs.iter() {
+        if let Err(e) = chisel_execute(ctx, dry_run, json) {
+            eprintln!("Ruleset {}: {}", ctx.name(), e);
+            failures.push(e);
         }
-    } else {
-        Err(ERR_FAILED_OPEN_BINARY)
     }
+    summarize_failures(failures, "Run failed")
 }
 
-fn chisel_subcommand_run(args: &ArgMatches) -> i32 {
+fn chisel_subcommand_verify(args: &ArgMatches) -> Result<(), ChiselError> {
     let config_path = args.value_of("CONFIG").unwrap_or(DEFAULT_CONFIG_PATH);
+    let ctxs = read_config(config_path)?;
+    // Files given on the command line replace the file of each ruleset, and are checked as they
+    // are. The file of a ruleset is its input, which is translated in memory first.
+    let files: Option<Vec<&str>> = args.values_of("FILE").map(|values| values.collect());
 
-    if let Ok(conf) = read_to_string(config_path) {
-        match yaml_configure(&conf) {
-            Ok(ctxs) => {
-                let result_final = ctxs.iter().fold(0, |acc, ctx| match chisel_execute(&ctx) {
-                    Ok(result) => acc + if result { 0 } else { 1 }, // Add the number of module failures to exit code.
-                    Err(msg) => err_exit(msg),
-                });
-                return result_final;
+    let mut failures = Vec::new();
+    for ctx in ctxs.iter() {
+        let paths = match &files {
+            Some(files) => files.clone(),
+            None => vec![ctx.file().as_str()],
+        };
+        for path in paths {
+            if let Err(e) = chisel_verify(ctx, path, files.is_none()) {
+                eprintln!("Ruleset {} ({}): {}", ctx.name(), path, e);
+                failures.push(e);
             }
-            Err(msg) => err_exit(msg),
+        }
+    }
+
+    if failures.is_empty() {
+        println!("Verification passed.");
+    }
+    summarize_failures(failures, "Verification failed")
+}
+
+/// Builds the options of a module from the command line. Values are parsed as YAML, so that
+/// booleans, numbers and lists can be given.
+fn apply_options(args: &ArgMatches) -> Result<Value, ChiselError> {
+    let mut options = Mapping::new();
+    if let Some(preset) = args.value_of("PRESET") {
+        options.insert(
+            Value::String("preset".to_string()),
+            Value::String(preset.to_string()),
+        );
+    }
+    for option in args.values_of("OPTION").into_iter().flatten() {
+        let mut parts = option.splitn(2, '=');
+        let (key, value) = match (parts.next(), parts.next()) {
+            (Some(key), Some(value)) if !key.is_empty() => (key, value),
+            _ => return Err(ChiselError::Config(ERR_INVALID_OPTION.to_string())),
         };
+        let value = serde_yaml::from_str(value).unwrap_or(Value::String(value.to_string()));
+        options.insert(Value::String(key.to_string()), value);
+    }
+    Ok(Value::Mapping(options))
+}
+
+fn chisel_subcommand_apply(args: &ArgMatches) -> Result<(), ChiselError> {
+    // MODULE is a required argument.
+    let name = args.value_of("MODULE").unwrap_or_default();
+    let options = apply_options(args)?;
+
+    // The input is read from standard input if no file is given, or if it is "-".
+    let mut module = match args.value_of("INPUT") {
+        Some(path) if path != "-" => read_module(path)?,
+        _ => {
+            let mut buffer = Vec::new();
+            if let Err(e) = stdin().read_to_end(&mut buffer) {
+                return Err(ChiselError::Io(format!(
+                    "Failed to read standard input: {}",
+                    e
+                )));
+            }
+            decode_module(&buffer)?
+        }
+    };
+
+    // Messages go to standard error, so that the module can be piped.
+    let mut report: Option<ValidationReport> = None;
+    let ret = match execute_validator(name, &options, &module, &mut report) {
+        Some(Ok(true)) => Ok(()),
+        Some(Ok(false)) => Err(ChiselError::Validation("Malformed".to_string())),
+        Some(Err(e)) => Err(e),
+        None => execute_translator(name, &options, &mut module).map(|_| ()),
+    };
+    if let Some(report) = report {
+        for finding in report.findings() {
+            eprintln!("{}", finding);
+        }
+    }
+    ret.map_err(|e| e.with_message(format!("{}: {}", name, e)))?;
+
+    // Validators pass the module through unchanged.
+    let output = encode_module(module)?;
+    match args.value_of("OUTPUT") {
+        Some(path) if path != "-" => write_output(path, &output),
+        _ => stdout()
+            .write_all(&output)
+            .map_err(|e| ChiselError::Io(format!("Failed to write standard output: {}", e))),
+    }
+}
+
+fn chisel_subcommand_callgraph(args: &ArgMatches) -> Result<(), ChiselError> {
+    // FILE is a required argument.
+    let module = read_module(args.value_of("FILE").unwrap_or_default())?;
+
+    // The binary decoded fine, so a graph which cannot be built means it is malformed.
+    let graph = CallGraph::new(&module).map_err(|e| ChiselError::Validation(e.to_string()))?;
+    let rendered = match args.value_of("FORMAT") {
+        Some("json") => graph
+            .to_json()
+            .map_err(|e| ChiselError::Io(e.to_string()))?,
+        _ => graph.to_dot(),
+    };
+
+    if let Some(output) = args.value_of("OUTPUT") {
+        write_output(output, rendered.as_bytes())
     } else {
-        err_exit(ERR_FAILED_OPEN_CONFIG);
+        println!("{}", rendered.trim_end());
+        Ok(())
     }
 }
 
-pub fn main() {
-    let cli_matches = App::new("chisel")
+/// Returns the command line interface of the binary.
+fn app<'a, 'b>() -> App<'a, 'b> {
+    App::new("chisel")
         .version(crate_version!())
         .about(crate_description!())
         .subcommand(
@@ -352,12 +798,366 @@ pub fn main() {
                         .help("Sets a custom configuration file")
                         .value_name("CONF_FILE")
                         .takes_value(true),
+                )
+                .arg(
+                    Arg::with_name("DRY_RUN")
+                        .long("dry-run")
+                        .help("Prints the changes to each module instead of writing any file"),
+                )
+                .arg(
+                    Arg::with_name("FORMAT")
+                        .short("f")
+                        .long("format")
+                        .help("Sets the output format, with one JSON document per ruleset")
+                        .possible_values(&["text", "json"])
+                        .default_value("text")
+                        .takes_value(true),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("verify")
+                .about("Runs the validator modules of the configuration, without writing any file.")
+                .arg(
+                    Arg::with_name("CONFIG")
+                        .short("c")
+                        .long("config")
+                        .help("Sets a custom configuration file")
+                        .value_name("CONF_FILE")
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::with_name("FILE")
+                        .help(
+                            "Sets the wasm binaries to verify, instead of the file of each ruleset",
+                        )
+                        .multiple(true)
+                        .index(1),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("apply")
+                .about("Runs a single module on a wasm binary, without a configuration file.")
+                .arg(
+                    Arg::with_name("MODULE")
+                        .help("Sets the module to run, e.g. remapimports")
+                        .required(true)
+                        .index(1),
+                )
+                .arg(
+                    Arg::with_name("INPUT")
+                        .help("Sets the wasm binary to read, or - for standard input (default)")
+                        .index(2),
+                )
+                .arg(
+                    Arg::with_name("PRESET")
+                        .short("p")
+                        .long("preset")
+                        .help("Sets the preset of the module")
+                        .value_name("PRESET")
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::with_name("OPTION")
+                        .short("O")
+                        .long("option")
+                        .help("Sets an option of the module, with a YAML value")
+                        .value_name("KEY=VALUE")
+                        .takes_value(true)
+                        .multiple(true)
+                        .number_of_values(1),
+                )
+                .arg(
+                    Arg::with_name("OUTPUT")
+                        .short("o")
+                        .long("output")
+                        .help("Sets the file to write, or - for standard output (default)")
+                        .value_name("OUT_FILE")
+                        .takes_value(true),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("callgraph")
+                .about("Renders the call graph of a wasm binary.")
+                .arg(
+                    Arg::with_name("FILE")
+                        .help("Sets the wasm binary to render")
+                        .required(true)
+                        .index(1),
+                )
+                .arg(
+                    Arg::with_name("FORMAT")
+                        .short("f")
+                        .long("format")
+                        .help("Sets the output format")
+                        .possible_values(&["dot", "json"])
+                        .default_value("dot")
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::with_name("OUTPUT")
+                        .short("o")
+                        .long("output")
+                        .help("Writes the graph to a file instead of standard output")
+                        .value_name("OUT_FILE")
+                        .takes_value(true),
                 ),
         )
-        .get_matches();
+}
+
+pub fn main() {
+    let cli_matches = app().get_matches_safe();
+
+    // Usage errors are configuration errors, while help and version requests succeed.
+    let cli_matches = match cli_matches {
+        Ok(matches) => matches,
+        Err(e) if e.use_stderr() => {
+            eprintln!("{}", e.message);
+            process::exit(ChiselError::Config(e.message).exit_code());
+        }
+        Err(e) => e.exit(),
+    };
 
-    match cli_matches.subcommand() {
-        ("run", Some(subcmd_matches)) => process::exit(chisel_subcommand_run(subcmd_matches)),
-        _ => err_exit(ERR_NO_SUBCOMMAND),
+    let ret = match cli_matches.subcommand() {
+        ("run", Some(subcmd_matches)) => chisel_subcommand_run(subcmd_matches),
+        ("verify", Some(subcmd_matches)) => chisel_subcommand_verify(subcmd_matches),
+        ("apply", Some(subcmd_matches)) => chisel_subcommand_apply(subcmd_matches),
+        ("callgraph", Some(subcmd_matches)) => chisel_subcommand_callgraph(subcmd_matches),
+        _ => Err(ChiselError::Config(ERR_NO_SUBCOMMAND.to_string())),
     };
+    if let Err(e) = ret {
+        eprintln!("{}: {}", crate_name!(), e);
+        process::exit(e.exit_code());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn apply_matches(args: &[&str]) -> ArgMatches<'static> {
+        let matches = app()
+            .get_matches_from_safe([&["chisel", "apply"], args].concat())
+            .unwrap();
+        matches.subcommand_matches("apply").unwrap().clone()
+    }
+
+    #[test]
+    fn from_ruleset_mismatched_types() {
+        let config = serde_yaml::from_str("[foo, bar]").unwrap();
+        match ChiselContext::from_ruleset(&config) {
+            Err(ChiselError::Config(message)) => assert_eq!(message, ERR_CONFIG_INVALID),
+            _ => panic!("expected a configuration error"),
+        }
+
+        let config = serde_yaml::from_str("{ruleset: [file.wasm]}").unwrap();
+        match ChiselContext::from_ruleset(&config) {
+            Err(ChiselError::Config(message)) => assert_eq!(message, ERR_RULESET_TYPE_MISMATCH),
+            _ => panic!("expected a configuration error"),
+        }
+
+        let config = serde_yaml::from_str("{ruleset: {file: 42}}").unwrap();
+        assert!(ChiselContext::from_ruleset(&config).is_err());
+
+        let config =
+            serde_yaml::from_str("{ruleset: {file: in.wasm, output: out.wasm, trimexports: {}}}")
+                .unwrap();
+        let contexts = ChiselContext::from_ruleset(&config).unwrap();
+        assert_eq!(contexts[0].name(), "ruleset");
+        assert_eq!(contexts[0].outfile().as_deref(), Some("out.wasm"));
+        assert_eq!(contexts[0].get_modules().len(), 1);
+    }
+
+    #[test]
+    fn apply_options_parsed() {
+        let matches = apply_matches(&[
+            "remapimports",
+            "-p",
+            "ewasm",
+            "-O",
+            "custom_section=name",
+            "-O",
+            "limit=1024",
+            "-O",
+            "list=[a, b]",
+            "-O",
+            "eq=a=b",
+        ]);
+        let expected: Value = serde_yaml::from_str(
+            "{preset: ewasm, custom_section: name, limit: 1024, list: [a, b], eq: a=b}",
+        )
+        .unwrap();
+        assert_eq!(apply_options(&matches).unwrap(), expected);
+
+        // Values which are not valid YAML are kept as strings.
+        let matches = apply_matches(&["remapimports", "-O", "name=[a"]);
+        let expected: Value = serde_yaml::from_str("{name: '[a'}").unwrap();
+        assert_eq!(apply_options(&matches).unwrap(), expected);
+    }
+
+    #[test]
+    fn apply_options_invalid() {
+        for option in &["novalue", "=value"] {
+            let matches = apply_matches(&["remapimports", "-O", option]);
+            assert_eq!(
+                apply_options(&matches),
+                Err(ChiselError::Config(ERR_INVALID_OPTION.to_string()))
+            );
+        }
+    }
+
+    struct FailingValidator;
+
+    impl ModuleValidator for FailingValidator {
+        fn report(&self, _module: &Module) -> Result<ValidationReport, ModuleError> {
+            Err(ModuleError::Custom("Broken module".to_string()))
+        }
+    }
+
+    /// Writes a module importing `env.ethereum_finish` to a temporary file, and returns its path
+    /// and contents.
+    fn write_input(name: &str) -> (String, Vec<u8>) {
+        use parity_wasm::elements::{
+            External, FunctionType, ImportEntry, ImportSection, Section, Type, TypeSection,
+            ValueType,
+        };
+
+        let module = Module::new(vec![
+            Section::Type(TypeSection::with_types(vec![Type::Function(
+                FunctionType::new(vec![ValueType::I32, ValueType::I32], None),
+            )])),
+            Section::Import(ImportSection::with_entries(vec![ImportEntry::new(
+                "env".to_string(),
+                "ethereum_finish".to_string(),
+                External::Function(0),
+            )])),
+        ]);
+        let path = std::env::temp_dir().join(name);
+        let path = path.to_str().unwrap().to_string();
+        let input = encode_module(module).unwrap();
+        write_output(&path, &input).unwrap();
+        (path, input)
+    }
+
+    /// Returns the rulesets of a configuration with one ruleset reading the given file.
+    fn ruleset_for(path: &str, modules: &str) -> Vec<ChiselContext> {
+        let config =
+            serde_yaml::from_str(&format!("{{ruleset: {{file: '{}', {}}}}}", path, modules))
+                .unwrap();
+        ChiselContext::from_ruleset(&config).unwrap()
+    }
+
+    #[test]
+    fn verify_translated_input() {
+        let (path, input) = write_input("chisel_verify_translated_input.wasm");
+        let contexts = ruleset_for(
+            &path,
+            "remapimports: {preset: ewasm}, verifyimports: {preset: ewasm}",
+        );
+
+        // The validator sees the imports as remapped by the translator before it.
+        assert_eq!(chisel_verify(&contexts[0], &path, true), Ok(()));
+        // Checked as it is, the input has an unlisted import.
+        assert!(chisel_verify(&contexts[0], &path, false).is_err());
+        // The input is never written.
+        assert_eq!(read(&path).unwrap(), input);
+    }
+
+    #[test]
+    fn dry_run() {
+        let (path, input) = write_input("chisel_dry_run.wasm");
+        let contexts = ruleset_for(&path, "remapimports: {preset: ewasm}");
+        let mut ruleset = RulesetResult::new(&contexts[0]);
+        assert_eq!(
+            execute_ruleset(&contexts[0], true, true, &mut ruleset),
+            Ok(())
+        );
+        assert_eq!(read(&path).unwrap(), input);
+        assert_eq!(ruleset.output, None);
+        assert_eq!(ruleset.modules[0].status, ModuleStatus::Translated);
+        let changes = ruleset.changes.unwrap().to_string();
+        assert!(changes.contains("env.ethereum_finish"), "{}", changes);
+    }
+
+    /// Returns the keys of a JSON object, sorted.
+    fn keys(value: &serde_json::Value) -> Vec<&str> {
+        let mut ret: Vec<&str> = value
+            .as_object()
+            .unwrap()
+            .keys()
+            .map(String::as_str)
+            .collect();
+        ret.sort();
+        ret
+    }
+
+    #[test]
+    fn ruleset_json() {
+        let (path, _) = write_input("chisel_ruleset_json.wasm");
+        let contexts = ruleset_for(
+            &path,
+            "remapimports: {preset: ewasm}, verifyimports: {preset: ewasm}, checkfloats: {}",
+        );
+        let (ruleset, result) = run_ruleset(&contexts[0], true, true);
+        assert_eq!(result, Ok(()));
+        let document = serde_json::to_value(&ruleset).unwrap();
+        assert_eq!(
+            keys(&document),
+            vec!["changes", "error", "input", "modules", "output", "ruleset", "success"]
+        );
+        assert_eq!(document["ruleset"], "ruleset");
+        assert_eq!(document["input"], path.as_str());
+        assert_eq!(document["success"], true);
+        assert_eq!(document["error"], serde_json::Value::Null);
+        // Nothing is written in a dry run.
+        assert_eq!(document["output"], serde_json::Value::Null);
+        assert!(document["changes"].is_object());
+
+        let module = &document["modules"][0];
+        assert_eq!(
+            keys(module),
+            vec!["error", "findings", "kind", "name", "preset", "status", "time_ms"]
+        );
+        assert_eq!(module["name"], "remapimports");
+        assert_eq!(module["preset"], "ewasm");
+        assert_eq!(module["kind"], "translator");
+        assert_eq!(module["status"], "translated");
+        assert_eq!(module["error"], serde_json::Value::Null);
+        assert_eq!(document["modules"][1]["kind"], "validator");
+        assert_eq!(document["modules"][1]["status"], "ok");
+        assert_eq!(document["modules"][2]["preset"], serde_json::Value::Null);
+
+        // A failing module fails the ruleset, which is otherwise run to the end.
+        let contexts = ruleset_for(&path, "verifyimports: {preset: ewasm}, nosuchmodule: {}");
+        let (ruleset, result) = run_ruleset(&contexts[0], true, true);
+        assert!(result.is_err());
+        let document = serde_json::to_value(&ruleset).unwrap();
+        assert_eq!(document["success"], false);
+        assert_eq!(document["error"], serde_json::Value::Null);
+        assert_eq!(document["modules"][0]["status"], "malformed");
+        assert_eq!(document["modules"][0]["findings"][0]["severity"], "error");
+        assert_eq!(document["modules"][1]["status"], "failed");
+        assert_eq!(document["modules"][1]["kind"], serde_json::Value::Null);
+        assert_eq!(document["modules"][1]["error"], ERR_MODULE_NOT_FOUND);
+
+        // An input which cannot be read stops the ruleset.
+        let contexts = ruleset_for("/nonexistent/input.wasm", "verifyimports: {preset: ewasm}");
+        let (ruleset, result) = run_ruleset(&contexts[0], false, true);
+        assert_eq!(result.as_ref().map_err(ChiselError::exit_code), Err(4));
+        let document = serde_json::to_value(&ruleset).unwrap();
+        assert_eq!(document["success"], false);
+        assert_eq!(document["error"], result.unwrap_err().message());
+        assert_eq!(document["modules"], serde_json::json!([]));
+    }
+
+    #[test]
+    fn validator_error_reported() {
+        let mut report = None;
+        assert_eq!(
+            validate_module(&Module::default(), FailingValidator, &mut report),
+            Err(ChiselError::Validation(
+                "Validation failed: Broken module".to_string()
+            ))
+        );
+        assert!(report.is_none());
+    }
 }
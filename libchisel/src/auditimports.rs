@@ -0,0 +1,244 @@
+use super::{
+    depgraph::{DepGraph, DepGraphBuilder},
+    imports::ImportList,
+    report::{Finding, FindingKind, ValidationReport},
+    utils::{invalid_preset, parse_config},
+    ModuleConfig, ModuleError, ModulePreset, ModuleValidator,
+};
+use parity_wasm::elements::{External, ImportEntry, Internal, Module};
+use serde_yaml::Value;
+
+/// Struct on which ModuleValidator is implemented. Reports the imported host functions each
+/// exported function may transitively call, and fails if any of them is forbidden.
+pub struct AuditImports<'a> {
+    /// Imports which must not be reachable. Only the namespace and field are compared.
+    forbidden: ImportList<'a>,
+    /// Exports to audit. All exported functions are audited if empty.
+    exports: Vec<String>,
+}
+
+impl<'a> AuditImports<'a> {
+    pub fn new(forbidden: ImportList<'a>, exports: Vec<String>) -> Self {
+        AuditImports { forbidden, exports }
+    }
+
+    fn is_forbidden(&self, import: &ImportEntry) -> bool {
+        self.forbidden
+            .entries()
+            .iter()
+            .any(|entry| entry.namespace() == import.module() && entry.field() == import.field())
+    }
+
+    /// Returns the audited exports with their function index, in the order of the export section.
+    fn audited_exports<'m>(&self, module: &'m Module) -> Vec<(&'m str, u32)> {
+        match module.export_section() {
+            Some(section) => section
+                .entries()
+                .iter()
+                .filter_map(|entry| match entry.internal() {
+                    Internal::Function(idx) => Some((entry.field(), *idx)),
+                    _ => None,
+                })
+                .filter(|(field, _)| {
+                    self.exports.is_empty() || self.exports.iter().any(|name| name == field)
+                })
+                .collect(),
+            None => Vec::new(),
+        }
+    }
+}
+
+impl<'a> ModulePreset for AuditImports<'a> {
+    fn with_preset(preset: &str) -> Result<Self, ()> {
+        match preset {
+            // Debugging functions must not be reachable from production contracts.
+            "ewasm" => Ok(AuditImports::new(
+                ImportList::with_preset("debug")?,
+                Vec::new(),
+            )),
+            _ => Err(()),
+        }
+    }
+}
+
+/// Options accepted by `AuditImports::with_config`. The forbidden imports of an interface file are
+/// added to those of the preset. Without either, reachable imports are only reported.
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Config {
+    preset: Option<String>,
+    /// Path to an interface definition file listing forbidden imports.
+    forbidden_interface: Option<String>,
+    exports: Option<Vec<String>>,
+}
+
+impl<'a> ModuleConfig for AuditImports<'a> {
+    fn with_config(config: &Value) -> Result<Self, ModuleError> {
+        let config: Config = parse_config(config)?;
+
+        let mut ret = match config.preset {
+            Some(preset) => {
+                AuditImports::with_preset(&preset).map_err(|_| invalid_preset(&preset))?
+            }
+            None => AuditImports::new(ImportList::new(), Vec::new()),
+        };
+        if let Some(path) = config.forbidden_interface {
+            ret.forbidden.concatenate(ImportList::from_file(path)?);
+        }
+        if let Some(exports) = config.exports {
+            ret.exports = exports;
+        }
+        Ok(ret)
+    }
+}
+
+impl<'a> ModuleValidator for AuditImports<'a> {
+    fn report(&self, module: &Module) -> Result<ValidationReport, ModuleError> {
+        let mut report = ValidationReport::new();
+
+        let imports: Vec<&ImportEntry> = match module.import_section() {
+            Some(section) => section
+                .entries()
+                .iter()
+                .filter(|entry| matches!(entry.external(), External::Function(_)))
+                .collect(),
+            None => Vec::new(),
+        };
+
+        let exports = self.audited_exports(module);
+        for name in self.exports.iter() {
+            if !exports.iter().any(|(field, _)| field == name) {
+                report.push(Finding::error(FindingKind::MissingExport).with_field(name));
+            }
+        }
+
+        for (field, idx) in exports {
+            // A module without code can only export imported functions.
+            let reachable = match DepGraph::build(module, idx) {
+                Ok(graph) => graph.reachable(),
+                Err(_) if module.code_section().is_none() => vec![idx],
+                Err(_) => {
+                    return Err(ModuleError::Custom(format!(
+                        "Failed to build the call graph of export {}",
+                        field
+                    )))
+                }
+            };
+            for import in reachable
+                .iter()
+                .filter_map(|idx| imports.get(*idx as usize))
+            {
+                let finding = if self.is_forbidden(import) {
+                    Finding::error(FindingKind::ForbiddenImport)
+                } else {
+                    Finding::info(FindingKind::ReachableImport)
+                };
+                report.push(
+                    finding
+                        .with_namespace(import.module())
+                        .with_field(import.field())
+                        .with_location(format!("export {}", field)),
+                );
+            }
+        }
+
+        Ok(report)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::report::Severity;
+    use parity_wasm::elements::deserialize_buffer;
+    use rustc_hex::FromHex;
+
+    // wast:
+    // (module
+    //   (import "ethereum" "useGas" (func $useGas (param i64)))
+    //   (import "debug" "print32" (func $print (param i32)))
+    //   (import "ethereum" "finish" (func $finish (param i32 i32)))
+    //   (memory 1)
+    //   (export "main" (func $main))
+    //   (export "debug_main" (func $debug_main))
+    //   (export "memory" (memory 0))
+    //   (func $main (call $helper) (call $finish (i32.const 0) (i32.const 0)))
+    //   (func $helper (call $useGas (i64.const 1)))
+    //   (func $debug_main (call $helper) (call $print (i32.const 0)))
+    // )
+    fn sample_module() -> Module {
+        let wasm: Vec<u8> = FromHex::from_hex(
+            "
+            0061736d0100000001110460017e0060017f0060027f7f00600000023503
+            08657468657265756d067573654761730000056465627567077072696e74
+            3332000108657468657265756d0666696e69736800020304030303030503
+            010001071e03046d61696e00030a64656275675f6d61696e0005066d656d
+            6f727902000a1c030a0010044100410010020b0600420110000b08001004
+            410010010b0039046e616d65013206000675736547617301057072696e74
+            020666696e69736803046d61696e040668656c706572050a64656275675f
+            6d61696e
+        ",
+        )
+        .unwrap();
+        deserialize_buffer::<Module>(&wasm).unwrap()
+    }
+
+    fn describe(report: &ValidationReport) -> Vec<String> {
+        report.findings().iter().map(|f| f.to_string()).collect()
+    }
+
+    #[test]
+    fn forbidden_import_reachable() {
+        let module = sample_module();
+        let audit = AuditImports::with_preset("ewasm").unwrap();
+        let report = audit.report(&module).unwrap();
+        assert_eq!(
+            describe(&report),
+            vec![
+                "info: reachable import ethereum.useGas in export main",
+                "info: reachable import ethereum.finish in export main",
+                "info: reachable import ethereum.useGas in export debug_main",
+                "error: forbidden import debug.print32 in export debug_main",
+            ]
+        );
+        assert!(!audit.validate(&module).unwrap());
+    }
+
+    #[test]
+    fn audit_selected_exports() {
+        let module = sample_module();
+        let config = serde_yaml::from_str("{preset: ewasm, exports: [main]}").unwrap();
+        let report = AuditImports::with_config(&config)
+            .unwrap()
+            .report(&module)
+            .unwrap();
+        assert!(report.is_valid());
+        assert_eq!(report.findings().len(), 2);
+        assert!(report
+            .findings()
+            .iter()
+            .all(|f| f.severity == Severity::Info));
+
+        let config = serde_yaml::from_str("{exports: [foo]}").unwrap();
+        let report = AuditImports::with_config(&config)
+            .unwrap()
+            .report(&module)
+            .unwrap();
+        assert_eq!(describe(&report), vec!["error: missing export foo"]);
+    }
+
+    #[test]
+    fn with_config() {
+        let module = sample_module();
+        let config = serde_yaml::from_str("{}").unwrap();
+        let audit = AuditImports::with_config(&config).unwrap();
+        assert_eq!(audit.report(&module).unwrap().findings().len(), 4);
+        assert!(audit.validate(&module).unwrap());
+
+        let config = serde_yaml::from_str("{preset: foo}").unwrap();
+        assert!(AuditImports::with_config(&config).is_err());
+
+        let config = serde_yaml::from_str("{forbidden: debug}").unwrap();
+        assert!(AuditImports::with_config(&config).is_err());
+    }
+}
@@ -0,0 +1,296 @@
+//! Renders the call graph of a module as Graphviz DOT or JSON.
+
+use std::collections::HashMap;
+
+use super::depgraph::{entry_points, DepGraph, DepGraphBuilder};
+use super::utils::function_names;
+use super::ModuleError;
+use parity_wasm::elements::{External, ImportCountType, Internal, Module};
+
+/// A function in the call graph.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct CallGraphNode {
+    pub index: u32,
+    /// Name used to display the function.
+    pub label: String,
+    /// `namespace.field` of an imported host function.
+    pub import: Option<String>,
+    /// Names under which the function is exported.
+    pub exports: Vec<String>,
+    /// Whether the function is an entry point of the module.
+    pub entry_point: bool,
+}
+
+/// A call from one function to another.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct CallGraphEdge {
+    pub caller: u32,
+    pub callee: u32,
+}
+
+/// Call graph of the functions reachable from the entry points of a module.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct CallGraph {
+    pub nodes: Vec<CallGraphNode>,
+    pub edges: Vec<CallGraphEdge>,
+}
+
+impl CallGraph {
+    pub fn new(module: &Module) -> Result<Self, ModuleError> {
+        let entries = entry_points(module);
+        let (reachable, edges) = if module.code_section().is_none() {
+            // A module without code can only export imported functions, which call nothing.
+            let imports_len = module.import_count(ImportCountType::Function) as u32;
+            if let Some(idx) = entries.iter().find(|idx| **idx >= imports_len) {
+                return Err(ModuleError::Custom(format!("Invalid function: {}", idx)));
+            }
+            (entries.clone(), Vec::new())
+        } else {
+            let graph = DepGraph::build_from_entry_points(module)
+                .map_err(|_| ModuleError::Custom("Failed to build the call graph".to_string()))?;
+            (graph.reachable(), graph.edges())
+        };
+
+        let imports: Vec<String> = match module.import_section() {
+            Some(section) => section
+                .entries()
+                .iter()
+                .filter(|entry| matches!(entry.external(), External::Function(_)))
+                .map(|entry| format!("{}.{}", entry.module(), entry.field()))
+                .collect(),
+            None => Vec::new(),
+        };
+
+        let mut exports: HashMap<u32, Vec<String>> = HashMap::new();
+        if let Some(section) = module.export_section() {
+            for entry in section.entries() {
+                if let Internal::Function(idx) = entry.internal() {
+                    exports
+                        .entry(*idx)
+                        .or_default()
+                        .push(entry.field().to_string());
+                }
+            }
+        }
+
+        let names = function_names(module);
+        let nodes = reachable
+            .into_iter()
+            .map(|idx| {
+                let import = imports.get(idx as usize).cloned();
+                let exports = exports.remove(&idx).unwrap_or_default();
+                // Host functions are best identified by their import name.
+                let label = import
+                    .clone()
+                    .or_else(|| names.get(&idx).cloned())
+                    .or_else(|| exports.first().cloned())
+                    .unwrap_or_else(|| format!("function {}", idx));
+                CallGraphNode {
+                    index: idx,
+                    label,
+                    import,
+                    exports,
+                    entry_point: entries.contains(&idx),
+                }
+            })
+            .collect();
+
+        let edges = edges
+            .into_iter()
+            .map(|(caller, callee)| CallGraphEdge { caller, callee })
+            .collect();
+
+        Ok(CallGraph { nodes, edges })
+    }
+
+    /// Renders the graph in the Graphviz DOT language. Imported functions are drawn as filled
+    /// boxes and entry points with a double outline.
+    pub fn to_dot(&self) -> String {
+        let mut ret = String::from("digraph callgraph {\n");
+        for node in self.nodes.iter() {
+            let mut attributes = format!("label=\"{}\"", escape_dot(&node.label));
+            if node.import.is_some() {
+                attributes.push_str(", shape=box, style=filled, fillcolor=lightgrey");
+            }
+            if node.entry_point {
+                attributes.push_str(", peripheries=2");
+            }
+            ret.push_str(&format!("    f{} [{}];\n", node.index, attributes));
+        }
+        for edge in self.edges.iter() {
+            ret.push_str(&format!("    f{} -> f{};\n", edge.caller, edge.callee));
+        }
+        ret.push_str("}\n");
+        ret
+    }
+
+    pub fn to_json(&self) -> Result<String, ModuleError> {
+        serde_json::to_string_pretty(self).map_err(|e| ModuleError::Custom(e.to_string()))
+    }
+}
+
+fn escape_dot(label: &str) -> String {
+    label.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use parity_wasm::builder;
+    use parity_wasm::elements::deserialize_buffer;
+    use rustc_hex::FromHex;
+
+    // wast:
+    // (module
+    //   (import "ethereum" "useGas" (func $useGas (param i64)))
+    //   (import "debug" "print32" (func $print (param i32)))
+    //   (memory 1)
+    //   (export "main" (func $main))
+    //   (export "memory" (memory 0))
+    //   (func $main (call $helper) (call $useGas (i64.const 1)))
+    //   (func $helper)
+    //   (func $dead (call $print (i32.const 0)))
+    // )
+    fn sample_module() -> Module {
+        let wasm: Vec<u8> = FromHex::from_hex(
+            "
+            0061736d01000000010c0360017e0060017f006000000223020865746865
+            7265756d067573654761730000056465627567077072696e743332000103
+            04030202020503010001071102046d61696e0002066d656d6f727902000a
+            140308001003420110000b02000b0600410010010b002b046e616d650124
+            05000675736547617301057072696e7402046d61696e030668656c706572
+            040464656164
+        ",
+        )
+        .unwrap();
+        deserialize_buffer::<Module>(&wasm).unwrap()
+    }
+
+    #[test]
+    fn nodes_and_edges() {
+        let graph = CallGraph::new(&sample_module()).unwrap();
+
+        assert_eq!(
+            graph.nodes,
+            vec![
+                CallGraphNode {
+                    index: 0,
+                    label: "ethereum.useGas".to_string(),
+                    import: Some("ethereum.useGas".to_string()),
+                    exports: vec![],
+                    entry_point: false,
+                },
+                CallGraphNode {
+                    index: 2,
+                    label: "main".to_string(),
+                    import: None,
+                    exports: vec!["main".to_string()],
+                    entry_point: true,
+                },
+                CallGraphNode {
+                    index: 3,
+                    label: "helper".to_string(),
+                    import: None,
+                    exports: vec![],
+                    entry_point: false,
+                },
+            ]
+        );
+        assert_eq!(
+            graph.edges,
+            vec![
+                CallGraphEdge {
+                    caller: 2,
+                    callee: 0
+                },
+                CallGraphEdge {
+                    caller: 2,
+                    callee: 3
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn render_dot() {
+        let graph = CallGraph::new(&sample_module()).unwrap();
+        assert_eq!(
+            graph.to_dot(),
+            "digraph callgraph {
+    f0 [label=\"ethereum.useGas\", shape=box, style=filled, fillcolor=lightgrey];
+    f2 [label=\"main\", peripheries=2];
+    f3 [label=\"helper\"];
+    f2 -> f0;
+    f2 -> f3;
+}
+"
+        );
+    }
+
+    #[test]
+    fn render_json() {
+        let graph = CallGraph::new(&sample_module()).unwrap();
+        let json: serde_json::Value = serde_json::from_str(&graph.to_json().unwrap()).unwrap();
+        assert_eq!(json["nodes"][0]["import"], "ethereum.useGas");
+        assert_eq!(json["nodes"][1]["exports"][0], "main");
+        assert_eq!(json["nodes"][2]["import"], serde_json::Value::Null);
+        assert_eq!(json["edges"][1]["callee"], 3);
+    }
+
+    #[test]
+    fn label_fallback() {
+        // Without a names section, functions are labelled by export name or index.
+        let mut module = sample_module();
+        module.clear_custom_section("name");
+        let graph = CallGraph::new(&module).unwrap();
+        let labels: Vec<&str> = graph.nodes.iter().map(|n| n.label.as_str()).collect();
+        assert_eq!(labels, vec!["ethereum.useGas", "main", "function 3"]);
+    }
+
+    #[test]
+    fn import_only() {
+        // wast:
+        // (module
+        //   (import "ethereum" "finish" (func $finish (param i32 i32)))
+        //   (import "ethereum" "revert" (func $revert (param i32 i32)))
+        //   (export "main" (func $finish))
+        // )
+        let wasm: Vec<u8> = FromHex::from_hex(
+            "
+            0061736d0100000001060160027f7f0002250208657468657265756d0666
+            696e697368000008657468657265756d067265766572740000070801046d
+            61696e00000018046e616d65011102000666696e69736801067265766572
+            74
+        ",
+        )
+        .unwrap();
+        let module = deserialize_buffer::<Module>(&wasm).unwrap();
+        let graph = CallGraph::new(&module).unwrap();
+        assert_eq!(
+            graph.nodes,
+            vec![CallGraphNode {
+                index: 0,
+                label: "ethereum.finish".to_string(),
+                import: Some("ethereum.finish".to_string()),
+                exports: vec!["main".to_string()],
+                entry_point: true,
+            }]
+        );
+        assert!(graph.edges.is_empty());
+
+        // Without code, only imported functions can be exported.
+        let module = builder::module()
+            .export()
+            .field("main")
+            .internal()
+            .func(2)
+            .build()
+            .build();
+        assert!(CallGraph::new(&module).is_err());
+    }
+
+    #[test]
+    fn escape_label() {
+        assert_eq!(escape_dot("a\"b\\c"), "a\\\"b\\\\c");
+    }
+}
@@ -0,0 +1,285 @@
+use super::{
+    report::{Finding, FindingKind, ValidationReport},
+    utils::{fmt_func_type, fmt_global_type, function_type_indices, invalid_preset, parse_config},
+    ModuleConfig, ModuleError, ModulePreset, ModuleValidator,
+};
+use parity_wasm::elements::{
+    BlockType, External, ImportCountType, Instruction, Module, Type, ValueType,
+};
+use serde_yaml::Value;
+
+/// Struct on which ModuleValidator is implemented. Reports every floating-point type and
+/// instruction in a module.
+pub struct CheckFloats {
+    /// Whether floating-point usage invalidates the module. Otherwise it is only warned about.
+    forbid: bool,
+}
+
+impl CheckFloats {
+    pub fn new(forbid: bool) -> Self {
+        CheckFloats { forbid }
+    }
+
+    fn finding(&self, kind: FindingKind) -> Finding {
+        if self.forbid {
+            Finding::error(kind)
+        } else {
+            Finding::warning(kind)
+        }
+    }
+}
+
+impl ModulePreset for CheckFloats {
+    fn with_preset(preset: &str) -> Result<Self, ()> {
+        match preset {
+            // Floating-point results are not deterministic across platforms.
+            "ewasm" => Ok(CheckFloats::new(true)),
+            _ => Err(()),
+        }
+    }
+}
+
+/// Options accepted by `CheckFloats::with_config`.
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Config {
+    preset: Option<String>,
+    forbid: Option<bool>,
+}
+
+impl ModuleConfig for CheckFloats {
+    fn with_config(config: &Value) -> Result<Self, ModuleError> {
+        let config: Config = parse_config(config)?;
+        let mut ret = if let Some(preset) = config.preset {
+            CheckFloats::with_preset(&preset).map_err(|_| invalid_preset(&preset))?
+        } else {
+            CheckFloats::new(true)
+        };
+        if let Some(forbid) = config.forbid {
+            ret.forbid = forbid;
+        }
+        Ok(ret)
+    }
+}
+
+impl ModuleValidator for CheckFloats {
+    fn report(&self, module: &Module) -> Result<ValidationReport, ModuleError> {
+        let mut report = ValidationReport::new();
+
+        let types = match module.type_section() {
+            Some(section) => section.types(),
+            None => &[],
+        };
+        for (idx, type_idx) in function_type_indices(module).into_iter().enumerate() {
+            if let Some(Type::Function(sig)) = types.get(type_idx as usize) {
+                let uses_float = sig.params().iter().any(is_float_type)
+                    || matches!(
+                        sig.return_type(),
+                        Some(ValueType::F32) | Some(ValueType::F64)
+                    );
+                if uses_float {
+                    report.push(
+                        self.finding(FindingKind::FloatType)
+                            .with_function(idx as u32)
+                            .with_actual(fmt_func_type(sig)),
+                    );
+                }
+            }
+        }
+
+        let mut globals = Vec::new();
+        if let Some(section) = module.import_section() {
+            for entry in section.entries() {
+                if let External::Global(global_type) = entry.external() {
+                    globals.push(global_type);
+                }
+            }
+        }
+        if let Some(section) = module.global_section() {
+            globals.extend(section.entries().iter().map(|entry| entry.global_type()));
+        }
+        for (idx, global_type) in globals.into_iter().enumerate() {
+            if is_float_type(&global_type.content_type()) {
+                report.push(
+                    self.finding(FindingKind::FloatType)
+                        .with_location(format!("global {}", idx))
+                        .with_actual(fmt_global_type(global_type)),
+                );
+            }
+        }
+
+        let imported = module.import_count(ImportCountType::Function);
+        if let Some(section) = module.code_section() {
+            for (idx, body) in section.bodies().iter().enumerate() {
+                let func_idx = (imported + idx) as u32;
+                for local in body.locals() {
+                    if is_float_type(&local.value_type()) {
+                        report.push(
+                            self.finding(FindingKind::FloatType)
+                                .with_function(func_idx)
+                                .with_actual(format!("(local {})", local.value_type())),
+                        );
+                    }
+                }
+                for (idx, instruction) in body.code().elements().iter().enumerate() {
+                    if is_float_instruction(instruction) {
+                        report.push(
+                            self.finding(FindingKind::FloatInstruction)
+                                .with_function(func_idx)
+                                .with_instruction(idx)
+                                .with_actual(instruction.to_string()),
+                        );
+                    }
+                }
+            }
+        }
+
+        Ok(report)
+    }
+}
+
+fn is_float_type(value_type: &ValueType) -> bool {
+    matches!(value_type, ValueType::F32 | ValueType::F64)
+}
+
+/// Returns true if the instruction operates on, produces or consumes floating-point values.
+pub fn is_float_instruction(instruction: &Instruction) -> bool {
+    use parity_wasm::elements::Instruction::*;
+    match instruction {
+        Block(BlockType::Value(value_type))
+        | Loop(BlockType::Value(value_type))
+        | If(BlockType::Value(value_type)) => is_float_type(value_type),
+        F32Load(..) | F64Load(..) | F32Store(..) | F64Store(..) | F32Const(_) | F64Const(_) => true,
+        F32Eq | F32Ne | F32Lt | F32Gt | F32Le | F32Ge | F64Eq | F64Ne | F64Lt | F64Gt | F64Le
+        | F64Ge => true,
+        F32Abs | F32Neg | F32Ceil | F32Floor | F32Trunc | F32Nearest | F32Sqrt | F32Add
+        | F32Sub | F32Mul | F32Div | F32Min | F32Max | F32Copysign => true,
+        F64Abs | F64Neg | F64Ceil | F64Floor | F64Trunc | F64Nearest | F64Sqrt | F64Add
+        | F64Sub | F64Mul | F64Div | F64Min | F64Max | F64Copysign => true,
+        I32TruncSF32 | I32TruncUF32 | I32TruncSF64 | I32TruncUF64 | I64TruncSF32 | I64TruncUF32
+        | I64TruncSF64 | I64TruncUF64 => true,
+        F32ConvertSI32 | F32ConvertUI32 | F32ConvertSI64 | F32ConvertUI64 | F32DemoteF64
+        | F64ConvertSI32 | F64ConvertUI32 | F64ConvertSI64 | F64ConvertUI64 | F64PromoteF32 => true,
+        I32ReinterpretF32 | I64ReinterpretF64 | F32ReinterpretI32 | F64ReinterpretI64 => true,
+        F32x4Splat | F64x2Splat | F32x4ExtractLane(_) | F64x2ExtractLane(_)
+        | F32x4ReplaceLane(_) | F64x2ReplaceLane(_) => true,
+        F32x4Eq | F64x2Eq | F32x4Ne | F64x2Ne | F32x4Lt | F64x2Lt | F32x4Le | F64x2Le | F32x4Gt
+        | F64x2Gt | F32x4Ge | F64x2Ge => true,
+        F32x4Neg | F64x2Neg | F32x4Abs | F64x2Abs | F32x4Min | F64x2Min | F32x4Max | F64x2Max
+        | F32x4Add | F64x2Add | F32x4Sub | F64x2Sub | F32x4Div | F64x2Div | F32x4Mul | F64x2Mul
+        | F32x4Sqrt | F64x2Sqrt => true,
+        F32x4ConvertSI32x4 | F32x4ConvertUI32x4 | F64x2ConvertSI64x2 | F64x2ConvertUI64x2
+        | I32x4TruncSF32x4Sat | I32x4TruncUF32x4Sat | I64x2TruncSF64x2Sat | I64x2TruncUF64x2Sat => {
+            true
+        }
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use parity_wasm::elements::deserialize_buffer;
+    use rustc_hex::FromHex;
+
+    // wast:
+    // (module
+    //   (import "env" "scale" (func $scale (param f64) (result f64)))
+    //   (global $g (mut f32) (f32.const 0))
+    //   (global $h i32 (i32.const 0))
+    //   (export "main" (func $main))
+    //   (func $main (param i32) (result i32)
+    //     (local f32)
+    //     (local.set 1 (f32.convert_i32_s (local.get 0)))
+    //     (i32.trunc_f32_s (local.get 1)))
+    //   (func $int (param i32) (result i32)
+    //     (i32.add (local.get 0) (i32.const 1)))
+    // )
+    fn float_module() -> Module {
+        let wasm: Vec<u8> = FromHex::from_hex(
+            "
+            0061736d01000000010b0260017c017c60017f017f020d0103656e760573
+            63616c6500000303020101060e027d0143000000000b7f0041000b070801
+            046d61696e00010a16020c01017d2000b221012001a80b0700200041016a
+            0b0023046e616d6501130300057363616c6501046d61696e0203696e7407
+            0702000167010168
+        ",
+        )
+        .unwrap();
+        deserialize_buffer::<Module>(&wasm).unwrap()
+    }
+
+    fn describe(report: &ValidationReport) -> Vec<String> {
+        report.findings().iter().map(|f| f.to_string()).collect()
+    }
+
+    #[test]
+    fn floats_forbidden() {
+        let module = float_module();
+        let checker = CheckFloats::with_preset("ewasm").unwrap();
+        let report = checker.report(&module).unwrap();
+        assert_eq!(
+            describe(&report),
+            vec![
+                "error: floating-point type in function 0, found (func (param f64) (result f64))",
+                "error: floating-point type in global 0, found (global (mut f32))",
+                "error: floating-point type in function 1, found (local f32)",
+                "error: floating-point instruction in function 1 at instruction 1, found f32.convert_s/i32",
+                "error: floating-point instruction in function 1 at instruction 4, found i32.trunc_s/f32",
+            ]
+        );
+        assert_eq!(report.findings()[3].function, Some(1));
+        assert_eq!(report.findings()[3].instruction, Some(1));
+        assert!(!checker.validate(&module).unwrap());
+    }
+
+    #[test]
+    fn floats_warned() {
+        let module = float_module();
+        let checker = CheckFloats::new(false);
+        let report = checker.report(&module).unwrap();
+        assert_eq!(report.findings().len(), 5);
+        assert!(report.is_valid());
+    }
+
+    #[test]
+    fn no_floats() {
+        // wast:
+        // (module
+        //   (func (param i32) (result i32) (i32.add (local.get 0) (i32.const 1)))
+        // )
+        let wasm: Vec<u8> = vec![
+            0x00, 0x61, 0x73, 0x6d, 0x01, 0x00, 0x00, 0x00, 0x01, 0x06, 0x01, 0x60, 0x01, 0x7f,
+            0x01, 0x7f, 0x03, 0x02, 0x01, 0x00, 0x0a, 0x09, 0x01, 0x07, 0x00, 0x20, 0x00, 0x41,
+            0x01, 0x6a, 0x0b,
+        ];
+        let module = deserialize_buffer::<Module>(&wasm).unwrap();
+        let report = CheckFloats::new(true).report(&module).unwrap();
+        assert!(report.findings().is_empty());
+    }
+
+    #[test]
+    fn float_block_type() {
+        assert!(is_float_instruction(&Instruction::Block(BlockType::Value(
+            ValueType::F64
+        ))));
+        assert!(!is_float_instruction(&Instruction::Block(
+            BlockType::Value(ValueType::I64)
+        )));
+        assert!(!is_float_instruction(&Instruction::I32Add));
+    }
+
+    #[test]
+    fn with_config() {
+        let config = serde_yaml::from_str("{preset: ewasm, forbid: false}").unwrap();
+        let checker = CheckFloats::with_config(&config).unwrap();
+        assert!(checker.validate(&float_module()).unwrap());
+
+        let config = serde_yaml::from_str("{}").unwrap();
+        let checker = CheckFloats::with_config(&config).unwrap();
+        assert!(!checker.validate(&float_module()).unwrap());
+
+        let config = serde_yaml::from_str("{preset: foo}").unwrap();
+        assert!(CheckFloats::with_config(&config).is_err());
+    }
+}
@@ -0,0 +1,326 @@
+use std::io::Cursor;
+
+use super::{
+    report::{Finding, FindingKind, ValidationReport},
+    utils::{function_names, parse_config},
+    ModuleConfig, ModuleError, ModuleValidator,
+};
+use parity_wasm::elements::{
+    serialize, Deserialize, External, FuncBody, ImportCountType, Instruction, Module,
+    ResizableLimits, Serialize, VarUint32,
+};
+use serde_yaml::Value;
+
+/// Limits relevant to on-chain deployment. Limits which are not set are not enforced.
+#[derive(Clone, Debug, Default, Deserialize, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct Limits {
+    /// Size of the binary, in bytes. The module is measured as encoded by parity-wasm, which is
+    /// the size of the binary written after the ruleset but may differ from the size of the input,
+    /// e.g. if it was encoded with padded LEB128 integers.
+    pub max_module_size: Option<usize>,
+    /// Number of functions defined in the module.
+    pub max_functions: Option<usize>,
+    /// Number of locals of a function, excluding parameters.
+    pub max_locals: Option<u32>,
+    /// Size of the body of a function, in bytes.
+    pub max_body_size: Option<usize>,
+    /// Depth of nested blocks, loops and ifs in a function.
+    pub max_nesting_depth: Option<u32>,
+    /// Number of globals, imported or defined.
+    pub max_globals: Option<usize>,
+    /// Initial and maximum number of elements of a table, imported or defined.
+    pub max_table_size: Option<u32>,
+    /// Total size of the data segments, in bytes.
+    pub max_data_size: Option<usize>,
+}
+
+/// Struct on which ModuleValidator is implemented. Reports every item of a module exceeding the
+/// configured limits.
+pub struct CheckLimits {
+    limits: Limits,
+}
+
+impl CheckLimits {
+    pub fn new(limits: Limits) -> Self {
+        CheckLimits { limits }
+    }
+}
+
+impl ModuleConfig for CheckLimits {
+    fn with_config(config: &Value) -> Result<Self, ModuleError> {
+        Ok(CheckLimits::new(parse_config(config)?))
+    }
+}
+
+/// Returns a finding if `actual` exceeds the limit, describing both as a number of `unit`.
+fn exceeds<T>(limit: Option<T>, actual: T, unit: &str) -> Option<Finding>
+where
+    T: PartialOrd + std::fmt::Display,
+{
+    match limit {
+        Some(limit) if actual > limit => Some(
+            Finding::error(FindingKind::LimitExceeded)
+                .with_expected(format!("at most {} {}", limit, unit))
+                .with_actual(format!("{} {}", actual, unit)),
+        ),
+        _ => None,
+    }
+}
+
+/// Returns the size of a function body as encoded in the code section, without its size prefix.
+fn body_size(body: &FuncBody) -> Result<usize, ModuleError> {
+    let mut encoded = Vec::new();
+    body.clone().serialize(&mut encoded)?;
+    let size: u32 = VarUint32::deserialize(&mut Cursor::new(&encoded))?.into();
+    Ok(size as usize)
+}
+
+/// Returns the deepest nesting of blocks, loops and ifs in a function body.
+fn nesting_depth(code: &[Instruction]) -> u32 {
+    let mut depth: u32 = 0;
+    let mut max = 0;
+    for instr in code {
+        match instr {
+            Instruction::Block(_) | Instruction::Loop(_) | Instruction::If(_) => {
+                depth += 1;
+                max = max.max(depth);
+            }
+            Instruction::End => depth = depth.saturating_sub(1),
+            _ => (),
+        }
+    }
+    max
+}
+
+impl CheckLimits {
+    fn check_table(&self, limits: &ResizableLimits) -> Option<Finding> {
+        let size = limits.maximum().unwrap_or(0).max(limits.initial());
+        exceeds(self.limits.max_table_size, size, "elements")
+    }
+}
+
+impl ModuleValidator for CheckLimits {
+    fn report(&self, module: &Module) -> Result<ValidationReport, ModuleError> {
+        let mut report = ValidationReport::new();
+        let limits = &self.limits;
+
+        if limits.max_module_size.is_some() {
+            let size = serialize(module.clone())?.len();
+            if let Some(finding) = exceeds(limits.max_module_size, size, "bytes") {
+                report.push(finding.with_location("module".to_string()));
+            }
+        }
+
+        if let Some(section) = module.code_section() {
+            let bodies = section.bodies();
+            if let Some(finding) = exceeds(limits.max_functions, bodies.len(), "functions") {
+                report.push(finding.with_location("module".to_string()));
+            }
+
+            let imports_len = module.import_count(ImportCountType::Function) as u32;
+            let names = function_names(module);
+            for (idx, body) in bodies.iter().enumerate() {
+                let func_idx = imports_len + idx as u32;
+                let locals: u32 = body.locals().iter().map(|local| local.count()).sum();
+                let mut findings = vec![
+                    exceeds(limits.max_locals, locals, "locals"),
+                    exceeds(
+                        limits.max_nesting_depth,
+                        nesting_depth(body.code().elements()),
+                        "nested blocks",
+                    ),
+                ];
+                if limits.max_body_size.is_some() {
+                    findings.push(exceeds(limits.max_body_size, body_size(body)?, "bytes"));
+                }
+                for finding in findings.into_iter().flatten() {
+                    let finding = finding.with_function(func_idx);
+                    report.push(match names.get(&func_idx) {
+                        Some(name) => finding.with_field(name),
+                        None => finding,
+                    });
+                }
+            }
+        }
+
+        let globals_len = module.import_count(ImportCountType::Global)
+            + module
+                .global_section()
+                .map_or(0, |section| section.entries().len());
+        if let Some(finding) = exceeds(limits.max_globals, globals_len, "globals") {
+            report.push(finding.with_location("module".to_string()));
+        }
+
+        let mut table_idx = 0;
+        if let Some(section) = module.import_section() {
+            for entry in section.entries() {
+                if let External::Table(table_type) = entry.external() {
+                    if let Some(finding) = self.check_table(table_type.limits()) {
+                        report.push(
+                            finding
+                                .with_namespace(entry.module())
+                                .with_field(entry.field())
+                                .with_location(format!("table {}", table_idx)),
+                        );
+                    }
+                    table_idx += 1;
+                }
+            }
+        }
+        if let Some(section) = module.table_section() {
+            for table_type in section.entries() {
+                if let Some(finding) = self.check_table(table_type.limits()) {
+                    report.push(finding.with_location(format!("table {}", table_idx)));
+                }
+                table_idx += 1;
+            }
+        }
+
+        if let Some(section) = module.data_section() {
+            let size: usize = section
+                .entries()
+                .iter()
+                .map(|segment| segment.value().len())
+                .sum();
+            if let Some(finding) = exceeds(limits.max_data_size, size, "bytes") {
+                report.push(finding.with_location("data section".to_string()));
+            }
+        }
+
+        Ok(report)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use parity_wasm::elements::deserialize_buffer;
+    use rustc_hex::FromHex;
+
+    // wast:
+    // (module
+    //   (global $a (mut i32) (i32.const 0))
+    //   (global $b i32 (i32.const 1))
+    //   (table 4 8 funcref)
+    //   (memory 1)
+    //   (data (i32.const 0) "hello")
+    //   (data (i32.const 16) "world!")
+    //   (export "main" (func $main))
+    //   (func $main
+    //     (local i32 i64 i64)
+    //     (block (loop (if (local.get 0) (then (nop))))))
+    //   (func $small (nop))
+    // )
+    fn sample_wasm() -> Vec<u8> {
+        FromHex::from_hex(
+            "
+            0061736d0100000001040160000003030200000405017001040805030100
+            01060b027f0141000b7f0041010b070801046d61696e00000a1802120201
+            7f027e0240034020000440010b0b0b0b0300010b0b16020041000b056865
+            6c6c6f0041100b06776f726c6421001e046e616d65010e0200046d61696e
+            0105736d616c6c070702000161010162
+        ",
+        )
+        .unwrap()
+    }
+
+    fn sample_module() -> Module {
+        deserialize_buffer::<Module>(&sample_wasm()).unwrap()
+    }
+
+    fn describe(report: &ValidationReport) -> Vec<String> {
+        report.findings().iter().map(|f| f.to_string()).collect()
+    }
+
+    #[test]
+    fn limits_exceeded() {
+        let config = serde_yaml::from_str(
+            "{max_module_size: 100, max_functions: 1, max_locals: 2, max_body_size: 10,
+              max_nesting_depth: 2, max_globals: 1, max_table_size: 6, max_data_size: 10}",
+        )
+        .unwrap();
+        let report = CheckLimits::with_config(&config)
+            .unwrap()
+            .report(&sample_module())
+            .unwrap();
+        assert_eq!(
+            describe(&report),
+            vec![
+                "error: limit exceeded in module, expected at most 100 bytes, found 136 bytes",
+                "error: limit exceeded in module, expected at most 1 functions, found 2 functions",
+                "error: limit exceeded main in function 0, expected at most 2 locals, found 3 locals",
+                "error: limit exceeded main in function 0, expected at most 2 nested blocks, found 3 nested blocks",
+                "error: limit exceeded main in function 0, expected at most 10 bytes, found 18 bytes",
+                "error: limit exceeded in module, expected at most 1 globals, found 2 globals",
+                "error: limit exceeded in table 0, expected at most 6 elements, found 8 elements",
+                "error: limit exceeded in data section, expected at most 10 bytes, found 11 bytes",
+            ]
+        );
+        assert!(!report.is_valid());
+    }
+
+    #[test]
+    fn module_size() {
+        // The size is that of the re-encoded module, which matches the input here.
+        assert_eq!(sample_wasm().len(), 136);
+        let module = sample_module();
+        let checker = |size| {
+            CheckLimits::new(Limits {
+                max_module_size: Some(size),
+                ..Limits::default()
+            })
+        };
+        assert!(checker(136).validate(&module).unwrap());
+        assert!(!checker(135).validate(&module).unwrap());
+    }
+
+    #[test]
+    fn limits_met() {
+        let limits = Limits {
+            max_module_size: Some(136),
+            max_functions: Some(2),
+            max_locals: Some(3),
+            max_body_size: Some(18),
+            max_nesting_depth: Some(3),
+            max_globals: Some(2),
+            max_table_size: Some(8),
+            max_data_size: Some(11),
+        };
+        let report = CheckLimits::new(limits).report(&sample_module()).unwrap();
+        assert!(report.findings().is_empty());
+
+        // Limits which are not set are not enforced.
+        let report = CheckLimits::new(Limits::default())
+            .report(&sample_module())
+            .unwrap();
+        assert!(report.findings().is_empty());
+    }
+
+    #[test]
+    fn nesting() {
+        use parity_wasm::elements::BlockType;
+        let code = vec![
+            Instruction::Block(BlockType::NoResult),
+            Instruction::End,
+            Instruction::Loop(BlockType::NoResult),
+            Instruction::Block(BlockType::NoResult),
+            Instruction::End,
+            Instruction::End,
+            Instruction::End,
+        ];
+        assert_eq!(nesting_depth(&code), 2);
+    }
+
+    #[test]
+    fn with_config() {
+        let config = serde_yaml::from_str("{}").unwrap();
+        assert_eq!(
+            CheckLimits::with_config(&config).unwrap().limits,
+            Limits::default()
+        );
+
+        let config = serde_yaml::from_str("{max_size: 10}").unwrap();
+        assert!(CheckLimits::with_config(&config).is_err());
+    }
+}
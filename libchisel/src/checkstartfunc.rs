@@ -1,5 +1,10 @@
-use super::{ModuleError, ModuleValidator};
+use super::{
+    report::{Finding, FindingKind, ValidationReport},
+    utils::{invalid_preset, parse_config},
+    ModuleConfig, ModuleError, ModulePreset, ModuleValidator,
+};
 use parity_wasm::elements::Module;
+use serde_yaml::Value;
 
 /// Struct on which ModuleValidator is implemented.
 pub struct CheckStartFunc {
@@ -14,9 +19,50 @@ impl CheckStartFunc {
     }
 }
 
+impl ModulePreset for CheckStartFunc {
+    fn with_preset(preset: &str) -> Result<Self, ()> {
+        match preset {
+            "ewasm" => Ok(CheckStartFunc::new(false)),
+            _ => Err(()),
+        }
+    }
+}
+
+/// Options accepted by `CheckStartFunc::with_config`.
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Config {
+    preset: Option<String>,
+    start_required: Option<bool>,
+}
+
+impl ModuleConfig for CheckStartFunc {
+    fn with_config(config: &Value) -> Result<Self, ModuleError> {
+        let config: Config = parse_config(config)?;
+        let mut ret = if let Some(preset) = config.preset {
+            CheckStartFunc::with_preset(&preset).map_err(|_| invalid_preset(&preset))?
+        } else {
+            CheckStartFunc::new(false)
+        };
+        if let Some(start_required) = config.start_required {
+            ret.start_required = start_required;
+        }
+        Ok(ret)
+    }
+}
+
 impl ModuleValidator for CheckStartFunc {
-    fn validate(&self, module: &Module) -> Result<bool, ModuleError> {
-        Ok(module.start_section().is_some() == self.start_required)
+    fn report(&self, module: &Module) -> Result<ValidationReport, ModuleError> {
+        let mut report = ValidationReport::new();
+        match (module.start_section(), self.start_required) {
+            (None, true) => report.push(Finding::error(FindingKind::MissingStartFunction)),
+            (Some(index), false) => report.push(
+                Finding::error(FindingKind::UnexpectedStartFunction)
+                    .with_actual(format!("function {}", index)),
+            ),
+            _ => (),
+        }
+        Ok(report)
     }
 }
 
@@ -53,6 +99,13 @@ mod tests {
 
         let result = checker.validate(&module).unwrap();
         assert_eq!(false, result);
+
+        let report = checker.report(&module).unwrap();
+        assert_eq!(
+            report.findings(),
+            &[Finding::error(FindingKind::UnexpectedStartFunction)
+                .with_actual("function 0".to_string())]
+        );
     }
 
     #[test]
@@ -84,4 +137,26 @@ mod tests {
         let result = checker.validate(&module).unwrap();
         assert_eq!(false, result);
     }
+
+    #[test]
+    fn start_required_with_config() {
+        let wasm: Vec<u8> = vec![
+            0x00, 0x61, 0x73, 0x6d, 0x01, 0x00, 0x00, 0x00, 0x01, 0x04, 0x01, 0x60, 0x00, 0x00,
+            0x03, 0x02, 0x01, 0x00, 0x07, 0x08, 0x01, 0x04, 0x6d, 0x61, 0x69, 0x6e, 0x00, 0x00,
+            0x0a, 0x04, 0x01, 0x02, 0x00, 0x0b,
+        ];
+
+        let module = deserialize_buffer::<Module>(&wasm).unwrap();
+
+        let config = serde_yaml::from_str("{start_required: true}").unwrap();
+        let checker = CheckStartFunc::with_config(&config).unwrap();
+        assert!(!checker.validate(&module).unwrap());
+
+        let config = serde_yaml::from_str("{preset: ewasm}").unwrap();
+        let checker = CheckStartFunc::with_config(&config).unwrap();
+        assert!(checker.validate(&module).unwrap());
+
+        let config = serde_yaml::from_str("{required: true}").unwrap();
+        assert!(CheckStartFunc::with_config(&config).is_err());
+    }
 }
@@ -1,6 +1,10 @@
-use std::collections::HashSet;
+//! Function dependency graph, describing which functions may call which.
 
-use parity_wasm::elements::{CodeSection, Func, FuncBody, FunctionSection, Instruction, Module};
+use std::collections::{BTreeSet, HashMap, HashSet};
+
+use parity_wasm::elements::{FuncBody, ImportCountType, Instruction, Internal, Module};
+
+use super::utils::function_type_indices;
 
 /// A function dependency graph is represented as a list of "edges", or pairs of function indices
 /// (a, b) where a calls b.
@@ -10,66 +14,296 @@ use parity_wasm::elements::{CodeSection, Func, FuncBody, FunctionSection, Instru
 #[derive(PartialEq, Eq, Hash)]
 struct Edge(u32, u32);
 
-/// Container struct for the function dependency graph.
+/// Container struct for the function dependency graph. Only functions reachable from the roots
+/// the graph was built from are part of it.
+#[derive(Default)]
 pub struct DepGraph {
     edges: HashSet<Edge>,
+    roots: BTreeSet<u32>,
+}
+
+/// Resolves the functions which may be called from a function body.
+struct CallTargets<'a> {
+    /// Number of imported functions. Those do not have a body.
+    imports_len: u32,
+    bodies: &'a [FuncBody],
+    /// Possible targets of `call_indirect`, keyed by the type index used by the instruction.
+    /// Any function in an element segment with a matching signature is considered a target.
+    indirect: HashMap<u32, Vec<u32>>,
 }
 
 /// Private interface for managing the function dependency graph
 trait DepGraphManager {
-    /// Recursive graph builder. Requires import section length in order to resolve the correct
-    /// function body.
-    fn probe(&mut self, idx: u32, imports_len: u32, bodies: &[FuncBody]);
+    /// Recursive graph builder. Fails if a function calls an index outside of the module.
+    fn probe(&mut self, idx: u32, targets: &CallTargets) -> Result<(), ()>;
     fn add_edge(&mut self, dep: Edge) -> bool;
 }
 
 /// Public interface for building function dependency graphs.
-pub trait DepGraphBuilder: DepGraphManager {
+pub trait DepGraphBuilder {
     /// Builds the dependency graph.
     fn build(module: &Module, entry_idx: u32) -> Result<Self, ()>
     where
         Self: std::marker::Sized;
+
+    /// Builds the dependency graph of every function reachable from any of the roots.
+    fn build_from_roots(module: &Module, roots: &[u32]) -> Result<Self, ()>
+    where
+        Self: std::marker::Sized;
+
+    /// Builds the dependency graph from all entry points of the module, see `entry_points`.
+    fn build_from_entry_points(module: &Module) -> Result<Self, ()>
+    where
+        Self: std::marker::Sized,
+    {
+        Self::build_from_roots(module, &entry_points(module))
+    }
+}
+
+/// Returns the functions which may be invoked from outside the module: exported functions, the
+/// start function and functions in element segments. The result is sorted.
+pub fn entry_points(module: &Module) -> Vec<u32> {
+    let mut ret: BTreeSet<u32> = BTreeSet::new();
+    if let Some(section) = module.export_section() {
+        for entry in section.entries() {
+            if let Internal::Function(idx) = entry.internal() {
+                ret.insert(*idx);
+            }
+        }
+    }
+    if let Some(idx) = module.start_section() {
+        ret.insert(idx);
+    }
+    if let Some(section) = module.elements_section() {
+        for entry in section.entries() {
+            ret.extend(entry.members());
+        }
+    }
+    ret.into_iter().collect()
 }
 
 impl DepGraph {
     pub fn new() -> Self {
         DepGraph {
             edges: HashSet::new(),
+            roots: BTreeSet::new(),
         }
     }
 
     pub fn edgecount(&self) -> usize {
         self.edges.len()
     }
-    // TODO: better access methods
+
+    /// Returns all edges as (caller, callee) pairs, sorted.
+    pub fn edges(&self) -> Vec<(u32, u32)> {
+        let mut ret: Vec<(u32, u32)> = self.edges.iter().map(|edge| (edge.0, edge.1)).collect();
+        ret.sort();
+        ret
+    }
+
+    /// Returns the functions the graph was built from, sorted.
+    pub fn roots(&self) -> Vec<u32> {
+        self.roots.iter().cloned().collect()
+    }
+
+    /// Returns all functions reachable from the roots, including the roots themselves, sorted.
+    pub fn reachable(&self) -> Vec<u32> {
+        let mut ret = self.roots.clone();
+        ret.extend(self.edges.iter().map(|edge| edge.1));
+        ret.into_iter().collect()
+    }
+
+    pub fn is_reachable(&self, idx: u32) -> bool {
+        self.roots.contains(&idx) || self.edges.iter().any(|edge| edge.1 == idx)
+    }
+
+    /// Returns the functions which may be called by the given function, sorted.
+    pub fn callees(&self, idx: u32) -> Vec<u32> {
+        let mut ret: Vec<u32> = self
+            .edges
+            .iter()
+            .filter(|edge| edge.0 == idx)
+            .map(|edge| edge.1)
+            .collect();
+        ret.sort();
+        ret
+    }
+
+    /// Returns the functions which may call the given function, sorted.
+    pub fn callers(&self, idx: u32) -> Vec<u32> {
+        let mut ret: Vec<u32> = self
+            .edges
+            .iter()
+            .filter(|edge| edge.1 == idx)
+            .map(|edge| edge.0)
+            .collect();
+        ret.sort();
+        ret
+    }
+
+    /// Returns groups of functions which may recurse through each other, i.e. the strongly
+    /// connected components of the graph containing a cycle. A function calling itself forms a
+    /// group on its own. Groups are sorted, and ordered by their first function.
+    pub fn cycles(&self) -> Vec<Vec<u32>> {
+        let mut adjacency: HashMap<u32, Vec<u32>> = HashMap::new();
+        for edge in self.edges.iter() {
+            adjacency.entry(edge.0).or_default().push(edge.1);
+        }
+
+        let mut search = ComponentSearch::default();
+        for idx in self.reachable() {
+            if !search.indices.contains_key(&idx) {
+                search.visit(idx, &adjacency);
+            }
+        }
+
+        let mut ret: Vec<Vec<u32>> = search
+            .components
+            .into_iter()
+            .filter(|component| {
+                component.len() > 1 || self.edges.contains(&Edge(component[0], component[0]))
+            })
+            .map(|mut component| {
+                component.sort();
+                component
+            })
+            .collect();
+        ret.sort();
+        ret
+    }
+}
+
+/// State of Tarjan's strongly connected components algorithm.
+#[derive(Default)]
+struct ComponentSearch {
+    next_index: u32,
+    indices: HashMap<u32, u32>,
+    lowlinks: HashMap<u32, u32>,
+    stack: Vec<u32>,
+    on_stack: HashSet<u32>,
+    components: Vec<Vec<u32>>,
+}
+
+impl ComponentSearch {
+    fn visit(&mut self, idx: u32, adjacency: &HashMap<u32, Vec<u32>>) {
+        self.indices.insert(idx, self.next_index);
+        self.lowlinks.insert(idx, self.next_index);
+        self.next_index += 1;
+        self.stack.push(idx);
+        self.on_stack.insert(idx);
+
+        for callee in adjacency.get(&idx).map_or(&[][..], |callees| callees) {
+            if !self.indices.contains_key(callee) {
+                self.visit(*callee, adjacency);
+                let lowlink = self.lowlinks[&idx].min(self.lowlinks[callee]);
+                self.lowlinks.insert(idx, lowlink);
+            } else if self.on_stack.contains(callee) {
+                let lowlink = self.lowlinks[&idx].min(self.indices[callee]);
+                self.lowlinks.insert(idx, lowlink);
+            }
+        }
+
+        // The function is the root of a component: pop it off the stack.
+        if self.lowlinks[&idx] == self.indices[&idx] {
+            let mut component = Vec::new();
+            while let Some(member) = self.stack.pop() {
+                self.on_stack.remove(&member);
+                component.push(member);
+                if member == idx {
+                    break;
+                }
+            }
+            self.components.push(component);
+        }
+    }
+}
+
+impl<'a> CallTargets<'a> {
+    fn new(module: &Module, bodies: &'a [FuncBody]) -> Self {
+        let func_types = function_type_indices(module);
+        let types = module
+            .type_section()
+            .map_or(&[][..], |section| section.types());
+
+        // Every function which can be placed into a table, without duplicates.
+        let mut table_funcs: Vec<u32> = Vec::new();
+        if let Some(section) = module.elements_section() {
+            for member in section.entries().iter().flat_map(|entry| entry.members()) {
+                if !table_funcs.contains(member) {
+                    table_funcs.push(*member);
+                }
+            }
+        }
+
+        // Signatures are compared structurally, since a module may declare the same type twice.
+        let indirect = types
+            .iter()
+            .enumerate()
+            .map(|(type_idx, ty)| {
+                let matching = table_funcs
+                    .iter()
+                    .filter(|func_idx| {
+                        func_types
+                            .get(**func_idx as usize)
+                            .and_then(|func_type| types.get(*func_type as usize))
+                            == Some(ty)
+                    })
+                    .cloned()
+                    .collect();
+                (type_idx as u32, matching)
+            })
+            .collect();
+
+        CallTargets {
+            imports_len: module.import_count(ImportCountType::Function) as u32,
+            bodies,
+            indirect,
+        }
+    }
+
+    /// Returns the number of functions, imported or defined.
+    fn funcs_len(&self) -> usize {
+        self.imports_len as usize + self.bodies.len()
+    }
+
+    /// Returns the functions a `call_indirect` of the given type may call.
+    fn indirect(&self, type_idx: u32) -> &[u32] {
+        self.indirect.get(&type_idx).map_or(&[], |funcs| funcs)
+    }
 }
 
 impl DepGraphManager for DepGraph {
     /// Recursively searches function bodies for calls to other functions and adds edges
     /// accordingly.
-    fn probe(&mut self, idx: u32, imports_len: u32, bodies: &[FuncBody]) {
+    fn probe(&mut self, idx: u32, targets: &CallTargets) -> Result<(), ()> {
         // If the function is an import, then just backtrack.
-        if idx < imports_len {
-            return;
+        if idx < targets.imports_len {
+            return Ok(());
         }
 
         // Overflow case handled by the previous early return condition.
-        let code_idx: usize = (idx - imports_len) as usize;
+        let code_idx: usize = (idx - targets.imports_len) as usize;
 
-        assert!((code_idx) < bodies.len());
-        let func_body = &bodies[code_idx];
+        let func_body = targets.bodies.get(code_idx).ok_or(())?;
 
         for instr in func_body.code().elements().iter() {
-            if let Instruction::Call(call_idx) = instr {
-                if self.add_edge(Edge::from((idx, *call_idx))) {
-                    self.probe(*call_idx, imports_len, bodies);
-                } else {
-                    // If the edge already exists then begin backtracking.
-                    return;
+            let callees: &[u32] = match instr {
+                Instruction::Call(call_idx) => std::slice::from_ref(call_idx),
+                Instruction::CallIndirect(type_idx, _) => targets.indirect(*type_idx),
+                _ => &[],
+            };
+            for callee in callees {
+                // Calls of a decoded module are not validated.
+                if *callee as usize >= targets.funcs_len() {
+                    return Err(());
+                }
+                // If the edge already exists then the callee has already been probed.
+                if self.add_edge(Edge::from((idx, *callee))) {
+                    self.probe(*callee, targets)?;
                 }
             }
-            // TODO: Support for call_indirect
         }
+        Ok(())
     }
 
     /// Simply inserts an edge into the graph. Returns false if it was duplicate.
@@ -80,16 +314,29 @@ impl DepGraphManager for DepGraph {
 
 impl DepGraphBuilder for DepGraph {
     fn build(module: &Module, entry_idx: u32) -> Result<Self, ()> {
+        DepGraph::build_from_roots(module, &[entry_idx])
+    }
+
+    fn build_from_roots(module: &Module, roots: &[u32]) -> Result<Self, ()> {
         if let Some(code_section) = module.code_section() {
             let mut ret = DepGraph::new();
+            let targets = CallTargets::new(module, code_section.bodies());
 
-            let imports_len: u32 = if let Some(section) = module.import_section() {
-                section.entries().len() as u32
-            } else {
-                0
-            };
+            if roots.iter().any(|idx| *idx as usize >= targets.funcs_len()) {
+                return Err(());
+            }
+            // Element segments of a decoded module are not validated.
+            if let Some(section) = module.elements_section() {
+                let mut members = section.entries().iter().flat_map(|entry| entry.members());
+                if members.any(|idx| *idx as usize >= targets.funcs_len()) {
+                    return Err(());
+                }
+            }
 
-            ret.probe(entry_idx, imports_len, &code_section.bodies());
+            for idx in roots {
+                ret.roots.insert(*idx);
+                ret.probe(*idx, &targets)?;
+            }
 
             Ok(ret)
         } else {
@@ -379,4 +626,179 @@ mod tests {
 
         assert!(g.edgecount() == 15);
     }
+
+    #[test]
+    fn call_indirect_element_segment() {
+        // wast:
+        // (module
+        //   (import "env" "memory" (memory 1))
+        //   (type $v (func))
+        //   (type $i (func (param i32)))
+        //   (table 3 anyfunc)
+        //   (elem (i32.const 0) $a $b $c)
+        //   (export "main" (func $main))
+        //   (func $main (call_indirect (type $v) (i32.const 0)))
+        //   (func $a)
+        //   (func $b (param i32))
+        //   (func $c (call $d))
+        //   (func $d)
+        // )
+
+        let wasm: Vec<u8> = vec![
+            0x00, 0x61, 0x73, 0x6d, 0x01, 0x00, 0x00, 0x00, 0x01, 0x08, 0x02, 0x60, 0x00, 0x00,
+            0x60, 0x01, 0x7f, 0x00, 0x02, 0x0f, 0x01, 0x03, 0x65, 0x6e, 0x76, 0x06, 0x6d, 0x65,
+            0x6d, 0x6f, 0x72, 0x79, 0x02, 0x00, 0x01, 0x03, 0x06, 0x05, 0x00, 0x00, 0x01, 0x00,
+            0x00, 0x04, 0x04, 0x01, 0x70, 0x00, 0x03, 0x07, 0x08, 0x01, 0x04, 0x6d, 0x61, 0x69,
+            0x6e, 0x00, 0x00, 0x09, 0x09, 0x01, 0x00, 0x41, 0x00, 0x0b, 0x03, 0x01, 0x02, 0x03,
+            0x0a, 0x17, 0x05, 0x07, 0x00, 0x41, 0x00, 0x11, 0x00, 0x00, 0x0b, 0x02, 0x00, 0x0b,
+            0x02, 0x00, 0x0b, 0x04, 0x00, 0x10, 0x04, 0x0b, 0x02, 0x00, 0x0b, 0x00, 0x23, 0x04,
+            0x6e, 0x61, 0x6d, 0x65, 0x01, 0x13, 0x05, 0x00, 0x04, 0x6d, 0x61, 0x69, 0x6e, 0x01,
+            0x01, 0x61, 0x02, 0x01, 0x62, 0x03, 0x01, 0x63, 0x04, 0x01, 0x64, 0x04, 0x07, 0x02,
+            0x00, 0x01, 0x76, 0x01, 0x01, 0x69,
+        ];
+
+        let module = deserialize_buffer::<Module>(&wasm).unwrap();
+        let g = DepGraph::build(&module, 0).unwrap();
+
+        // $main may call $a and $c, but not $b which has a different signature.
+        assert!(g.edgecount() == 3);
+        assert!(g.edges.contains(&Edge(0, 1)));
+        assert!(!g.edges.contains(&Edge(0, 2)));
+        assert!(g.edges.contains(&Edge(0, 3)));
+        assert!(g.edges.contains(&Edge(3, 4)));
+    }
+
+    #[test]
+    fn repeated_call() {
+        // wast:
+        // (module
+        //   (export "main" (func $main))
+        //   (func $main (call $a) (call $a) (call $b))
+        //   (func $a)
+        //   (func $b)
+        // )
+
+        let wasm: Vec<u8> = vec![
+            0x00, 0x61, 0x73, 0x6d, 0x01, 0x00, 0x00, 0x00, 0x01, 0x04, 0x01, 0x60, 0x00, 0x00,
+            0x03, 0x04, 0x03, 0x00, 0x00, 0x00, 0x07, 0x08, 0x01, 0x04, 0x6d, 0x61, 0x69, 0x6e,
+            0x00, 0x00, 0x0a, 0x10, 0x03, 0x08, 0x00, 0x10, 0x01, 0x10, 0x01, 0x10, 0x02, 0x0b,
+            0x02, 0x00, 0x0b, 0x02, 0x00, 0x0b, 0x00, 0x14, 0x04, 0x6e, 0x61, 0x6d, 0x65, 0x01,
+            0x0d, 0x03, 0x00, 0x04, 0x6d, 0x61, 0x69, 0x6e, 0x01, 0x01, 0x61, 0x02, 0x01, 0x62,
+        ];
+
+        let module = deserialize_buffer::<Module>(&wasm).unwrap();
+        let g = DepGraph::build(&module, 0).unwrap();
+
+        assert!(g.edgecount() == 2);
+    }
+
+    fn entry_points_module() -> Module {
+        // wast:
+        // (module
+        //   (import "env" "a" (func $imp))
+        //   (table 1 anyfunc)
+        //   (elem (i32.const 0) $elem)
+        //   (export "main" (func $main))
+        //   (export "other" (func $other))
+        //   (start $start)
+        //   (func $main (call $rec1))
+        //   (func $rec1 (call $rec2))
+        //   (func $rec2 (call $rec1) (call $imp))
+        //   (func $other (call $other))
+        //   (func $start)
+        //   (func $elem)
+        //   (func $dead (call $main))
+        // )
+
+        let wasm: Vec<u8> = vec![
+            0x00, 0x61, 0x73, 0x6d, 0x01, 0x00, 0x00, 0x00, 0x01, 0x04, 0x01, 0x60, 0x00, 0x00,
+            0x02, 0x09, 0x01, 0x03, 0x65, 0x6e, 0x76, 0x01, 0x61, 0x00, 0x00, 0x03, 0x08, 0x07,
+            0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x04, 0x04, 0x01, 0x70, 0x00, 0x01, 0x07,
+            0x10, 0x02, 0x04, 0x6d, 0x61, 0x69, 0x6e, 0x00, 0x01, 0x05, 0x6f, 0x74, 0x68, 0x65,
+            0x72, 0x00, 0x04, 0x08, 0x01, 0x05, 0x09, 0x07, 0x01, 0x00, 0x41, 0x00, 0x0b, 0x01,
+            0x06, 0x0a, 0x22, 0x07, 0x04, 0x00, 0x10, 0x02, 0x0b, 0x04, 0x00, 0x10, 0x03, 0x0b,
+            0x06, 0x00, 0x10, 0x02, 0x10, 0x00, 0x0b, 0x04, 0x00, 0x10, 0x04, 0x0b, 0x02, 0x00,
+            0x0b, 0x02, 0x00, 0x0b, 0x04, 0x00, 0x10, 0x01, 0x0b, 0x00, 0x39, 0x04, 0x6e, 0x61,
+            0x6d, 0x65, 0x01, 0x32, 0x08, 0x00, 0x03, 0x69, 0x6d, 0x70, 0x01, 0x04, 0x6d, 0x61,
+            0x69, 0x6e, 0x02, 0x04, 0x72, 0x65, 0x63, 0x31, 0x03, 0x04, 0x72, 0x65, 0x63, 0x32,
+            0x04, 0x05, 0x6f, 0x74, 0x68, 0x65, 0x72, 0x05, 0x05, 0x73, 0x74, 0x61, 0x72, 0x74,
+            0x06, 0x04, 0x65, 0x6c, 0x65, 0x6d, 0x07, 0x04, 0x64, 0x65, 0x61, 0x64,
+        ];
+
+        deserialize_buffer::<Module>(&wasm).unwrap()
+    }
+
+    #[test]
+    fn multiple_roots() {
+        let module = entry_points_module();
+        assert_eq!(entry_points(&module), vec![1, 4, 5, 6]);
+
+        let g = DepGraph::build_from_entry_points(&module).unwrap();
+        assert_eq!(g.roots(), vec![1, 4, 5, 6]);
+        assert_eq!(g.reachable(), vec![0, 1, 2, 3, 4, 5, 6]);
+        assert!(g.is_reachable(0));
+        assert!(!g.is_reachable(7));
+        assert_eq!(g.edges(), vec![(1, 2), (2, 3), (3, 0), (3, 2), (4, 4)]);
+        assert_eq!(g.callees(3), vec![0, 2]);
+        assert_eq!(g.callers(2), vec![1, 3]);
+        assert!(g.callees(5).is_empty());
+
+        let g = DepGraph::build(&module, 7).unwrap();
+        assert_eq!(g.reachable(), vec![0, 1, 2, 3, 7]);
+
+        assert!(DepGraph::build_from_roots(&module, &[8]).is_err());
+    }
+
+    #[test]
+    fn recursion_cycles() {
+        let module = entry_points_module();
+        let g = DepGraph::build_from_entry_points(&module).unwrap();
+        assert_eq!(g.cycles(), vec![vec![2, 3], vec![4]]);
+
+        let g = DepGraph::build(&module, 1).unwrap();
+        assert_eq!(g.cycles(), vec![vec![2, 3]]);
+
+        let g = DepGraph::build(&module, 5).unwrap();
+        assert!(g.cycles().is_empty());
+    }
+
+    #[test]
+    fn out_of_range_callee() {
+        use parity_wasm::builder;
+        use parity_wasm::elements::Instructions;
+
+        let module = builder::module()
+            .function()
+            .signature()
+            .build()
+            .body()
+            .with_instructions(Instructions::new(vec![
+                Instruction::Call(99),
+                Instruction::End,
+            ]))
+            .build()
+            .build()
+            .build();
+        assert!(DepGraph::build(&module, 0).is_err());
+
+        // An element segment member reached through `call_indirect`.
+        let module = builder::module()
+            .function()
+            .signature()
+            .build()
+            .body()
+            .with_instructions(Instructions::new(vec![
+                Instruction::I32Const(0),
+                Instruction::CallIndirect(0, 0),
+                Instruction::End,
+            ]))
+            .build()
+            .build()
+            .table()
+            .with_min(1)
+            .with_element(0, vec![99])
+            .build()
+            .build();
+        assert!(DepGraph::build(&module, 0).is_err());
+    }
 }
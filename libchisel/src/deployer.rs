@@ -1,7 +1,9 @@
-use super::{ModuleCreator, ModuleError};
+use super::limitmemory::{pages_for, LimitMemory};
+use super::{ModuleConfig, ModuleCreator, ModuleError, ModuleTranslator};
 use crate::utils::*;
 use parity_wasm::builder;
 use parity_wasm::elements::{CustomSection, Module};
+use serde_yaml::Value;
 
 use byteorder::{LittleEndian, WriteBytesExt};
 
@@ -21,6 +23,54 @@ impl<'a> Deployer<'a> {
             _ => Err(()),
         }
     }
+
+    /// Constructs the deployer from a mapping of options.
+    pub fn with_config(config: &Value, payload: &'a [u8]) -> Result<Self, ModuleError> {
+        let config: PresetConfig = parse_config(config)?;
+        Deployer::with_preset(&config.preset, payload).map_err(|_| invalid_preset(&config.preset))
+    }
+
+    /// Enforces a page budget on the memory of the deployer, which is sized after the payload.
+    /// The deployer then fails to be created if the payload needs more memory than the budget.
+    pub fn with_memory_limit(self, limit: LimitMemory) -> LimitedDeployer<'a> {
+        LimitedDeployer {
+            deployer: self,
+            memory_limit: Some(limit),
+        }
+    }
+}
+
+/// Struct on which ModuleCreator is implemented. A deployer along with an optional page budget
+/// enforced on its memory.
+pub struct LimitedDeployer<'a> {
+    deployer: Deployer<'a>,
+    memory_limit: Option<LimitMemory>,
+}
+
+/// Options accepted by `LimitedDeployer::with_config`. The memory limit takes the options of
+/// `LimitMemory`.
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Config {
+    preset: String,
+    memory_limit: Option<Value>,
+}
+
+impl<'a> LimitedDeployer<'a> {
+    /// Constructs the deployer from a mapping of options, which may include a memory limit.
+    pub fn with_config(config: &Value, payload: &'a [u8]) -> Result<Self, ModuleError> {
+        let config: Config = parse_config(config)?;
+        let deployer = Deployer::with_preset(&config.preset, payload)
+            .map_err(|_| invalid_preset(&config.preset))?;
+        let memory_limit = match config.memory_limit {
+            Some(limit) => Some(LimitMemory::with_config(&limit)?),
+            None => None,
+        };
+        Ok(LimitedDeployer {
+            deployer,
+            memory_limit,
+        })
+    }
 }
 
 /*
@@ -75,7 +125,7 @@ fn create_custom_deployer(payload: &[u8]) -> Result<Module, ModuleError> {
     let mut module: Module = parity_wasm::deserialize_buffer(&code)?;
 
     // Re-write memory to pre-allocate enough for code size
-    let memory_initial = (payload.len() as u32 / 65536) + 1;
+    let memory_initial = pages_for(payload.len());
     let mem_type = parity_wasm::elements::MemoryType::new(memory_initial, None, false);
     module
         .memory_section_mut()
@@ -108,7 +158,7 @@ fn create_memory_deployer(payload: &[u8]) -> Module {
         parity_wasm::elements::Instruction::End,
     ];
 
-    let memory_initial = (payload.len() as u32 / 65536) + 1;
+    let memory_initial = pages_for(payload.len());
 
     let module = builder::module()
         // Create a func/type for the ethereum::finish
@@ -169,6 +219,16 @@ impl<'a> ModuleCreator for Deployer<'a> {
     }
 }
 
+impl<'a> ModuleCreator for LimitedDeployer<'a> {
+    fn create(&self) -> Result<Module, ModuleError> {
+        let mut output = self.deployer.create()?;
+        if let Some(limit) = &self.memory_limit {
+            limit.translate_inplace(&mut output)?;
+        }
+        Ok(output)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -287,4 +347,40 @@ mod tests {
             .initial();
         assert_eq!(memory_initial, 10);
     }
+
+    #[test]
+    fn memory_limit() {
+        let payload = [0; 632232];
+        let config = serde_yaml::from_str(
+            "{preset: memory, memory_limit: {max_pages: 16, set_maximum: true}}",
+        )
+        .unwrap();
+        let module = LimitedDeployer::with_config(&config, &payload)
+            .unwrap()
+            .create()
+            .unwrap();
+        let limits = module.memory_section().unwrap().entries()[0].limits();
+        assert_eq!((limits.initial(), limits.maximum()), (10, Some(16)));
+
+        // The payload does not fit in the budget.
+        let module = Deployer::with_preset("customsection", &payload)
+            .unwrap()
+            .with_memory_limit(LimitMemory::new(8, false))
+            .create();
+        assert!(module.is_err());
+
+        let config = serde_yaml::from_str("{preset: memory, memory_limit: {}}").unwrap();
+        assert!(LimitedDeployer::with_config(&config, &payload).is_err());
+
+        // The plain deployer only takes a preset.
+        let config =
+            serde_yaml::from_str("{preset: memory, memory_limit: {max_pages: 16}}").unwrap();
+        assert!(Deployer::with_config(&config, &payload).is_err());
+        let config = serde_yaml::from_str("{preset: memory}").unwrap();
+        let module = LimitedDeployer::with_config(&config, &payload)
+            .unwrap()
+            .create()
+            .unwrap();
+        assert_eq!(module, Deployer::Memory(&payload).create().unwrap());
+    }
 }
@@ -0,0 +1,310 @@
+//! Summarizes the changes made to a module, e.g. by a pipeline of translators.
+
+use std::fmt;
+
+use super::ModuleError;
+use parity_wasm::elements::{serialize, Internal, Module, Section};
+
+/// Names which were renamed, removed or added.
+#[derive(Clone, Debug, Default, PartialEq, Serialize)]
+pub struct NameChanges {
+    pub renamed: Vec<(String, String)>,
+    pub removed: Vec<String>,
+    pub added: Vec<String>,
+}
+
+/// Semantic difference between two versions of a module.
+#[derive(Clone, Debug, Default, PartialEq, Serialize)]
+pub struct ModuleDiff {
+    /// Imports, as `namespace.field`.
+    pub imports: NameChanges,
+    pub exports: NameChanges,
+    /// Exports which kept their name but now refer to another item, with the old and new item.
+    pub retargeted_exports: Vec<(String, String, String)>,
+    /// Sections, as named by `section_name`. Sections are never renamed.
+    pub sections: NameChanges,
+    /// The start function before and after, if it changed.
+    pub start: Option<(Option<u32>, Option<u32>)>,
+    /// Number of functions defined in the module before and after.
+    pub functions: (usize, usize),
+    /// Size of the binary before and after, in bytes.
+    pub size: (usize, usize),
+}
+
+impl NameChanges {
+    /// Compares two lists of names. Lists of the same length are compared position by position,
+    /// as renaming keeps the order of the entries, and differing names are considered renamed.
+    fn new(before: Vec<String>, after: Vec<String>, detect_renames: bool) -> Self {
+        let mut ret = NameChanges::default();
+        if detect_renames && before.len() == after.len() {
+            ret.renamed = before
+                .into_iter()
+                .zip(after)
+                .filter(|(old, new)| old != new)
+                .collect();
+        } else {
+            ret.removed = before
+                .iter()
+                .filter(|name| !after.contains(name))
+                .cloned()
+                .collect();
+            ret.added = after
+                .iter()
+                .filter(|name| !before.contains(name))
+                .cloned()
+                .collect();
+        }
+        ret
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.renamed.is_empty() && self.removed.is_empty() && self.added.is_empty()
+    }
+}
+
+/// Returns a short description of a section, e.g. `import` or `custom "name"`.
+pub fn section_name(section: &Section) -> String {
+    match section {
+        Section::Unparsed { id, .. } => format!("unknown {}", id),
+        Section::Custom(custom) => format!("custom \"{}\"", custom.name()),
+        Section::Type(_) => "type".to_string(),
+        Section::Import(_) => "import".to_string(),
+        Section::Function(_) => "function".to_string(),
+        Section::Table(_) => "table".to_string(),
+        Section::Memory(_) => "memory".to_string(),
+        Section::Global(_) => "global".to_string(),
+        Section::Export(_) => "export".to_string(),
+        Section::Start(_) => "start".to_string(),
+        Section::Element(_) => "element".to_string(),
+        Section::Code(_) => "code".to_string(),
+        Section::Data(_) => "data".to_string(),
+        Section::Name(_) => "custom \"name\"".to_string(),
+        Section::Reloc(_) => "custom \"reloc\"".to_string(),
+    }
+}
+
+fn fmt_internal(internal: &Internal) -> String {
+    match internal {
+        Internal::Function(idx) => format!("func {}", idx),
+        Internal::Table(idx) => format!("table {}", idx),
+        Internal::Memory(idx) => format!("memory {}", idx),
+        Internal::Global(idx) => format!("global {}", idx),
+    }
+}
+
+fn import_names(module: &Module) -> Vec<String> {
+    match module.import_section() {
+        Some(section) => section
+            .entries()
+            .iter()
+            .map(|entry| format!("{}.{}", entry.module(), entry.field()))
+            .collect(),
+        None => Vec::new(),
+    }
+}
+
+fn exports(module: &Module) -> Vec<(String, Internal)> {
+    match module.export_section() {
+        Some(section) => section
+            .entries()
+            .iter()
+            .map(|entry| (entry.field().to_string(), *entry.internal()))
+            .collect(),
+        None => Vec::new(),
+    }
+}
+
+fn function_count(module: &Module) -> usize {
+    module
+        .function_section()
+        .map_or(0, |section| section.entries().len())
+}
+
+impl ModuleDiff {
+    pub fn new(before: &Module, after: &Module) -> Result<Self, ModuleError> {
+        let exports_before = exports(before);
+        let exports_after = exports(after);
+        let retargeted_exports = exports_before
+            .iter()
+            .filter_map(|(field, old)| {
+                match exports_after.iter().find(|(other, _)| other == field) {
+                    Some((_, new)) if new != old => {
+                        Some((field.clone(), fmt_internal(old), fmt_internal(new)))
+                    }
+                    _ => None,
+                }
+            })
+            .collect();
+        let export_names = |exports: Vec<(String, Internal)>| {
+            exports.into_iter().map(|(field, _)| field).collect()
+        };
+
+        let start = match (before.start_section(), after.start_section()) {
+            (old, new) if old != new => Some((old, new)),
+            _ => None,
+        };
+
+        Ok(ModuleDiff {
+            imports: NameChanges::new(import_names(before), import_names(after), true),
+            exports: NameChanges::new(
+                export_names(exports_before),
+                export_names(exports_after),
+                true,
+            ),
+            retargeted_exports,
+            sections: NameChanges::new(
+                before.sections().iter().map(section_name).collect(),
+                after.sections().iter().map(section_name).collect(),
+                false,
+            ),
+            start,
+            functions: (function_count(before), function_count(after)),
+            size: (
+                serialize(before.clone())?.len(),
+                serialize(after.clone())?.len(),
+            ),
+        })
+    }
+
+    /// Returns true if no change was found. The size is not compared, as it may change without
+    /// anything else changing, e.g. when code is rewritten.
+    pub fn is_empty(&self) -> bool {
+        self.imports.is_empty()
+            && self.exports.is_empty()
+            && self.retargeted_exports.is_empty()
+            && self.sections.is_empty()
+            && self.start.is_none()
+            && self.functions.0 == self.functions.1
+    }
+}
+
+fn fmt_start(start: Option<u32>) -> String {
+    match start {
+        Some(idx) => format!("func {}", idx),
+        None => "none".to_string(),
+    }
+}
+
+fn fmt_changes(f: &mut fmt::Formatter, kind: &str, changes: &NameChanges) -> fmt::Result {
+    for (old, new) in changes.renamed.iter() {
+        writeln!(f, "{}: renamed {} -> {}", kind, old, new)?;
+    }
+    for name in changes.removed.iter() {
+        writeln!(f, "{}: removed {}", kind, name)?;
+    }
+    for name in changes.added.iter() {
+        writeln!(f, "{}: added {}", kind, name)?;
+    }
+    Ok(())
+}
+
+/// Lists the changes, one per line.
+impl fmt::Display for ModuleDiff {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt_changes(f, "imports", &self.imports)?;
+        fmt_changes(f, "exports", &self.exports)?;
+        for (field, old, new) in self.retargeted_exports.iter() {
+            writeln!(f, "exports: changed {} from {} to {}", field, old, new)?;
+        }
+        fmt_changes(f, "sections", &self.sections)?;
+        if let Some((old, new)) = self.start {
+            writeln!(f, "start: {} -> {}", fmt_start(old), fmt_start(new))?;
+        }
+        if self.functions.0 != self.functions.1 {
+            writeln!(f, "functions: {} -> {}", self.functions.0, self.functions.1)?;
+        }
+        let (old, new) = self.size;
+        write!(
+            f,
+            "size: {} -> {} bytes ({:+})",
+            old,
+            new,
+            new as i64 - old as i64
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use parity_wasm::elements::deserialize_buffer;
+    use rustc_hex::FromHex;
+
+    // wast:
+    // (module
+    //   (import "env" "ethereum_useGas" (func (param i64)))
+    //   (memory 1)
+    //   (export "main" (func $main))
+    //   (export "memory" (memory 0))
+    //   (func $main)
+    //   (func $main2)
+    //   (start $main2)
+    // )
+    fn sample_module() -> Module {
+        let wasm: Vec<u8> = FromHex::from_hex(
+            "
+            0061736d0100000001080260017e00600000021701
+            03656e760f657468657265756d5f7573654761730000030302010105030100
+            01071102046d61696e0001066d656d6f727902000801020a070202000b0200
+            0b0020046e616d65010e0201046d61696e02056d61696e3202090300010000
+            01000200
+        ",
+        )
+        .unwrap();
+        deserialize_buffer::<Module>(&wasm).unwrap()
+    }
+
+    #[test]
+    fn no_changes() {
+        let module = sample_module();
+        let diff = ModuleDiff::new(&module, &module).unwrap();
+        assert!(diff.is_empty());
+        assert_eq!(diff.to_string(), "size: 118 -> 118 bytes (+0)");
+    }
+
+    #[test]
+    fn semantic_changes() {
+        let before = sample_module();
+        let mut after = before.clone();
+        {
+            let import = &mut after.import_section_mut().unwrap().entries_mut()[0];
+            *import.module_mut() = "ethereum".to_string();
+            *import.field_mut() = "useGas".to_string();
+        }
+        {
+            let exports = after.export_section_mut().unwrap().entries_mut();
+            exports.remove(1);
+            *exports[0].internal_mut() = Internal::Function(2);
+        }
+        after.clear_start_section();
+        after
+            .sections_mut()
+            .retain(|section| section_name(section) != "custom \"name\"");
+
+        let diff = ModuleDiff::new(&before, &after).unwrap();
+        assert!(!diff.is_empty());
+        assert_eq!(
+            diff.to_string(),
+            "imports: renamed env.ethereum_useGas -> ethereum.useGas
+exports: removed memory
+exports: changed main from func 1 to func 2
+sections: removed start
+sections: removed custom \"name\"
+start: func 2 -> none
+size: 118 -> 68 bytes (-50)"
+        );
+    }
+
+    #[test]
+    fn name_changes() {
+        let names = |names: &[&str]| names.iter().map(|name| name.to_string()).collect();
+        let changes = NameChanges::new(names(&["a", "b"]), names(&["b", "a"]), true);
+        assert_eq!(changes.renamed.len(), 2);
+        assert!(changes.removed.is_empty());
+
+        let changes = NameChanges::new(names(&["a", "b"]), names(&["b", "c"]), false);
+        assert!(changes.renamed.is_empty());
+        assert_eq!(changes.removed, vec!["a".to_string()]);
+        assert_eq!(changes.added, vec!["c".to_string()]);
+    }
+}
@@ -1,13 +1,17 @@
-use super::{ModuleError, ModuleTranslator};
+use std::borrow::Cow;
+
+use super::utils::parse_config;
+use super::{ModuleConfig, ModuleError, ModuleTranslator};
 
 use parity_wasm::builder::*;
 use parity_wasm::elements::*;
+use serde_yaml::Value;
 
 /// Enum on which ModuleTranslator is implemented.
 pub enum DropSection<'a> {
     NamesSection,
-    /// Name of the custom section.
-    CustomSectionByName(&'a String),
+    /// Name of the custom section. Owned when read from a configuration.
+    CustomSectionByName(Cow<'a, str>),
     /// Index of the custom section.
     CustomSectionByIndex(usize),
     /// Index of the unknown section.
@@ -21,13 +25,45 @@ fn names_section_index_for(module: &Module) -> Option<usize> {
     })
 }
 
-fn custom_section_index_for(module: &Module, name: &String) -> Option<usize> {
+fn custom_section_index_for(module: &Module, name: &str) -> Option<usize> {
     module.sections().iter().position(|e| match e {
         Section::Custom(_section) => _section.name() == name,
         _ => false,
     })
 }
 
+/// Options accepted by `DropSection::with_config`. Exactly one of them must be set.
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Config {
+    names_section: Option<bool>,
+    custom_section: Option<String>,
+    custom_section_index: Option<usize>,
+    unknown_section_index: Option<usize>,
+}
+
+impl<'a> ModuleConfig for DropSection<'a> {
+    fn with_config(config: &Value) -> Result<Self, ModuleError> {
+        let config: Config = parse_config(config)?;
+        match (
+            config.names_section,
+            config.custom_section,
+            config.custom_section_index,
+            config.unknown_section_index,
+        ) {
+            (Some(true), None, None, None) => Ok(DropSection::NamesSection),
+            (None, Some(name), None, None) => Ok(DropSection::CustomSectionByName(name.into())),
+            (None, None, Some(index), None) => Ok(DropSection::CustomSectionByIndex(index)),
+            (None, None, None, Some(index)) => Ok(DropSection::UnknownSectionByIndex(index)),
+            _ => Err(ModuleError::Custom(
+                "Exactly one of names_section, custom_section, custom_section_index and \
+                 unknown_section_index must be set"
+                    .to_string(),
+            )),
+        }
+    }
+}
+
 impl<'a> DropSection<'a> {
     fn find_index(&self, module: &Module) -> Option<usize> {
         match &self {
@@ -78,8 +114,7 @@ mod tests {
     #[test]
     fn keep_intact() {
         let mut module = builder::module().build();
-        let name = "empty".to_string();
-        let dropper = DropSection::CustomSectionByName(&name);
+        let dropper = DropSection::CustomSectionByName("empty".into());
         let did_change = dropper.translate_inplace(&mut module).unwrap();
         assert_eq!(did_change, false);
     }
@@ -92,8 +127,7 @@ mod tests {
                 vec![],
             )))
             .build();
-        let name = "empty".to_string();
-        let dropper = DropSection::CustomSectionByName(&name);
+        let dropper = DropSection::CustomSectionByName("empty".into());
         let did_change = dropper.translate_inplace(&mut module).unwrap();
         assert_eq!(did_change, false);
     }
@@ -106,8 +140,7 @@ mod tests {
                 vec![],
             )))
             .build();
-        let name = "test".to_string();
-        let dropper = DropSection::CustomSectionByName(&name);
+        let dropper = DropSection::CustomSectionByName("test".into());
         let did_change = dropper.translate_inplace(&mut module).unwrap();
         assert_eq!(did_change, true);
     }
@@ -163,4 +196,30 @@ mod tests {
         let did_change = dropper.translate_inplace(&mut module).unwrap();
         assert_eq!(did_change, false);
     }
+
+    #[test]
+    fn remove_custom_section_with_config() {
+        let mut module = builder::module()
+            .with_section(Section::Custom(CustomSection::new(
+                "test".to_string(),
+                vec![],
+            )))
+            .build();
+        let config = serde_yaml::from_str("{custom_section: test}").unwrap();
+        let dropper = DropSection::with_config(&config).unwrap();
+        let did_change = dropper.translate_inplace(&mut module).unwrap();
+        assert!(did_change);
+    }
+
+    #[test]
+    fn invalid_config() {
+        let config = serde_yaml::from_str("{}").unwrap();
+        assert!(DropSection::with_config(&config).is_err());
+
+        let config = serde_yaml::from_str("{custom_section: test, names_section: true}").unwrap();
+        assert!(DropSection::with_config(&config).is_err());
+
+        let config = serde_yaml::from_str("{section: test}").unwrap();
+        assert!(DropSection::with_config(&config).is_err());
+    }
 }
@@ -0,0 +1,408 @@
+use std::borrow::Cow;
+use std::fs::read_to_string;
+use std::path::Path;
+
+use serde::{Deserialize, Deserializer};
+
+use super::{
+    utils::{fmt_func_type, function_signature, ValueTypeDefinition},
+    ModuleError, ModulePreset,
+};
+
+use parity_wasm::elements::{ExportEntry, FunctionType, Internal, Module, ValueType};
+
+/// List of the exports of an interface, shared by `TrimExports` and `VerifyExports`.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ExportList<'a>(Vec<ListedExport<'a>>);
+
+/// Enum representing a type of export and any extra data to check. Functions are only checked
+/// against their signature if it is given.
+#[derive(Clone, Debug, PartialEq)]
+pub enum ExportType<'a> {
+    Function(Cow<'a, str>, Option<FunctionType>),
+    Global(Cow<'a, str>),
+    Memory(Cow<'a, str>),
+    Table(Cow<'a, str>),
+}
+
+/// An export of an interface, which modules may be required to have.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ListedExport<'a> {
+    export: ExportType<'a>,
+    required: bool,
+}
+
+/// Interface definition file, listing the exports of an interface.
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct InterfaceDefinition {
+    exports: Vec<ExportDefinition>,
+}
+
+/// Definition of a single export, as found in an interface definition file or inline in a
+/// configuration.
+#[derive(Deserialize)]
+#[serde(tag = "kind", rename_all = "lowercase", deny_unknown_fields)]
+enum ExportDefinition {
+    Function {
+        field: String,
+        params: Option<Vec<ValueTypeDefinition>>,
+        result: Option<ValueTypeDefinition>,
+        #[serde(default)]
+        optional: bool,
+    },
+    Global {
+        field: String,
+        #[serde(default)]
+        optional: bool,
+    },
+    Memory {
+        field: String,
+        #[serde(default)]
+        optional: bool,
+    },
+    Table {
+        field: String,
+        #[serde(default)]
+        optional: bool,
+    },
+}
+
+impl<'a> ExportType<'a> {
+    pub fn field(&self) -> &str {
+        match self {
+            ExportType::Function(field, _) => field,
+            ExportType::Global(field) => field,
+            ExportType::Memory(field) => field,
+            ExportType::Table(field) => field,
+        }
+    }
+
+    /// Returns a human-readable description of the expected export.
+    pub fn describe(&self) -> String {
+        match self {
+            ExportType::Function(_, Some(sig)) => fmt_func_type(sig),
+            ExportType::Function(_, None) => "func".to_string(),
+            ExportType::Global(_) => "global".to_string(),
+            ExportType::Memory(_) => "memory".to_string(),
+            ExportType::Table(_) => "table".to_string(),
+        }
+    }
+
+    /// Returns true if an export has the same name and kind. Signatures are not compared.
+    pub fn is_kind_of(&self, export: &ExportEntry) -> bool {
+        let same_kind = matches!(
+            (self, export.internal()),
+            (ExportType::Function(..), Internal::Function(_))
+                | (ExportType::Global(_), Internal::Global(_))
+                | (ExportType::Memory(_), Internal::Memory(_))
+                | (ExportType::Table(_), Internal::Table(_))
+        );
+        same_kind && self.field() == export.field()
+    }
+
+    /// Returns true if an export of the module matches this one, including the signature of
+    /// functions when it is given.
+    pub fn matches(&self, module: &Module, export: &ExportEntry) -> bool {
+        if !self.is_kind_of(export) {
+            return false;
+        }
+        match (self, export.internal()) {
+            (ExportType::Function(_, Some(sig)), Internal::Function(idx)) => {
+                function_signature(module, *idx) == Some(sig)
+            }
+            _ => true,
+        }
+    }
+}
+
+/// Returns a human-readable description of an export entry found in the module, in the format of
+/// `ExportType::describe`.
+pub fn describe_export(module: &Module, export: &ExportEntry) -> String {
+    match export.internal() {
+        Internal::Function(index) => match function_signature(module, *index) {
+            Some(sig) => fmt_func_type(sig),
+            None => "func".to_string(),
+        },
+        Internal::Global(_) => "global".to_string(),
+        Internal::Memory(_) => "memory".to_string(),
+        Internal::Table(_) => "table".to_string(),
+    }
+}
+
+impl<'a> ListedExport<'a> {
+    pub fn new(export: ExportType<'a>, required: bool) -> Self {
+        ListedExport { export, required }
+    }
+
+    pub fn export(&self) -> &ExportType<'a> {
+        &self.export
+    }
+
+    pub fn is_required(&self) -> bool {
+        self.required
+    }
+}
+
+impl<'a> ExportList<'a> {
+    pub fn new() -> Self {
+        ExportList(Vec::new())
+    }
+
+    pub fn entries(&self) -> &Vec<ListedExport<'a>> {
+        &self.0
+    }
+
+    pub fn into_inner(self) -> Vec<ListedExport<'a>> {
+        self.0
+    }
+
+    /// Appends the exports of another list. An export with the same field as an existing one
+    /// replaces it, so that e.g. inline exports override those of a preset.
+    pub fn concatenate(&mut self, other: ExportList<'a>) {
+        for entry in other.into_inner() {
+            match self
+                .0
+                .iter_mut()
+                .find(|existing| existing.export.field() == entry.export.field())
+            {
+                Some(existing) => *existing = entry,
+                None => self.0.push(entry),
+            }
+        }
+    }
+
+    pub fn with_entries(entries: Vec<ListedExport<'a>>) -> Self {
+        ExportList(entries)
+    }
+
+    /// Looks up the entry with the name and kind of an export.
+    pub fn lookup(&self, export: &ExportEntry) -> Option<&ListedExport<'a>> {
+        self.0.iter().find(|entry| entry.export.is_kind_of(export))
+    }
+}
+
+impl ExportList<'static> {
+    /// Parses an interface definition written in YAML or JSON.
+    pub fn from_definition(definition: &str) -> Result<Self, ModuleError> {
+        let definition: InterfaceDefinition = serde_yaml::from_str(definition)?;
+        Ok(ExportList::from(definition.exports))
+    }
+
+    /// Loads an interface definition from a YAML or JSON file.
+    pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Self, ModuleError> {
+        let definition = read_to_string(&path).map_err(|e| {
+            ModuleError::Custom(format!(
+                "Failed to read interface {}: {}",
+                path.as_ref().display(),
+                e
+            ))
+        })?;
+        ExportList::from_definition(&definition)
+    }
+}
+
+/// Deserializes a list of export definitions, as written inline in a configuration.
+impl<'de> Deserialize<'de> for ExportList<'static> {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        Ok(ExportList::from(Vec::<ExportDefinition>::deserialize(
+            deserializer,
+        )?))
+    }
+}
+
+impl From<Vec<ExportDefinition>> for ExportList<'static> {
+    fn from(definitions: Vec<ExportDefinition>) -> Self {
+        ExportList(definitions.into_iter().map(ListedExport::from).collect())
+    }
+}
+
+impl From<ExportDefinition> for ListedExport<'static> {
+    fn from(definition: ExportDefinition) -> Self {
+        match definition {
+            ExportDefinition::Function {
+                field,
+                params,
+                result,
+                optional,
+            } => {
+                // The signature is only checked if any part of it is given.
+                let sig = match (params, result) {
+                    (None, None) => None,
+                    (params, result) => Some(FunctionType::new(
+                        params
+                            .unwrap_or_default()
+                            .into_iter()
+                            .map(ValueType::from)
+                            .collect(),
+                        result.map(ValueType::from),
+                    )),
+                };
+                ListedExport::new(ExportType::Function(field.into(), sig), !optional)
+            }
+            ExportDefinition::Global { field, optional } => {
+                ListedExport::new(ExportType::Global(field.into()), !optional)
+            }
+            ExportDefinition::Memory { field, optional } => {
+                ListedExport::new(ExportType::Memory(field.into()), !optional)
+            }
+            ExportDefinition::Table { field, optional } => {
+                ListedExport::new(ExportType::Table(field.into()), !optional)
+            }
+        }
+    }
+}
+
+impl<'a> ModulePreset for ExportList<'a> {
+    fn with_preset(preset: &str) -> Result<Self, ()> {
+        match preset {
+            "ewasm" => Ok(ExportList(vec![
+                ListedExport::new(
+                    ExportType::Function("main".into(), Some(FunctionType::default())),
+                    true,
+                ),
+                ListedExport::new(ExportType::Memory("memory".into()), true),
+            ])),
+            "pwasm" => Ok(ExportList(vec![ListedExport::new(
+                ExportType::Function("_call".into(), None),
+                true,
+            )])),
+            _ => Err(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use parity_wasm::builder;
+
+    #[test]
+    fn definition_yaml() {
+        let definition = "
+exports:
+  - kind: function
+    field: main
+    params: []
+  - kind: function
+    field: get
+    params: [i32]
+    result: i64
+  - kind: function
+    field: any
+  - kind: memory
+    field: memory
+  - kind: global
+    field: version
+    optional: true
+";
+        let list = ExportList::from_definition(definition).unwrap();
+        assert_eq!(
+            list.entries(),
+            &vec![
+                ListedExport::new(
+                    ExportType::Function("main".into(), Some(FunctionType::default())),
+                    true
+                ),
+                ListedExport::new(
+                    ExportType::Function(
+                        "get".into(),
+                        Some(FunctionType::new(
+                            vec![ValueType::I32],
+                            Some(ValueType::I64)
+                        ))
+                    ),
+                    true
+                ),
+                ListedExport::new(ExportType::Function("any".into(), None), true),
+                ListedExport::new(ExportType::Memory("memory".into()), true),
+                ListedExport::new(ExportType::Global("version".into()), false),
+            ]
+        );
+    }
+
+    #[test]
+    fn definition_inline() {
+        let config: serde_yaml::Value =
+            serde_yaml::from_str("[{kind: table, field: table, optional: true}]").unwrap();
+        let list: ExportList = serde_yaml::from_value(config).unwrap();
+        assert_eq!(
+            list.entries(),
+            &vec![ListedExport::new(ExportType::Table("table".into()), false)]
+        );
+    }
+
+    #[test]
+    fn concatenate_overrides() {
+        let mut list = ExportList::with_preset("ewasm").unwrap();
+        list.concatenate(
+            ExportList::from_definition(
+                "exports: [{kind: function, field: main, params: [i32]}, {kind: global, field: version}]",
+            )
+            .unwrap(),
+        );
+        assert_eq!(
+            list.entries(),
+            &vec![
+                ListedExport::new(
+                    ExportType::Function(
+                        "main".into(),
+                        Some(FunctionType::new(vec![ValueType::I32], None))
+                    ),
+                    true
+                ),
+                ListedExport::new(ExportType::Memory("memory".into()), true),
+                ListedExport::new(ExportType::Global("version".into()), true),
+            ]
+        );
+    }
+
+    #[test]
+    fn definition_errors() {
+        // Unknown kind.
+        assert!(ExportList::from_definition("exports: [{kind: func, field: main}]").is_err());
+        // Namespaces only apply to imports.
+        assert!(ExportList::from_definition(
+            "exports: [{kind: memory, namespace: env, field: memory}]"
+        )
+        .is_err());
+        // Missing field.
+        assert!(ExportList::from_definition("exports: [{kind: memory}]").is_err());
+        assert!(ExportList::from_file("/nonexistent/interface.yml").is_err());
+    }
+
+    #[test]
+    fn matches() {
+        let module = builder::module()
+            .function()
+            .signature()
+            .with_param(ValueType::I32)
+            .build()
+            .body()
+            .build()
+            .build()
+            .export()
+            .field("main")
+            .internal()
+            .func(0)
+            .build()
+            .build();
+        let export = &module.export_section().unwrap().entries()[0];
+
+        assert!(ExportType::Function("main".into(), None).matches(&module, export));
+        assert!(ExportType::Function(
+            "main".into(),
+            Some(FunctionType::new(vec![ValueType::I32], None))
+        )
+        .matches(&module, export));
+        assert!(
+            !ExportType::Function("main".into(), Some(FunctionType::default()))
+                .matches(&module, export)
+        );
+        assert!(!ExportType::Memory("main".into()).matches(&module, export));
+        assert!(!ExportType::Function("_call".into(), None).matches(&module, export));
+    }
+}
@@ -1,16 +1,81 @@
-use super::ModulePreset;
+use std::borrow::Cow;
+use std::fs::read_to_string;
+use std::path::Path;
 
-use parity_wasm::elements::{FunctionType, ValueType};
+use super::{utils::ValueTypeDefinition, ModuleError, ModulePreset};
+
+use parity_wasm::elements::{FunctionType, GlobalType, ResizableLimits, ValueType};
 
 pub struct ImportList<'a>(Vec<ImportType<'a>>);
 
-/// Enum internally representing a type of import.
+/// Enum internally representing a type of import. Globals, memories and tables are only checked
+/// against their type or limits if those are given.
 #[derive(Clone)]
 pub enum ImportType<'a> {
-    Function(&'a str, &'a str, FunctionType),
-    Global(&'a str, &'a str),
-    Memory(&'a str, &'a str),
-    Table(&'a str, &'a str),
+    Function(Cow<'a, str>, Cow<'a, str>, FunctionType),
+    Global(Cow<'a, str>, Cow<'a, str>, Option<GlobalType>),
+    Memory(Cow<'a, str>, Cow<'a, str>, Option<ResizableLimits>),
+    Table(Cow<'a, str>, Cow<'a, str>, Option<ResizableLimits>),
+}
+
+/// Interface definition file, listing the imports of a host interface.
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct InterfaceDefinition {
+    imports: Vec<ImportDefinition>,
+}
+
+/// Definition of a single import, as found in an interface definition file.
+#[derive(Deserialize)]
+#[serde(tag = "kind", rename_all = "lowercase", deny_unknown_fields)]
+enum ImportDefinition {
+    Function {
+        namespace: String,
+        field: String,
+        #[serde(default)]
+        params: Vec<ValueTypeDefinition>,
+        result: Option<ValueTypeDefinition>,
+    },
+    Global {
+        namespace: String,
+        field: String,
+        #[serde(rename = "type")]
+        content_type: Option<ValueTypeDefinition>,
+        #[serde(default)]
+        mutable: bool,
+    },
+    Memory {
+        namespace: String,
+        field: String,
+        initial: Option<u32>,
+        maximum: Option<u32>,
+    },
+    Table {
+        namespace: String,
+        field: String,
+        initial: Option<u32>,
+        maximum: Option<u32>,
+    },
+}
+
+impl<'a> ImportType<'a> {
+    pub fn namespace(&self) -> &str {
+        match self {
+            ImportType::Function(namespace, _, _) => namespace,
+            ImportType::Global(namespace, _, _) => namespace,
+            ImportType::Memory(namespace, _, _) => namespace,
+            ImportType::Table(namespace, _, _) => namespace,
+        }
+    }
+
+    pub fn field(&self) -> &str {
+        match self {
+            ImportType::Function(_, field, _) => field,
+            ImportType::Global(_, field, _) => field,
+            ImportType::Memory(_, field, _) => field,
+            ImportType::Table(_, field, _) => field,
+        }
+    }
 }
 
 impl<'a> ImportList<'a> {
@@ -40,6 +105,97 @@ impl<'a> ImportList<'a> {
     }
 }
 
+impl ImportList<'static> {
+    /// Parses an interface definition written in YAML or JSON.
+    pub fn from_definition(definition: &str) -> Result<Self, ModuleError> {
+        let definition: InterfaceDefinition = serde_yaml::from_str(definition)?;
+        Ok(ImportList(
+            definition
+                .imports
+                .into_iter()
+                .map(ImportType::from_definition)
+                .collect::<Result<_, _>>()?,
+        ))
+    }
+
+    /// Loads an interface definition from a YAML or JSON file.
+    pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Self, ModuleError> {
+        let definition = read_to_string(&path).map_err(|e| {
+            ModuleError::Custom(format!(
+                "Failed to read interface {}: {}",
+                path.as_ref().display(),
+                e
+            ))
+        })?;
+        ImportList::from_definition(&definition)
+    }
+}
+
+/// Builds the limits of a memory or table. A maximum can only be checked along with an initial
+/// size, so giving it alone is rejected rather than silently ignored.
+fn limits_from_definition(
+    field: &str,
+    initial: Option<u32>,
+    maximum: Option<u32>,
+) -> Result<Option<ResizableLimits>, ModuleError> {
+    match (initial, maximum) {
+        (Some(initial), maximum) => Ok(Some(ResizableLimits::new(initial, maximum))),
+        (None, None) => Ok(None),
+        (None, Some(_)) => Err(ModuleError::Custom(format!(
+            "Import {} has a maximum but no initial size",
+            field
+        ))),
+    }
+}
+
+impl ImportType<'static> {
+    fn from_definition(definition: ImportDefinition) -> Result<Self, ModuleError> {
+        Ok(match definition {
+            ImportDefinition::Function {
+                namespace,
+                field,
+                params,
+                result,
+            } => ImportType::Function(
+                namespace.into(),
+                field.into(),
+                FunctionType::new(
+                    params.into_iter().map(ValueType::from).collect(),
+                    result.map(ValueType::from),
+                ),
+            ),
+            ImportDefinition::Global {
+                namespace,
+                field,
+                content_type,
+                mutable,
+            } => ImportType::Global(
+                namespace.into(),
+                field.into(),
+                content_type.map(|t| GlobalType::new(t.into(), mutable)),
+            ),
+            ImportDefinition::Memory {
+                namespace,
+                field,
+                initial,
+                maximum,
+            } => {
+                let limits = limits_from_definition(&field, initial, maximum)?;
+                ImportType::Memory(namespace.into(), field.into(), limits)
+            }
+            ImportDefinition::Table {
+                namespace,
+                field,
+                initial,
+                maximum,
+            } => {
+                let limits = limits_from_definition(&field, initial, maximum)?;
+                ImportType::Table(namespace.into(), field.into(), limits)
+            }
+        })
+    }
+}
+
 impl<'a> ModulePreset for ImportList<'a> {
     fn with_preset(preset: &str) -> Result<Self, ()>
     where
@@ -48,33 +204,33 @@ impl<'a> ModulePreset for ImportList<'a> {
         match preset {
             "ewasm" => Ok(ImportList(vec![
                 ImportType::Function(
-                    "ethereum",
-                    "useGas",
+                    "ethereum".into(),
+                    "useGas".into(),
                     FunctionType::new(vec![ValueType::I64], None),
                 ),
                 ImportType::Function(
-                    "ethereum",
-                    "getGasLeft",
+                    "ethereum".into(),
+                    "getGasLeft".into(),
                     FunctionType::new(vec![], Some(ValueType::I64)),
                 ),
                 ImportType::Function(
-                    "ethereum",
-                    "getAddress",
+                    "ethereum".into(),
+                    "getAddress".into(),
                     FunctionType::new(vec![ValueType::I32], None),
                 ),
                 ImportType::Function(
-                    "ethereum",
-                    "getExternalBalance",
+                    "ethereum".into(),
+                    "getExternalBalance".into(),
                     FunctionType::new(vec![ValueType::I32, ValueType::I32], None),
                 ),
                 ImportType::Function(
-                    "ethereum",
-                    "getBlockHash",
+                    "ethereum".into(),
+                    "getBlockHash".into(),
                     FunctionType::new(vec![ValueType::I64, ValueType::I32], Some(ValueType::I32)),
                 ),
                 ImportType::Function(
-                    "ethereum",
-                    "call",
+                    "ethereum".into(),
+                    "call".into(),
                     FunctionType::new(
                         vec![
                             ValueType::I64,
@@ -87,8 +243,8 @@ impl<'a> ModulePreset for ImportList<'a> {
                     ),
                 ),
                 ImportType::Function(
-                    "ethereum",
-                    "callCode",
+                    "ethereum".into(),
+                    "callCode".into(),
                     FunctionType::new(
                         vec![
                             ValueType::I64,
@@ -101,8 +257,8 @@ impl<'a> ModulePreset for ImportList<'a> {
                     ),
                 ),
                 ImportType::Function(
-                    "ethereum",
-                    "callDelegate",
+                    "ethereum".into(),
+                    "callDelegate".into(),
                     FunctionType::new(
                         vec![
                             ValueType::I64,
@@ -114,8 +270,8 @@ impl<'a> ModulePreset for ImportList<'a> {
                     ),
                 ),
                 ImportType::Function(
-                    "ethereum",
-                    "callStatic",
+                    "ethereum".into(),
+                    "callStatic".into(),
                     FunctionType::new(
                         vec![
                             ValueType::I64,
@@ -127,8 +283,8 @@ impl<'a> ModulePreset for ImportList<'a> {
                     ),
                 ),
                 ImportType::Function(
-                    "ethereum",
-                    "create",
+                    "ethereum".into(),
+                    "create".into(),
                     FunctionType::new(
                         vec![
                             ValueType::I64,
@@ -140,28 +296,28 @@ impl<'a> ModulePreset for ImportList<'a> {
                     ),
                 ),
                 ImportType::Function(
-                    "ethereum",
-                    "callDataCopy",
+                    "ethereum".into(),
+                    "callDataCopy".into(),
                     FunctionType::new(vec![ValueType::I32, ValueType::I32, ValueType::I32], None),
                 ),
                 ImportType::Function(
-                    "ethereum",
-                    "getCallDataSize",
+                    "ethereum".into(),
+                    "getCallDataSize".into(),
                     FunctionType::new(vec![], Some(ValueType::I32)),
                 ),
                 ImportType::Function(
-                    "ethereum",
-                    "getCodeSize",
+                    "ethereum".into(),
+                    "getCodeSize".into(),
                     FunctionType::new(vec![], Some(ValueType::I32)),
                 ),
                 ImportType::Function(
-                    "ethereum",
-                    "getExternalCodeSize",
+                    "ethereum".into(),
+                    "getExternalCodeSize".into(),
                     FunctionType::new(vec![ValueType::I32], Some(ValueType::I32)),
                 ),
                 ImportType::Function(
-                    "ethereum",
-                    "externalCodeCopy",
+                    "ethereum".into(),
+                    "externalCodeCopy".into(),
                     FunctionType::new(
                         vec![
                             ValueType::I32,
@@ -173,68 +329,68 @@ impl<'a> ModulePreset for ImportList<'a> {
                     ),
                 ),
                 ImportType::Function(
-                    "ethereum",
-                    "codeCopy",
+                    "ethereum".into(),
+                    "codeCopy".into(),
                     FunctionType::new(vec![ValueType::I32, ValueType::I32, ValueType::I32], None),
                 ),
                 ImportType::Function(
-                    "ethereum",
-                    "getCaller",
+                    "ethereum".into(),
+                    "getCaller".into(),
                     FunctionType::new(vec![ValueType::I32], None),
                 ),
                 ImportType::Function(
-                    "ethereum",
-                    "getCallValue",
+                    "ethereum".into(),
+                    "getCallValue".into(),
                     FunctionType::new(vec![ValueType::I32], None),
                 ),
                 ImportType::Function(
-                    "ethereum",
-                    "getBlockDifficulty",
+                    "ethereum".into(),
+                    "getBlockDifficulty".into(),
                     FunctionType::new(vec![ValueType::I32], None),
                 ),
                 ImportType::Function(
-                    "ethereum",
-                    "getBlockCoinbase",
+                    "ethereum".into(),
+                    "getBlockCoinbase".into(),
                     FunctionType::new(vec![ValueType::I32], None),
                 ),
                 ImportType::Function(
-                    "ethereum",
-                    "getBlockNumber",
+                    "ethereum".into(),
+                    "getBlockNumber".into(),
                     FunctionType::new(vec![], Some(ValueType::I64)),
                 ),
                 ImportType::Function(
-                    "ethereum",
-                    "getBlockGasLimit",
+                    "ethereum".into(),
+                    "getBlockGasLimit".into(),
                     FunctionType::new(vec![], Some(ValueType::I64)),
                 ),
                 ImportType::Function(
-                    "ethereum",
-                    "getBlockTimestamp",
+                    "ethereum".into(),
+                    "getBlockTimestamp".into(),
                     FunctionType::new(vec![], Some(ValueType::I64)),
                 ),
                 ImportType::Function(
-                    "ethereum",
-                    "getTxGasPrice",
+                    "ethereum".into(),
+                    "getTxGasPrice".into(),
                     FunctionType::new(vec![ValueType::I32], None),
                 ),
                 ImportType::Function(
-                    "ethereum",
-                    "getTxOrigin",
+                    "ethereum".into(),
+                    "getTxOrigin".into(),
                     FunctionType::new(vec![ValueType::I32], None),
                 ),
                 ImportType::Function(
-                    "ethereum",
-                    "storageStore",
+                    "ethereum".into(),
+                    "storageStore".into(),
                     FunctionType::new(vec![ValueType::I32, ValueType::I32], None),
                 ),
                 ImportType::Function(
-                    "ethereum",
-                    "storageLoad",
+                    "ethereum".into(),
+                    "storageLoad".into(),
                     FunctionType::new(vec![ValueType::I32, ValueType::I32], None),
                 ),
                 ImportType::Function(
-                    "ethereum",
-                    "log",
+                    "ethereum".into(),
+                    "log".into(),
                     FunctionType::new(
                         vec![
                             ValueType::I32,
@@ -249,72 +405,72 @@ impl<'a> ModulePreset for ImportList<'a> {
                     ),
                 ),
                 ImportType::Function(
-                    "ethereum",
-                    "getReturnDataSize",
+                    "ethereum".into(),
+                    "getReturnDataSize".into(),
                     FunctionType::new(vec![], Some(ValueType::I32)),
                 ),
                 ImportType::Function(
-                    "ethereum",
-                    "returnDataCopy",
+                    "ethereum".into(),
+                    "returnDataCopy".into(),
                     FunctionType::new(vec![ValueType::I32, ValueType::I32, ValueType::I32], None),
                 ),
                 ImportType::Function(
-                    "ethereum",
-                    "finish",
+                    "ethereum".into(),
+                    "finish".into(),
                     FunctionType::new(vec![ValueType::I32, ValueType::I32], None),
                 ),
                 ImportType::Function(
-                    "ethereum",
-                    "revert",
+                    "ethereum".into(),
+                    "revert".into(),
                     FunctionType::new(vec![ValueType::I32, ValueType::I32], None),
                 ),
                 ImportType::Function(
-                    "ethereum",
-                    "selfDestruct",
+                    "ethereum".into(),
+                    "selfDestruct".into(),
                     FunctionType::new(vec![ValueType::I32], None),
                 ),
             ])),
             "debug" => Ok(ImportList(vec![
                 ImportType::Function(
-                    "debug",
-                    "print32",
+                    "debug".into(),
+                    "print32".into(),
                     FunctionType::new(vec![ValueType::I32], None),
                 ),
                 ImportType::Function(
-                    "debug",
-                    "print64",
+                    "debug".into(),
+                    "print64".into(),
                     FunctionType::new(vec![ValueType::I64], None),
                 ),
                 ImportType::Function(
-                    "debug",
-                    "printMem",
+                    "debug".into(),
+                    "printMem".into(),
                     FunctionType::new(vec![ValueType::I32, ValueType::I32], None),
                 ),
                 ImportType::Function(
-                    "debug",
-                    "printMemHex",
+                    "debug".into(),
+                    "printMemHex".into(),
                     FunctionType::new(vec![ValueType::I32, ValueType::I32], None),
                 ),
                 ImportType::Function(
-                    "debug",
-                    "printStorage",
+                    "debug".into(),
+                    "printStorage".into(),
                     FunctionType::new(vec![ValueType::I32], None),
                 ),
                 ImportType::Function(
-                    "debug",
-                    "printStorageHex",
+                    "debug".into(),
+                    "printStorageHex".into(),
                     FunctionType::new(vec![ValueType::I32], None),
                 ),
             ])),
             "bignum" => Ok(ImportList(vec![
                 ImportType::Function(
-                    "bignum",
-                    "mul256",
+                    "bignum".into(),
+                    "mul256".into(),
                     FunctionType::new(vec![ValueType::I32, ValueType::I32, ValueType::I32], None),
                 ),
                 ImportType::Function(
-                    "bignum",
-                    "umulmod256",
+                    "bignum".into(),
+                    "umulmod256".into(),
                     FunctionType::new(
                         vec![
                             ValueType::I32,
@@ -330,3 +486,112 @@ impl<'a> ModulePreset for ImportList<'a> {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn definition_yaml() {
+        let definition = "
+imports:
+  - kind: function
+    namespace: ethereum
+    field: useGas
+    params: [i64]
+  - kind: function
+    namespace: ethereum
+    field: getGasLeft
+    result: i64
+  - kind: global
+    namespace: env
+    field: stackTop
+    type: i32
+    mutable: true
+  - kind: memory
+    namespace: env
+    field: memory
+    initial: 1
+    maximum: 2
+  - kind: table
+    namespace: env
+    field: table
+";
+        let list = ImportList::from_definition(definition).unwrap();
+        let entries = list.entries();
+        assert_eq!(entries.len(), 5);
+        match &entries[0] {
+            ImportType::Function(namespace, field, sig) => {
+                assert_eq!(namespace, "ethereum");
+                assert_eq!(field, "useGas");
+                assert_eq!(*sig, FunctionType::new(vec![ValueType::I64], None));
+            }
+            _ => panic!("expected a function"),
+        }
+        match &entries[1] {
+            ImportType::Function(_, _, sig) => {
+                assert_eq!(*sig, FunctionType::new(vec![], Some(ValueType::I64)))
+            }
+            _ => panic!("expected a function"),
+        }
+        match &entries[2] {
+            ImportType::Global(_, _, Some(global_type)) => {
+                assert_eq!(*global_type, GlobalType::new(ValueType::I32, true))
+            }
+            _ => panic!("expected a typed global"),
+        }
+        match &entries[3] {
+            ImportType::Memory(_, _, Some(limits)) => {
+                assert_eq!(limits.initial(), 1);
+                assert_eq!(limits.maximum(), Some(2));
+            }
+            _ => panic!("expected a memory with limits"),
+        }
+        match &entries[4] {
+            ImportType::Table(_, field, None) => assert_eq!(field, "table"),
+            _ => panic!("expected a table without limits"),
+        }
+    }
+
+    #[test]
+    fn definition_json() {
+        let definition = r#"{
+            "imports": [
+                { "kind": "function", "namespace": "debug", "field": "print32", "params": ["i32"] }
+            ]
+        }"#;
+        let list = ImportList::from_definition(definition).unwrap();
+        assert_eq!(list.entries().len(), 1);
+    }
+
+    #[test]
+    fn definition_errors() {
+        // Unknown kind.
+        assert!(
+            ImportList::from_definition("imports: [{kind: func, namespace: env, field: foo}]")
+                .is_err()
+        );
+        // Unknown field.
+        assert!(ImportList::from_definition(
+            "imports: [{kind: function, namespace: env, field: foo, returns: i32}]"
+        )
+        .is_err());
+        // Unknown value type.
+        assert!(ImportList::from_definition(
+            "imports: [{kind: function, namespace: env, field: foo, params: [u32]}]"
+        )
+        .is_err());
+        // Missing namespace.
+        assert!(ImportList::from_definition("imports: [{kind: memory, field: memory}]").is_err());
+        // Maximum without an initial size.
+        assert!(ImportList::from_definition(
+            "imports: [{kind: memory, namespace: env, field: memory, maximum: 4}]"
+        )
+        .is_err());
+        assert!(ImportList::from_definition(
+            "imports: [{kind: table, namespace: env, field: table, maximum: 4}]"
+        )
+        .is_err());
+        assert!(ImportList::from_file("/nonexistent/interface.yml").is_err());
+    }
+}
@@ -1,25 +1,46 @@
 extern crate byteorder;
 extern crate parity_wasm;
+extern crate regex;
 extern crate rustc_hex;
+extern crate serde;
+#[macro_use]
+extern crate serde_derive;
+extern crate serde_json;
+extern crate serde_yaml;
 
 use parity_wasm::elements::Module;
+use serde_yaml::Value;
 
+pub mod callgraph;
+pub mod depgraph;
+pub mod diff;
+pub mod exports;
 pub mod imports;
+pub mod report;
 
+pub mod auditimports;
+pub mod checkfloats;
+pub mod checklimits;
 pub mod checkstartfunc;
 pub mod deployer;
 pub mod dropsection;
+pub mod limitmemory;
+pub mod limitstack;
+pub mod metergas;
 pub mod remapimports;
 pub mod remapstart;
+pub mod renameexports;
 pub mod repack;
+pub mod softfloat;
 pub mod trimexports;
+pub mod trimfunctions;
 pub mod trimstartfunc;
 pub mod verifyexports;
 pub mod verifyimports;
 
-mod depgraph;
 mod utils;
 
+use report::ValidationReport;
 use std::{error, fmt};
 
 #[derive(Eq, PartialEq, Debug)]
@@ -39,12 +60,27 @@ pub trait ModuleTranslator {
     fn translate(&self, module: &Module) -> Result<Option<Module>, ModuleError>;
 
     /// Translates module in-place. Returns true if the module was modified. Can fail with ModuleError::NotSupported.
-    fn translate_inplace(&self, module: &mut Module) -> Result<bool, ModuleError>;
+    ///
+    /// By default, the module is replaced by the result of `translate`, so that it is left
+    /// untouched on failure.
+    fn translate_inplace(&self, module: &mut Module) -> Result<bool, ModuleError> {
+        if let Some(new_module) = self.translate(module)? {
+            *module = new_module;
+            Ok(true)
+        } else {
+            Ok(false)
+        }
+    }
 }
 
 pub trait ModuleValidator {
+    /// Validates module. Returns a report listing every problem found.
+    fn report(&self, module: &Module) -> Result<ValidationReport, ModuleError>;
+
     /// Validates module. Returns true if it is valid or false if invalid.
-    fn validate(&self, module: &Module) -> Result<bool, ModuleError>;
+    fn validate(&self, module: &Module) -> Result<bool, ModuleError> {
+        Ok(self.report(module)?.is_valid())
+    }
 }
 
 pub trait ModulePreset {
@@ -53,6 +89,14 @@ pub trait ModulePreset {
         Self: std::marker::Sized;
 }
 
+pub trait ModuleConfig {
+    /// Constructs the module from a mapping of options. Fails with ModuleError::Custom on unknown
+    /// or mistyped options.
+    fn with_config(config: &Value) -> Result<Self, ModuleError>
+    where
+        Self: std::marker::Sized;
+}
+
 impl From<String> for ModuleError {
     fn from(error: String) -> Self {
         ModuleError::Custom(error)
@@ -118,8 +162,8 @@ mod tests {
     }
 
     impl ModuleValidator for SampleModule {
-        fn validate(&self, module: &Module) -> Result<bool, ModuleError> {
-            Ok(true)
+        fn report(&self, module: &Module) -> Result<ValidationReport, ModuleError> {
+            Ok(ValidationReport::new())
         }
     }
 
@@ -151,6 +195,13 @@ mod tests {
         assert!(result.is_ok());
     }
 
+    #[test]
+    fn validator_report_succeeds() {
+        let validator = SampleModule {};
+        let result = validator.report(&Module::default());
+        assert!(result.unwrap().is_valid());
+    }
+
     #[test]
     fn from_error() {
         let err: ModuleError = "custom message".to_string().into();
@@ -0,0 +1,255 @@
+use super::{
+    report::{Finding, FindingKind, ValidationReport},
+    utils::{fmt_limits, parse_config},
+    ModuleConfig, ModuleError, ModuleTranslator, ModuleValidator,
+};
+use parity_wasm::elements::{External, MemoryType, Module, ResizableLimits};
+use serde_yaml::Value;
+
+/// Size of a page of linear memory, in bytes.
+pub const PAGE_SIZE: usize = 65536;
+
+/// Returns the number of pages to allocate for `len` bytes. A page is always left for data
+/// following them.
+pub fn pages_for(len: usize) -> u32 {
+    (len / PAGE_SIZE) as u32 + 1
+}
+
+/// Struct on which ModuleValidator and ModuleTranslator are implemented. Enforces a budget of
+/// pages on the memories of a module, whether defined or imported.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct LimitMemory {
+    max_pages: u32,
+    /// Whether the translator sets the maximum of defined memories which have none. Maxima above
+    /// the budget are always clamped.
+    set_maximum: bool,
+}
+
+impl LimitMemory {
+    pub fn new(max_pages: u32, set_maximum: bool) -> Self {
+        LimitMemory {
+            max_pages,
+            set_maximum,
+        }
+    }
+
+    /// Returns the finding for limits exceeding the budget, if any. A missing maximum is only
+    /// warned about, as the memory may grow beyond the budget.
+    fn check(&self, limits: &ResizableLimits) -> Option<Finding> {
+        let finding = match limits.maximum() {
+            _ if limits.initial() > self.max_pages => Finding::error(FindingKind::ExcessiveMemory),
+            Some(maximum) if maximum > self.max_pages => {
+                Finding::error(FindingKind::ExcessiveMemory)
+            }
+            None => Finding::warning(FindingKind::UnboundedMemory),
+            _ => return None,
+        };
+        Some(
+            finding
+                .with_expected(format!("at most {} pages", self.max_pages))
+                .with_actual(fmt_limits("memory", limits)),
+        )
+    }
+}
+
+/// Options accepted by `LimitMemory::with_config`.
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Config {
+    max_pages: u32,
+    #[serde(default)]
+    set_maximum: bool,
+}
+
+impl ModuleConfig for LimitMemory {
+    fn with_config(config: &Value) -> Result<Self, ModuleError> {
+        let config: Config = parse_config(config)?;
+        Ok(LimitMemory::new(config.max_pages, config.set_maximum))
+    }
+}
+
+impl ModuleValidator for LimitMemory {
+    fn report(&self, module: &Module) -> Result<ValidationReport, ModuleError> {
+        let mut report = ValidationReport::new();
+        let mut idx = 0;
+        if let Some(section) = module.import_section() {
+            for entry in section.entries() {
+                if let External::Memory(memory_type) = entry.external() {
+                    if let Some(finding) = self.check(memory_type.limits()) {
+                        report.push(
+                            finding
+                                .with_namespace(entry.module())
+                                .with_field(entry.field())
+                                .with_location(format!("memory {}", idx)),
+                        );
+                    }
+                    idx += 1;
+                }
+            }
+        }
+        if let Some(section) = module.memory_section() {
+            for memory_type in section.entries() {
+                if let Some(finding) = self.check(memory_type.limits()) {
+                    report.push(finding.with_location(format!("memory {}", idx)));
+                }
+                idx += 1;
+            }
+        }
+        Ok(report)
+    }
+}
+
+impl ModuleTranslator for LimitMemory {
+    /// Clamps the maximum of defined memories to the budget. Fails if a memory still exceeds it,
+    /// as initial sizes and imported memories cannot be rewritten.
+    fn translate(&self, module: &Module) -> Result<Option<Module>, ModuleError> {
+        let mut ret = module.clone();
+        let mut changed = false;
+        if let Some(section) = ret.memory_section_mut() {
+            for memory_type in section.entries_mut() {
+                let limits = *memory_type.limits();
+                let maximum = match limits.maximum() {
+                    Some(maximum) if maximum > self.max_pages => self.max_pages,
+                    None if self.set_maximum => self.max_pages,
+                    _ => continue,
+                };
+                *memory_type = MemoryType::new(limits.initial(), Some(maximum), limits.shared());
+                changed = true;
+            }
+        }
+
+        if let Some(finding) = self
+            .report(&ret)?
+            .findings()
+            .iter()
+            .find(|finding| finding.is_error())
+        {
+            return Err(ModuleError::Custom(finding.to_string()));
+        }
+        if changed {
+            Ok(Some(ret))
+        } else {
+            Ok(None)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use parity_wasm::elements::deserialize_buffer;
+    use rustc_hex::FromHex;
+
+    fn module(hex: &str) -> Module {
+        let wasm: Vec<u8> = FromHex::from_hex(hex).unwrap();
+        deserialize_buffer::<Module>(&wasm).unwrap()
+    }
+
+    fn describe(report: &ValidationReport) -> Vec<String> {
+        report.findings().iter().map(|f| f.to_string()).collect()
+    }
+
+    fn limits(module: &Module) -> ResizableLimits {
+        *module.memory_section().unwrap().entries()[0].limits()
+    }
+
+    #[test]
+    fn validate_limits() {
+        // wast:
+        // (module (memory 1 8))
+        let bounded = module("0061736d01000000050401010108");
+        let report = LimitMemory::new(4, false).report(&bounded).unwrap();
+        assert_eq!(
+            describe(&report),
+            vec!["error: memory limit exceeded in memory 0, expected at most 4 pages, found (memory 1 8)"]
+        );
+        assert!(LimitMemory::new(8, false).validate(&bounded).unwrap());
+
+        // wast:
+        // (module (memory 2))
+        let unbounded = module("0061736d010000000503010002");
+        let report = LimitMemory::new(4, false).report(&unbounded).unwrap();
+        assert_eq!(
+            describe(&report),
+            vec![
+                "warning: unbounded memory in memory 0, expected at most 4 pages, found (memory 2)"
+            ]
+        );
+        assert!(report.is_valid());
+        assert!(!LimitMemory::new(1, false).validate(&unbounded).unwrap());
+    }
+
+    #[test]
+    fn validate_imported_memory() {
+        // wast:
+        // (module (import "env" "memory" (memory 1)))
+        let imported = module("0061736d01000000020f0103656e76066d656d6f7279020001");
+        let report = LimitMemory::new(0, false).report(&imported).unwrap();
+        assert_eq!(
+            describe(&report),
+            vec!["error: memory limit exceeded env.memory in memory 0, expected at most 0 pages, found (memory 1)"]
+        );
+
+        // Imported memories are not rewritten.
+        assert!(LimitMemory::new(4, true)
+            .translate(&imported)
+            .unwrap()
+            .is_none());
+    }
+
+    #[test]
+    fn clamp_maximum() {
+        let bounded = module("0061736d01000000050401010108");
+        let clamped = LimitMemory::new(4, false)
+            .translate(&bounded)
+            .unwrap()
+            .unwrap();
+        assert_eq!(limits(&clamped), ResizableLimits::new(1, Some(4)));
+        assert!(LimitMemory::new(8, true)
+            .translate(&bounded)
+            .unwrap()
+            .is_none());
+    }
+
+    #[test]
+    fn set_maximum() {
+        let unbounded = module("0061736d010000000503010002");
+        assert!(LimitMemory::new(4, false)
+            .translate(&unbounded)
+            .unwrap()
+            .is_none());
+        let bounded = LimitMemory::new(4, true)
+            .translate(&unbounded)
+            .unwrap()
+            .unwrap();
+        assert_eq!(limits(&bounded), ResizableLimits::new(2, Some(4)));
+
+        // The initial size cannot be rewritten.
+        assert!(LimitMemory::new(1, true).translate(&unbounded).is_err());
+    }
+
+    #[test]
+    fn pages() {
+        assert_eq!(pages_for(0), 1);
+        assert_eq!(pages_for(65535), 1);
+        assert_eq!(pages_for(632232), 10);
+    }
+
+    #[test]
+    fn with_config() {
+        let config = serde_yaml::from_str("{max_pages: 16}").unwrap();
+        assert_eq!(
+            LimitMemory::with_config(&config).unwrap(),
+            LimitMemory::new(16, false)
+        );
+
+        let config = serde_yaml::from_str("{max_pages: 16, set_maximum: true}").unwrap();
+        assert_eq!(
+            LimitMemory::with_config(&config).unwrap(),
+            LimitMemory::new(16, true)
+        );
+
+        let config = serde_yaml::from_str("{}").unwrap();
+        assert!(LimitMemory::with_config(&config).is_err());
+    }
+}
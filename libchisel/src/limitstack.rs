@@ -0,0 +1,498 @@
+use super::utils::{add_global, function_type_indices, invalid_preset, parse_config};
+use super::{ModuleConfig, ModuleError, ModulePreset, ModuleTranslator};
+use parity_wasm::elements::{
+    BlockType, FunctionType, GlobalEntry, GlobalType, ImportCountType, InitExpr, Instruction,
+    Module, Type, ValueType,
+};
+use serde_yaml::Value;
+
+/// Struct on which ModuleTranslator is implemented. Instruments every function to keep track of
+/// the stack height in a global, which is increased on entry by the size of the function's frame
+/// and decreased on exit. Execution traps if the limit would be exceeded. Modules whose functions
+/// all start with this instrumentation are left untouched, so that running the pass twice does not
+/// count every frame twice.
+pub struct LimitStack {
+    /// Maximum stack height, counted in values: parameters, locals and operands.
+    limit: u32,
+}
+
+impl LimitStack {
+    pub fn new(limit: u32) -> Self {
+        LimitStack { limit }
+    }
+}
+
+impl ModulePreset for LimitStack {
+    fn with_preset(preset: &str) -> Result<Self, ()> {
+        match preset {
+            "ewasm" => Ok(LimitStack::new(64 * 1024)),
+            _ => Err(()),
+        }
+    }
+}
+
+/// Options accepted by `LimitStack::with_config`.
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Config {
+    preset: Option<String>,
+    limit: Option<u32>,
+}
+
+impl ModuleConfig for LimitStack {
+    fn with_config(config: &Value) -> Result<Self, ModuleError> {
+        let config: Config = parse_config(config)?;
+        let mut ret = match (config.preset, config.limit) {
+            (Some(preset), _) => {
+                LimitStack::with_preset(&preset).map_err(|_| invalid_preset(&preset))?
+            }
+            (None, Some(limit)) => LimitStack::new(limit),
+            (None, None) => {
+                return Err(ModuleError::Custom(
+                    "Either a preset or a limit must be set".to_string(),
+                ))
+            }
+        };
+        if let Some(limit) = config.limit {
+            ret.limit = limit;
+        }
+        Ok(ret)
+    }
+}
+
+impl ModuleTranslator for LimitStack {
+    fn translate(&self, module: &Module) -> Result<Option<Module>, ModuleError> {
+        let bodies_len = match module.code_section() {
+            Some(section) if !section.bodies().is_empty() => section.bodies().len(),
+            _ => return Ok(None),
+        };
+        if is_instrumented(module) {
+            return Ok(None);
+        }
+
+        let signatures = function_signatures(module)?;
+        let imports_len = module.import_count(ImportCountType::Function);
+        let mut costs = Vec::with_capacity(bodies_len);
+        for (idx, body) in module
+            .code_section()
+            .expect("checked above")
+            .bodies()
+            .iter()
+            .enumerate()
+        {
+            let sig = &signatures[imports_len + idx];
+            let locals: u32 = body.locals().iter().map(|local| local.count()).sum();
+            let height = max_stack_height(body.code().elements(), sig, &signatures, module)?;
+            costs.push(sig.params().len() as u32 + locals + height);
+        }
+
+        let mut ret = module.clone();
+        let global = add_global(
+            &mut ret,
+            GlobalEntry::new(
+                GlobalType::new(ValueType::I32, true),
+                InitExpr::new(vec![Instruction::I32Const(0), Instruction::End]),
+            ),
+        );
+        if let Some(section) = ret.code_section_mut() {
+            for (idx, body) in section.bodies_mut().iter_mut().enumerate() {
+                let result = signatures[imports_len + idx].return_type();
+                let code = body.code_mut().elements_mut();
+                *code = instrument(code, result, costs[idx], self.limit, global);
+            }
+        }
+        Ok(Some(ret))
+    }
+}
+
+/// Returns the signature of every function in the function index space.
+fn function_signatures(module: &Module) -> Result<Vec<FunctionType>, ModuleError> {
+    let types = match module.type_section() {
+        Some(section) => section.types(),
+        None => &[],
+    };
+    function_type_indices(module)
+        .into_iter()
+        .map(|type_idx| match types.get(type_idx as usize) {
+            Some(Type::Function(sig)) => Ok(sig.clone()),
+            None => Err(ModuleError::Custom(format!(
+                "Invalid type index: {}",
+                type_idx
+            ))),
+        })
+        .collect()
+}
+
+/// Returns true if every function body starts with the prologue added by `instrument`, using the
+/// same global.
+fn is_instrumented(module: &Module) -> bool {
+    use parity_wasm::elements::Instruction::*;
+
+    let bodies = match module.code_section() {
+        Some(section) => section.bodies(),
+        None => return false,
+    };
+    let globals: Vec<Option<u32>> = bodies
+        .iter()
+        .map(|body| match body.code().elements() {
+            [GetGlobal(a), I32Const(_), I32Add, SetGlobal(b), GetGlobal(c), I32Const(_), I32GtU, If(BlockType::NoResult), Unreachable, End, Block(_), ..]
+                if a == b && b == c =>
+            {
+                Some(*a)
+            }
+            _ => None,
+        })
+        .collect();
+    match globals.first() {
+        Some(Some(global)) => globals.iter().all(|other| *other == Some(*global)),
+        _ => false,
+    }
+}
+
+/// Wraps the body of a function in a block, so that every exit passes through the code which
+/// restores the stack height. Returns become branches out of that block.
+fn instrument(
+    code: &[Instruction],
+    result: Option<ValueType>,
+    cost: u32,
+    limit: u32,
+    global: u32,
+) -> Vec<Instruction> {
+    use parity_wasm::elements::Instruction::*;
+
+    let mut ret = vec![
+        GetGlobal(global),
+        I32Const(cost as i32),
+        I32Add,
+        SetGlobal(global),
+        GetGlobal(global),
+        I32Const(limit as i32),
+        I32GtU,
+        If(BlockType::NoResult),
+        Unreachable,
+        End,
+        Block(match result {
+            Some(value_type) => BlockType::Value(value_type),
+            None => BlockType::NoResult,
+        }),
+    ];
+
+    // The final `end` of the body closes the wrapping block instead.
+    let mut depth = 0;
+    for instr in code {
+        match instr {
+            Block(_) | Loop(_) | If(_) => depth += 1,
+            End => depth -= 1,
+            _ => (),
+        }
+        ret.push(match instr {
+            Return => Br(depth as u32),
+            other => other.clone(),
+        });
+    }
+
+    ret.extend(vec![
+        GetGlobal(global),
+        I32Const(cost as i32),
+        I32Sub,
+        SetGlobal(global),
+        End,
+    ]);
+    ret
+}
+
+/// A block, loop, if or the function body itself.
+struct Frame {
+    /// Operand stack height when the frame was entered.
+    start: u32,
+    /// Number of values left on the stack when the frame ends.
+    arity: u32,
+}
+
+/// Computes the maximum height of the operand stack during the execution of a function body. Code
+/// following an unconditional branch is assumed to start with the stack of the enclosing frame.
+fn max_stack_height(
+    code: &[Instruction],
+    sig: &FunctionType,
+    signatures: &[FunctionType],
+    module: &Module,
+) -> Result<u32, ModuleError> {
+    use parity_wasm::elements::Instruction::*;
+
+    let mut frames = vec![Frame {
+        start: 0,
+        arity: arity(sig.return_type()),
+    }];
+    let mut height: u32 = 0;
+    let mut max: u32 = 0;
+    for instr in code {
+        let start = match frames.last() {
+            Some(frame) => frame.start,
+            None => return Err(ModuleError::Custom("Unbalanced function body".to_string())),
+        };
+        let (pops, pushes) = match instr {
+            Block(block_type) | Loop(block_type) => {
+                frames.push(Frame {
+                    start: height,
+                    arity: block_arity(*block_type),
+                });
+                continue;
+            }
+            If(block_type) => {
+                height = height.saturating_sub(1).max(start);
+                frames.push(Frame {
+                    start: height,
+                    arity: block_arity(*block_type),
+                });
+                continue;
+            }
+            Else => {
+                height = start;
+                continue;
+            }
+            End => {
+                let frame = frames.pop().expect("checked above");
+                height = frame.start + frame.arity;
+                max = max.max(height);
+                continue;
+            }
+            Br(_) | BrTable(_) | Return | Unreachable => {
+                height = start;
+                continue;
+            }
+            Call(idx) => match signatures.get(*idx as usize) {
+                Some(callee) => (callee.params().len() as u32, arity(callee.return_type())),
+                None => return Err(ModuleError::Custom(format!("Invalid function: {}", idx))),
+            },
+            CallIndirect(type_idx, _) => match module
+                .type_section()
+                .and_then(|section| section.types().get(*type_idx as usize))
+            {
+                Some(Type::Function(callee)) => (
+                    callee.params().len() as u32 + 1,
+                    arity(callee.return_type()),
+                ),
+                None => {
+                    return Err(ModuleError::Custom(format!(
+                        "Invalid type index: {}",
+                        type_idx
+                    )))
+                }
+            },
+            other => match stack_effect(other) {
+                Some(effect) => effect,
+                None => {
+                    return Err(ModuleError::Custom(format!(
+                        "Unsupported instruction: {}",
+                        other
+                    )))
+                }
+            },
+        };
+        height = height.saturating_sub(pops).max(start) + pushes;
+        max = max.max(height);
+    }
+    Ok(max)
+}
+
+fn arity(result: Option<ValueType>) -> u32 {
+    if result.is_some() {
+        1
+    } else {
+        0
+    }
+}
+
+fn block_arity(block_type: BlockType) -> u32 {
+    match block_type {
+        BlockType::Value(_) => 1,
+        BlockType::NoResult => 0,
+    }
+}
+
+/// Returns the number of operands popped and pushed by a non-control instruction.
+fn stack_effect(instruction: &Instruction) -> Option<(u32, u32)> {
+    use parity_wasm::elements::Instruction::*;
+    match instruction {
+        Nop => Some((0, 0)),
+        Drop => Some((1, 0)),
+        Select => Some((3, 1)),
+        BrIf(_) => Some((1, 0)),
+        GetLocal(_) | GetGlobal(_) => Some((0, 1)),
+        SetLocal(_) | SetGlobal(_) => Some((1, 0)),
+        TeeLocal(_) => Some((1, 1)),
+        I32Load(..) | I64Load(..) | F32Load(..) | F64Load(..) | I32Load8S(..) | I32Load8U(..)
+        | I32Load16S(..) | I32Load16U(..) | I64Load8S(..) | I64Load8U(..) | I64Load16S(..)
+        | I64Load16U(..) | I64Load32S(..) | I64Load32U(..) => Some((1, 1)),
+        I32Store(..) | I64Store(..) | F32Store(..) | F64Store(..) | I32Store8(..)
+        | I32Store16(..) | I64Store8(..) | I64Store16(..) | I64Store32(..) => Some((2, 0)),
+        CurrentMemory(_) => Some((0, 1)),
+        GrowMemory(_) => Some((1, 1)),
+        I32Const(_) | I64Const(_) | F32Const(_) | F64Const(_) => Some((0, 1)),
+        I32Eqz | I64Eqz => Some((1, 1)),
+        I32Eq | I32Ne | I32LtS | I32LtU | I32GtS | I32GtU | I32LeS | I32LeU | I32GeS | I32GeU
+        | I64Eq | I64Ne | I64LtS | I64LtU | I64GtS | I64GtU | I64LeS | I64LeU | I64GeS | I64GeU
+        | F32Eq | F32Ne | F32Lt | F32Gt | F32Le | F32Ge | F64Eq | F64Ne | F64Lt | F64Gt | F64Le
+        | F64Ge => Some((2, 1)),
+        I32Clz | I32Ctz | I32Popcnt | I64Clz | I64Ctz | I64Popcnt => Some((1, 1)),
+        I32Add | I32Sub | I32Mul | I32DivS | I32DivU | I32RemS | I32RemU | I32And | I32Or
+        | I32Xor | I32Shl | I32ShrS | I32ShrU | I32Rotl | I32Rotr | I64Add | I64Sub | I64Mul
+        | I64DivS | I64DivU | I64RemS | I64RemU | I64And | I64Or | I64Xor | I64Shl | I64ShrS
+        | I64ShrU | I64Rotl | I64Rotr => Some((2, 1)),
+        F32Abs | F32Neg | F32Ceil | F32Floor | F32Trunc | F32Nearest | F32Sqrt | F64Abs
+        | F64Neg | F64Ceil | F64Floor | F64Trunc | F64Nearest | F64Sqrt => Some((1, 1)),
+        F32Add | F32Sub | F32Mul | F32Div | F32Min | F32Max | F32Copysign | F64Add | F64Sub
+        | F64Mul | F64Div | F64Min | F64Max | F64Copysign => Some((2, 1)),
+        I32WrapI64 | I32TruncSF32 | I32TruncUF32 | I32TruncSF64 | I32TruncUF64 | I64ExtendSI32
+        | I64ExtendUI32 | I64TruncSF32 | I64TruncUF32 | I64TruncSF64 | I64TruncUF64
+        | F32ConvertSI32 | F32ConvertUI32 | F32ConvertSI64 | F32ConvertUI64 | F32DemoteF64
+        | F64ConvertSI32 | F64ConvertUI32 | F64ConvertSI64 | F64ConvertUI64 | F64PromoteF32 => {
+            Some((1, 1))
+        }
+        I32ReinterpretF32 | I64ReinterpretF64 | F32ReinterpretI32 | F64ReinterpretI64 => {
+            Some((1, 1))
+        }
+        I32Extend8S | I32Extend16S | I64Extend8S | I64Extend16S | I64Extend32S => Some((1, 1)),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use parity_wasm::elements::deserialize_buffer;
+    use rustc_hex::FromHex;
+    use Instruction::*;
+
+    // wast:
+    // (module
+    //   (export "fac" (func $fac))
+    //   (export "early" (func $early))
+    //   (func $fac (param i64) (result i64)
+    //     (if (result i64) (i64.eqz (local.get 0))
+    //       (then (i64.const 1))
+    //       (else (i64.mul (local.get 0) (call $fac (i64.sub (local.get 0) (i64.const 1)))))))
+    //   (func $early (param i32) (result i32)
+    //     (local i64 i64)
+    //     (block (br_if 0 (local.get 0)) (return (i32.const 1)))
+    //     (i32.const 0))
+    // )
+    fn sample_module() -> Module {
+        let wasm: Vec<u8> = FromHex::from_hex(
+            "
+            0061736d01000000010b0260017e017e60017f017f0303020001070f0203
+            6661630000056561726c7900010a28021500200050047e42010520002000
+            42017d10007e0b0b1001027e024020000d0041010f0b41000b0014046e61
+            6d65010d02000366616301056561726c79
+        ",
+        )
+        .unwrap();
+        deserialize_buffer::<Module>(&wasm).unwrap()
+    }
+
+    fn code(module: &Module, idx: usize) -> Vec<Instruction> {
+        module.code_section().unwrap().bodies()[idx]
+            .code()
+            .elements()
+            .to_vec()
+    }
+
+    #[test]
+    fn stack_height() {
+        let module = sample_module();
+        let signatures = function_signatures(&module).unwrap();
+        let heights: Vec<u32> = module
+            .code_section()
+            .unwrap()
+            .bodies()
+            .iter()
+            .zip(signatures.iter())
+            .map(|(body, sig)| {
+                max_stack_height(body.code().elements(), sig, &signatures, &module).unwrap()
+            })
+            .collect();
+        assert_eq!(heights, vec![3, 1]);
+    }
+
+    #[test]
+    fn instrument_functions() {
+        let module = LimitStack::new(100)
+            .translate(&sample_module())
+            .unwrap()
+            .unwrap();
+
+        let global = &module.global_section().unwrap().entries()[0];
+        assert_eq!(global.global_type(), &GlobalType::new(ValueType::I32, true));
+
+        // One parameter, two locals and an operand stack of one value.
+        assert_eq!(
+            code(&module, 1),
+            vec![
+                GetGlobal(0),
+                I32Const(4),
+                I32Add,
+                SetGlobal(0),
+                GetGlobal(0),
+                I32Const(100),
+                I32GtU,
+                If(BlockType::NoResult),
+                Unreachable,
+                End,
+                Block(BlockType::Value(ValueType::I32)),
+                Block(BlockType::NoResult),
+                GetLocal(0),
+                BrIf(0),
+                I32Const(1),
+                Br(1),
+                End,
+                I32Const(0),
+                End,
+                GetGlobal(0),
+                I32Const(4),
+                I32Sub,
+                SetGlobal(0),
+                End,
+            ]
+        );
+
+        // Recursive calls are unaffected.
+        assert!(code(&module, 0).contains(&Call(0)));
+    }
+
+    #[test]
+    fn already_instrumented() {
+        let pass = LimitStack::new(100);
+        let instrumented = pass.translate(&sample_module()).unwrap().unwrap();
+        assert_eq!(pass.translate(&instrumented).unwrap(), None);
+
+        let mut module = instrumented;
+        assert!(!pass.translate_inplace(&mut module).unwrap());
+        assert_eq!(module.global_section().unwrap().entries().len(), 1);
+        assert_eq!(code(&module, 1).len(), 24);
+    }
+
+    #[test]
+    fn unsupported_instruction() {
+        let mut module = sample_module();
+        module.code_section_mut().unwrap().bodies_mut()[0]
+            .code_mut()
+            .elements_mut()
+            .insert(0, F32x4Splat);
+        assert!(LimitStack::new(100).translate(&module).is_err());
+    }
+
+    #[test]
+    fn with_config() {
+        let config = serde_yaml::from_str("{preset: ewasm}").unwrap();
+        assert_eq!(LimitStack::with_config(&config).unwrap().limit, 65536);
+
+        let config = serde_yaml::from_str("{preset: ewasm, limit: 1024}").unwrap();
+        assert_eq!(LimitStack::with_config(&config).unwrap().limit, 1024);
+
+        let config = serde_yaml::from_str("{}").unwrap();
+        assert!(LimitStack::with_config(&config).is_err());
+
+        let config = serde_yaml::from_str("{preset: foo}").unwrap();
+        assert!(LimitStack::with_config(&config).is_err());
+    }
+}
@@ -0,0 +1,462 @@
+use std::collections::HashMap;
+use std::fs::read_to_string;
+use std::mem;
+use std::path::Path;
+
+use super::utils::{add_function_imports, add_type, fmt_func_type, invalid_preset, parse_config};
+use super::{ModuleConfig, ModuleError, ModulePreset, ModuleTranslator};
+use parity_wasm::elements::{
+    External, FunctionType, ImportEntry, Instruction, Module, Type, ValueType,
+};
+use serde_yaml::Value;
+
+/// Host function charging gas, called with the cost of each metered block.
+const GAS_NAMESPACE: &str = "ethereum";
+const GAS_FIELD: &str = "useGas";
+
+/// Gas cost of each instruction, keyed by its mnemonic as printed by parity-wasm, e.g. `i32.add`,
+/// `get_local` or `call`. Instructions which are not listed cost the default.
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct CostTable {
+    #[serde(default = "CostTable::default_cost")]
+    default: u32,
+    #[serde(default)]
+    costs: HashMap<String, u32>,
+}
+
+impl CostTable {
+    pub fn new(default: u32) -> Self {
+        CostTable {
+            default,
+            costs: HashMap::new(),
+        }
+    }
+
+    fn default_cost() -> u32 {
+        1
+    }
+
+    /// Parses a cost table written in YAML or JSON.
+    pub fn from_definition(definition: &str) -> Result<Self, ModuleError> {
+        Ok(serde_yaml::from_str(definition)?)
+    }
+
+    /// Loads a cost table from a YAML or JSON file.
+    pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Self, ModuleError> {
+        let definition = read_to_string(&path).map_err(|e| {
+            ModuleError::Custom(format!(
+                "Failed to read cost table {}: {}",
+                path.as_ref().display(),
+                e
+            ))
+        })?;
+        CostTable::from_definition(&definition)
+    }
+
+    pub fn set_default(&mut self, cost: u32) {
+        self.default = cost;
+    }
+
+    pub fn insert(&mut self, mnemonic: &str, cost: u32) {
+        self.costs.insert(mnemonic.to_string(), cost);
+    }
+
+    /// Adds all costs of another table, which take precedence over existing ones. The default
+    /// cost is kept.
+    pub fn extend(&mut self, other: CostTable) {
+        self.costs.extend(other.costs);
+    }
+
+    pub fn cost(&self, instruction: &Instruction) -> u32 {
+        let rendered = instruction.to_string();
+        let mnemonic = rendered.split_whitespace().next().unwrap_or_default();
+        *self.costs.get(mnemonic).unwrap_or(&self.default)
+    }
+}
+
+/// Struct on which ModuleTranslator is implemented. Splits every function into basic blocks and
+/// charges the cost of each block by calling `ethereum.useGas` on entry. The import is added if
+/// missing. Modules which already call `ethereum.useGas` are considered metered and left
+/// untouched, so that running the pass twice does not charge every block twice.
+pub struct MeterGas {
+    costs: CostTable,
+}
+
+impl MeterGas {
+    pub fn new(costs: CostTable) -> Self {
+        MeterGas { costs }
+    }
+}
+
+impl ModulePreset for MeterGas {
+    fn with_preset(preset: &str) -> Result<Self, ()> {
+        match preset {
+            "ewasm" => Ok(MeterGas::new(CostTable::new(1))),
+            _ => Err(()),
+        }
+    }
+}
+
+/// Options accepted by `MeterGas::with_config`. Costs are applied on top of the preset, and
+/// inline ones on top of those loaded from a file.
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Config {
+    preset: Option<String>,
+    default_cost: Option<u32>,
+    costs: Option<HashMap<String, u32>>,
+    /// Path to a cost table file.
+    cost_table: Option<String>,
+}
+
+impl ModuleConfig for MeterGas {
+    fn with_config(config: &Value) -> Result<Self, ModuleError> {
+        let config: Config = parse_config(config)?;
+        let mut costs = match config.preset {
+            Some(preset) => {
+                MeterGas::with_preset(&preset)
+                    .map_err(|_| invalid_preset(&preset))?
+                    .costs
+            }
+            None => CostTable::new(CostTable::default_cost()),
+        };
+        if let Some(path) = config.cost_table {
+            let table = CostTable::from_file(path)?;
+            costs.set_default(table.default);
+            costs.extend(table);
+        }
+        if let Some(cost) = config.default_cost {
+            costs.set_default(cost);
+        }
+        for (mnemonic, cost) in config.costs.unwrap_or_default() {
+            costs.insert(&mnemonic, cost);
+        }
+        Ok(MeterGas::new(costs))
+    }
+}
+
+impl ModuleTranslator for MeterGas {
+    fn translate(&self, module: &Module) -> Result<Option<Module>, ModuleError> {
+        let bodies = match module.code_section() {
+            Some(section) => section.bodies(),
+            None => return Ok(None),
+        };
+        if let Some(use_gas) = find_use_gas(module)? {
+            let metered = bodies
+                .iter()
+                .flat_map(|body| body.code().elements())
+                .any(|instr| *instr == Instruction::Call(use_gas));
+            if metered {
+                return Ok(None);
+            }
+        }
+
+        let mut ret = module.clone();
+        let use_gas = use_gas_index(&mut ret)?;
+        let mut charged = false;
+        if let Some(section) = ret.code_section_mut() {
+            for body in section.bodies_mut() {
+                charged |= meter_code(body.code_mut().elements_mut(), &self.costs, use_gas);
+            }
+        }
+        // Nothing is charged if every block is free, in which case the import is not needed
+        // either.
+        if charged {
+            Ok(Some(ret))
+        } else {
+            Ok(None)
+        }
+    }
+}
+
+fn use_gas_signature() -> FunctionType {
+    FunctionType::new(vec![ValueType::I64], None)
+}
+
+/// Returns the function index of `ethereum.useGas` if it is imported.
+fn find_use_gas(module: &Module) -> Result<Option<u32>, ModuleError> {
+    let signature = use_gas_signature();
+    let types = match module.type_section() {
+        Some(section) => section.types(),
+        None => &[],
+    };
+    if let Some(section) = module.import_section() {
+        let existing = section
+            .entries()
+            .iter()
+            .filter_map(|entry| match entry.external() {
+                External::Function(type_idx) => Some((entry, *type_idx)),
+                _ => None,
+            })
+            .enumerate()
+            .find(|(_, (entry, _))| entry.module() == GAS_NAMESPACE && entry.field() == GAS_FIELD);
+        if let Some((idx, (_, type_idx))) = existing {
+            return match types.get(type_idx as usize) {
+                Some(Type::Function(sig)) if *sig == signature => Ok(Some(idx as u32)),
+                Some(Type::Function(sig)) => Err(ModuleError::Custom(format!(
+                    "Imported {}.{} has signature {}, expected {}",
+                    GAS_NAMESPACE,
+                    GAS_FIELD,
+                    fmt_func_type(sig),
+                    fmt_func_type(&signature)
+                ))),
+                None => Err(ModuleError::Custom(format!(
+                    "Invalid type index: {}",
+                    type_idx
+                ))),
+            };
+        }
+    }
+    Ok(None)
+}
+
+/// Returns the function index of `ethereum.useGas`, importing it if necessary.
+fn use_gas_index(module: &mut Module) -> Result<u32, ModuleError> {
+    if let Some(idx) = find_use_gas(module)? {
+        return Ok(idx);
+    }
+    let type_idx = add_type(module, use_gas_signature());
+    add_function_imports(
+        module,
+        vec![ImportEntry::new(
+            GAS_NAMESPACE.to_string(),
+            GAS_FIELD.to_string(),
+            External::Function(type_idx),
+        )],
+    )
+}
+
+/// Returns true if the instruction ends a basic block: the next instruction may be reached other
+/// than by falling through, or not at all.
+fn ends_block(instruction: &Instruction) -> bool {
+    matches!(
+        instruction,
+        Instruction::Loop(_)
+            | Instruction::If(_)
+            | Instruction::Else
+            | Instruction::End
+            | Instruction::Br(_)
+            | Instruction::BrIf(_)
+            | Instruction::BrTable(_)
+            | Instruction::Return
+            | Instruction::Unreachable
+    )
+}
+
+/// Charges the cost of each basic block at its start. Blocks which cost nothing are not charged.
+/// Returns true if any block was charged.
+fn meter_code(code: &mut Vec<Instruction>, costs: &CostTable, use_gas: u32) -> bool {
+    let mut ret = Vec::with_capacity(code.len());
+    let mut block = Vec::new();
+    let mut cost: u64 = 0;
+    let mut charged = false;
+    for instr in mem::take(code) {
+        cost += u64::from(costs.cost(&instr));
+        let ends = ends_block(&instr);
+        block.push(instr);
+        if ends {
+            charged |= charge_block(&mut ret, &mut block, cost, use_gas);
+            cost = 0;
+        }
+    }
+    charged |= charge_block(&mut ret, &mut block, cost, use_gas);
+    *code = ret;
+    charged
+}
+
+fn charge_block(
+    code: &mut Vec<Instruction>,
+    block: &mut Vec<Instruction>,
+    cost: u64,
+    use_gas: u32,
+) -> bool {
+    let charged = cost > 0;
+    if charged {
+        code.push(Instruction::I64Const(cost as i64));
+        code.push(Instruction::Call(use_gas));
+    }
+    code.append(block);
+    charged
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::function_names;
+    use parity_wasm::elements::{deserialize_buffer, BlockType, Internal};
+    use rustc_hex::FromHex;
+    use Instruction::*;
+
+    // wast:
+    // (module
+    //   (import "ethereum" "finish" (func $finish (param i32 i32)))
+    //   (memory 1)
+    //   (export "main" (func $main))
+    //   (func $main
+    //     (local i32)
+    //     (loop $l
+    //       (local.set 0 (i32.add (local.get 0) (i32.const 1)))
+    //       (br_if $l (i32.lt_u (local.get 0) (i32.const 10))))
+    //     (call $finish (i32.const 0) (i32.const 0)))
+    // )
+    fn loop_module() -> Module {
+        let wasm: Vec<u8> = FromHex::from_hex(
+            "
+            0061736d0100000001090260027f7f006000000213010865746865726575
+            6d0666696e6973680000030201010503010001070801046d61696e00010a
+            1d011b01017f0340200041016a21002000410a490d000b4100410010000b
+            001e046e616d65010f02000666696e69736801046d61696e030601010100
+            016c
+        ",
+        )
+        .unwrap();
+        deserialize_buffer::<Module>(&wasm).unwrap()
+    }
+
+    fn code(module: &Module) -> Vec<Instruction> {
+        module.code_section().unwrap().bodies()[0]
+            .code()
+            .elements()
+            .to_vec()
+    }
+
+    #[test]
+    fn meter_basic_blocks() {
+        let module = MeterGas::with_preset("ewasm")
+            .unwrap()
+            .translate(&loop_module())
+            .unwrap()
+            .unwrap();
+
+        let import = &module.import_section().unwrap().entries()[1];
+        assert_eq!((import.module(), import.field()), ("ethereum", "useGas"));
+        let export = &module.export_section().unwrap().entries()[0];
+        assert_eq!(export.internal(), &Internal::Function(2));
+        assert_eq!(
+            function_names(&module).get(&2).map(String::as_str),
+            Some("main")
+        );
+
+        assert_eq!(
+            code(&module),
+            vec![
+                I64Const(1),
+                Call(1),
+                Loop(BlockType::NoResult),
+                I64Const(8),
+                Call(1),
+                GetLocal(0),
+                I32Const(1),
+                I32Add,
+                SetLocal(0),
+                GetLocal(0),
+                I32Const(10),
+                I32LtU,
+                BrIf(0),
+                I64Const(1),
+                Call(1),
+                End,
+                I64Const(4),
+                Call(1),
+                I32Const(0),
+                I32Const(0),
+                Call(0),
+                End,
+            ]
+        );
+    }
+
+    #[test]
+    fn custom_costs() {
+        let config =
+            serde_yaml::from_str("{default_cost: 2, costs: {end: 0, loop: 0, i32.add: 5}}")
+                .unwrap();
+        let module = MeterGas::with_config(&config)
+            .unwrap()
+            .translate(&loop_module())
+            .unwrap()
+            .unwrap();
+        let charges: Vec<Instruction> = code(&module)
+            .into_iter()
+            .filter(|instr| matches!(instr, I64Const(_)))
+            .collect();
+        assert_eq!(charges, vec![I64Const(19), I64Const(6)]);
+    }
+
+    #[test]
+    fn existing_import() {
+        // wast:
+        // (module
+        //   (import "ethereum" "useGas" (func $useGas (param i64)))
+        //   (export "main" (func $main))
+        //   (func $main (nop))
+        // )
+        let wasm: Vec<u8> = FromHex::from_hex(
+            "
+            0061736d0100000001080260017e0060000002130108657468657265756d
+            06757365476173000003020101070801046d61696e00010a05010300010b
+            0016046e616d65010f02000675736547617301046d61696e
+        ",
+        )
+        .unwrap();
+        let module = deserialize_buffer::<Module>(&wasm).unwrap();
+        let metered = MeterGas::new(CostTable::new(1))
+            .translate(&module)
+            .unwrap()
+            .unwrap();
+        assert_eq!(metered.import_section().unwrap().entries().len(), 1);
+        assert_eq!(code(&metered), vec![I64Const(2), Call(0), Nop, End]);
+
+        // The existing import must have the expected signature.
+        let mut module = module;
+        module.type_section_mut().unwrap().types_mut()[0] =
+            Type::Function(FunctionType::new(vec![ValueType::I32], None));
+        assert!(MeterGas::new(CostTable::new(1)).translate(&module).is_err());
+    }
+
+    #[test]
+    fn already_metered() {
+        let pass = MeterGas::with_preset("ewasm").unwrap();
+        let metered = pass.translate(&loop_module()).unwrap().unwrap();
+        assert_eq!(pass.translate(&metered).unwrap(), None);
+
+        let mut module = metered;
+        assert!(!pass.translate_inplace(&mut module).unwrap());
+        assert_eq!(code(&module).len(), 22);
+    }
+
+    #[test]
+    fn nothing_charged() {
+        let mut costs = CostTable::new(0);
+        costs.insert("call", 0);
+        assert_eq!(
+            MeterGas::new(costs).translate(&loop_module()).unwrap(),
+            None
+        );
+    }
+
+    #[test]
+    fn cost_table() {
+        let table = CostTable::from_definition("{costs: {call: 10, i32.const: 0}}").unwrap();
+        assert_eq!(table.cost(&Call(3)), 10);
+        assert_eq!(table.cost(&I32Const(5)), 0);
+        assert_eq!(table.cost(&I64Const(5)), 1);
+        assert!(CostTable::from_definition("{cost: {}}").is_err());
+    }
+
+    #[test]
+    fn with_config() {
+        let config = serde_yaml::from_str("{preset: ewasm}").unwrap();
+        assert_eq!(
+            MeterGas::with_config(&config).unwrap().costs,
+            CostTable::new(1)
+        );
+
+        let config = serde_yaml::from_str("{preset: foo}").unwrap();
+        assert!(MeterGas::with_config(&config).is_err());
+
+        let config = serde_yaml::from_str("{cost_table: /nonexistent/costs.yml}").unwrap();
+        assert!(MeterGas::with_config(&config).is_err());
+    }
+}
@@ -1,16 +1,68 @@
 use std::collections::HashMap;
+use std::fs::read_to_string;
+use std::path::Path;
 
-use super::{ModuleError, ModulePreset, ModuleTranslator};
+use super::utils::{invalid_preset, parse_config};
+use super::{ModuleConfig, ModuleError, ModulePreset, ModuleTranslator};
 use parity_wasm::elements::*;
+use regex::Regex;
+use serde_yaml::Value;
 
-#[derive(Hash, Eq, PartialEq, Debug, Clone)]
+#[derive(Hash, Eq, PartialEq, Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
 pub struct ImportPair {
+    #[serde(rename = "namespace")]
     pub module: String,
     pub field: String,
 }
 
+/// Single entry of a translation table, as found in the configuration or a translations file.
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct TranslationDefinition {
+    from: ImportPair,
+    to: ImportPair,
+}
+
+/// Translation rule as found in the configuration or a translations file.
+#[derive(Deserialize)]
+#[serde(tag = "kind", rename_all = "lowercase", deny_unknown_fields)]
+enum RuleDefinition {
+    Prefix {
+        namespace: Option<String>,
+        prefix: String,
+        to_namespace: String,
+        #[serde(default = "default_strip_prefix")]
+        strip_prefix: bool,
+    },
+    Namespace {
+        namespace: String,
+        to_namespace: String,
+    },
+    Regex {
+        namespace: String,
+        field: String,
+        to_namespace: String,
+        to_field: String,
+    },
+}
+
+fn default_strip_prefix() -> bool {
+    true
+}
+
+/// Translations file, listing explicit import translations and translation rules.
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct TranslationsDefinition {
+    #[serde(default)]
+    translations: Vec<TranslationDefinition>,
+    #[serde(default)]
+    rules: Vec<RuleDefinition>,
+}
+
 impl ImportPair {
-    fn new(module: &str, field: &str) -> ImportPair {
+    pub fn new(module: &str, field: &str) -> ImportPair {
         ImportPair {
             module: module.to_string(),
             field: field.to_string(),
@@ -18,179 +70,193 @@ impl ImportPair {
     }
 }
 
-#[derive(Default)]
-pub struct Translations {
-    translations: HashMap<ImportPair, ImportPair>,
+/// Rule translating every import it matches, as opposed to a single explicit translation.
+#[derive(Debug, Clone)]
+pub enum TranslationRule {
+    /// Moves imports whose field starts with a prefix to another namespace, optionally removing
+    /// the prefix from the field. If a namespace is given, only imports from it are matched.
+    Prefix {
+        namespace: Option<String>,
+        prefix: String,
+        to_namespace: String,
+        strip_prefix: bool,
+    },
+    /// Moves all imports of a namespace to another namespace.
+    Namespace {
+        namespace: String,
+        to_namespace: String,
+    },
+    /// Matches the namespace and the field against regular expressions. Capture groups of each
+    /// expression can be referenced in the respective replacement, e.g. `$1`.
+    Regex {
+        namespace: Regex,
+        field: Regex,
+        to_namespace: String,
+        to_field: String,
+    },
 }
 
-impl ModulePreset for Translations {
-    fn with_preset(preset: &str) -> Result<Self, ()> {
-        match preset {
-            "ewasm" => {
-                let trans: HashMap<ImportPair, ImportPair> = [
-                    (
-                        ImportPair::new("env", "ethereum_useGas"),
-                        ImportPair::new("ethereum", "useGas"),
-                    ),
-                    (
-                        ImportPair::new("env", "ethereum_getGasLeft"),
-                        ImportPair::new("ethereum", "getGasLeft"),
-                    ),
-                    (
-                        ImportPair::new("env", "ethereum_getAddress"),
-                        ImportPair::new("ethereum", "getAddress"),
-                    ),
-                    (
-                        ImportPair::new("env", "ethereum_getBlockHash"),
-                        ImportPair::new("ethereum", "getBlockHash"),
-                    ),
-                    (
-                        ImportPair::new("env", "ethereum_getBlockCoinbase"),
-                        ImportPair::new("ethereum", "getBlockCoinbase"),
-                    ),
-                    (
-                        ImportPair::new("env", "ethereum_getBlockDifficulty"),
-                        ImportPair::new("ethereum", "getBlockDifficulty"),
-                    ),
-                    (
-                        ImportPair::new("env", "ethereum_getBlockGasLimit"),
-                        ImportPair::new("ethereum", "getBlockGasLimit"),
-                    ),
-                    (
-                        ImportPair::new("env", "ethereum_getBlockNumber"),
-                        ImportPair::new("ethereum", "getBlockNumber"),
-                    ),
-                    (
-                        ImportPair::new("env", "ethereum_getBlockTimestamp"),
-                        ImportPair::new("ethereum", "getBlockTimestamp"),
-                    ),
-                    (
-                        ImportPair::new("env", "ethereum_getExternalBalance"),
-                        ImportPair::new("ethereum", "getExternalBalance"),
-                    ),
-                    (
-                        ImportPair::new("env", "ethereum_getTxGasPrice"),
-                        ImportPair::new("ethereum", "getTxGasPrice"),
-                    ),
-                    (
-                        ImportPair::new("env", "ethereum_getTxOrigin"),
-                        ImportPair::new("ethereum", "getTxOrigin"),
-                    ),
-                    (
-                        ImportPair::new("env", "ethereum_getCaller"),
-                        ImportPair::new("ethereum", "getCaller"),
-                    ),
-                    (
-                        ImportPair::new("env", "ethereum_getCallDataSize"),
-                        ImportPair::new("ethereum", "getCallDataSize"),
-                    ),
-                    (
-                        ImportPair::new("env", "ethereum_getCallValue"),
-                        ImportPair::new("ethereum", "getCallValue"),
-                    ),
-                    (
-                        ImportPair::new("env", "ethereum_callDataCopy"),
-                        ImportPair::new("ethereum", "callDataCopy"),
-                    ),
-                    (
-                        ImportPair::new("env", "ethereum_getCodeSize"),
-                        ImportPair::new("ethereum", "getCodeSize"),
-                    ),
-                    (
-                        ImportPair::new("env", "ethereum_getExternalCodeSize"),
-                        ImportPair::new("ethereum", "getExternalCodeSize"),
-                    ),
-                    (
-                        ImportPair::new("env", "ethereum_externalCodeCopy"),
-                        ImportPair::new("ethereum", "externalCodeCopy"),
-                    ),
-                    (
-                        ImportPair::new("env", "ethereum_codeCopy"),
-                        ImportPair::new("ethereum", "codeCopy"),
-                    ),
-                    (
-                        ImportPair::new("env", "ethereum_getReturnDataSize"),
-                        ImportPair::new("ethereum", "getReturnDataSize"),
-                    ),
-                    (
-                        ImportPair::new("env", "ethereum_returnDataCopy"),
-                        ImportPair::new("ethereum", "returnDataCopy"),
-                    ),
-                    (
-                        ImportPair::new("env", "ethereum_create"),
-                        ImportPair::new("ethereum", "create"),
-                    ),
-                    (
-                        ImportPair::new("env", "ethereum_call"),
-                        ImportPair::new("ethereum", "call"),
-                    ),
-                    (
-                        ImportPair::new("env", "ethereum_callCode"),
-                        ImportPair::new("ethereum", "callCode"),
-                    ),
-                    (
-                        ImportPair::new("env", "ethereum_callDelegate"),
-                        ImportPair::new("ethereum", "callDelegate"),
-                    ),
-                    (
-                        ImportPair::new("env", "ethereum_callStatic"),
-                        ImportPair::new("ethereum", "callStatic"),
-                    ),
-                    (
-                        ImportPair::new("env", "ethereum_storageLoad"),
-                        ImportPair::new("ethereum", "storageLoad"),
-                    ),
-                    (
-                        ImportPair::new("env", "ethereum_log"),
-                        ImportPair::new("ethereum", "log"),
-                    ),
-                    (
-                        ImportPair::new("env", "ethereum_storageStore"),
-                        ImportPair::new("ethereum", "storageStore"),
-                    ),
-                    (
-                        ImportPair::new("env", "ethereum_revert"),
-                        ImportPair::new("ethereum", "revert"),
-                    ),
-                    (
-                        ImportPair::new("env", "ethereum_finish"),
-                        ImportPair::new("ethereum", "finish"),
-                    ),
-                    (
-                        ImportPair::new("env", "ethereum_selfDestruct"),
-                        ImportPair::new("ethereum", "selfDestruct"),
-                    ),
-                ]
-                .iter()
-                .cloned()
-                .collect();
-                Ok(Translations {
-                    translations: trans,
+impl TranslationRule {
+    /// Returns the translation of an import if the rule matches it.
+    pub fn apply(&self, pair: &ImportPair) -> Option<ImportPair> {
+        match self {
+            TranslationRule::Prefix {
+                namespace,
+                prefix,
+                to_namespace,
+                strip_prefix,
+            } => {
+                if let Some(namespace) = namespace {
+                    if *namespace != pair.module {
+                        return None;
+                    }
+                }
+                if !pair.field.starts_with(prefix.as_str()) {
+                    return None;
+                }
+                let field = if *strip_prefix {
+                    &pair.field[prefix.len()..]
+                } else {
+                    &pair.field
+                };
+                Some(ImportPair::new(to_namespace, field))
+            }
+            TranslationRule::Namespace {
+                namespace,
+                to_namespace,
+            } => {
+                if *namespace == pair.module {
+                    Some(ImportPair::new(to_namespace, &pair.field))
+                } else {
+                    None
+                }
+            }
+            TranslationRule::Regex {
+                namespace,
+                field,
+                to_namespace,
+                to_field,
+            } => {
+                let namespace_captures = namespace.captures(&pair.module)?;
+                let field_captures = field.captures(&pair.field)?;
+                let mut new_namespace = String::new();
+                namespace_captures.expand(to_namespace, &mut new_namespace);
+                let mut new_field = String::new();
+                field_captures.expand(to_field, &mut new_field);
+                Some(ImportPair {
+                    module: new_namespace,
+                    field: new_field,
                 })
             }
-            _ => Err(()),
         }
     }
 }
 
+fn compile_regex(pattern: &str) -> Result<Regex, ModuleError> {
+    Regex::new(pattern)
+        .map_err(|e| ModuleError::Custom(format!("Invalid regular expression {}: {}", pattern, e)))
+}
+
+impl RuleDefinition {
+    fn into_rule(self) -> Result<TranslationRule, ModuleError> {
+        Ok(match self {
+            RuleDefinition::Prefix {
+                namespace,
+                prefix,
+                to_namespace,
+                strip_prefix,
+            } => TranslationRule::Prefix {
+                namespace,
+                prefix,
+                to_namespace,
+                strip_prefix,
+            },
+            RuleDefinition::Namespace {
+                namespace,
+                to_namespace,
+            } => TranslationRule::Namespace {
+                namespace,
+                to_namespace,
+            },
+            RuleDefinition::Regex {
+                namespace,
+                field,
+                to_namespace,
+                to_field,
+            } => TranslationRule::Regex {
+                namespace: compile_regex(&namespace)?,
+                field: compile_regex(&field)?,
+                to_namespace,
+                to_field,
+            },
+        })
+    }
+}
+
+/// Table of import translations. Explicit translations take precedence over rules, and rules
+/// are tried in order until one matches.
+#[derive(Default)]
+pub struct Translations {
+    translations: HashMap<ImportPair, ImportPair>,
+    rules: Vec<TranslationRule>,
+}
+
 impl Translations {
-    fn insert(&mut self, from_module: &str, from_field: &str, to_module: &str, to_field: &str) {
+    pub fn new() -> Self {
+        Translations {
+            translations: HashMap::new(),
+            rules: Vec::new(),
+        }
+    }
+
+    /// Parses a translation table written in YAML or JSON.
+    pub fn from_definition(definition: &str) -> Result<Self, ModuleError> {
+        let definition: TranslationsDefinition = serde_yaml::from_str(definition)?;
+        let mut ret = Translations::from(definition.translations);
+        for rule in definition.rules {
+            ret.push_rule(rule.into_rule()?);
+        }
+        Ok(ret)
+    }
+
+    /// Loads a translation table from a YAML or JSON file.
+    pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Self, ModuleError> {
+        let definition = read_to_string(&path).map_err(|e| {
+            ModuleError::Custom(format!(
+                "Failed to read translations {}: {}",
+                path.as_ref().display(),
+                e
+            ))
+        })?;
+        Translations::from_definition(&definition)
+    }
+
+    /// Adds all translations and rules of another table. Translations of the other table take
+    /// precedence over existing ones for the same import, and its rules are tried first.
+    pub fn extend(&mut self, other: Translations) {
+        self.translations.extend(other.translations);
+        let mut rules = other.rules;
+        rules.append(&mut self.rules);
+        self.rules = rules;
+    }
+
+    pub fn insert(&mut self, from_module: &str, from_field: &str, to_module: &str, to_field: &str) {
         self.translations.insert(
             ImportPair::new(from_module, from_field),
             ImportPair::new(to_module, to_field),
         );
     }
 
-    //    fn get_simple(&self, module: &str, field: &str) -> Option<&str, &str> {
-    //        if let Some(translation) = self.translations.get(&ImportPair::new(module, field)) {
-    //            Some(translation.module.clone(), translation.field.clone())
-    //        } else {
-    //            None
-    //        }
-    //    }
+    /// Appends a rule, which is tried after all existing ones.
+    pub fn push_rule(&mut self, rule: TranslationRule) {
+        self.rules.push(rule);
+    }
 
-    fn get(&self, pair: &ImportPair) -> Option<&ImportPair> {
-        self.translations.get(&pair)
+    fn get(&self, pair: &ImportPair) -> Option<ImportPair> {
+        if let Some(translation) = self.translations.get(pair) {
+            return Some(translation.clone());
+        }
+        self.rules.iter().filter_map(|rule| rule.apply(pair)).next()
     }
 }
 
@@ -198,17 +264,91 @@ pub struct RemapImports {
     translations: Translations,
 }
 
-impl ModulePreset for RemapImports {
+impl ModulePreset for Translations {
     fn with_preset(preset: &str) -> Result<Self, ()> {
         match preset {
-            "ewasm" => Ok(RemapImports {
-                translations: Translations::with_preset("ewasm").unwrap(),
-            }),
+            // Every `env.ethereum_X` import becomes `ethereum.X`.
+            "ewasm" => {
+                let mut translations = Translations::new();
+                translations.push_rule(TranslationRule::Prefix {
+                    namespace: Some("env".to_string()),
+                    prefix: "ethereum_".to_string(),
+                    to_namespace: "ethereum".to_string(),
+                    strip_prefix: true,
+                });
+                Ok(translations)
+            }
             _ => Err(()),
         }
     }
 }
 
+impl ModulePreset for RemapImports {
+    fn with_preset(preset: &str) -> Result<Self, ()> {
+        Ok(RemapImports {
+            translations: Translations::with_preset(preset)?,
+        })
+    }
+}
+
+impl From<Vec<TranslationDefinition>> for Translations {
+    fn from(definitions: Vec<TranslationDefinition>) -> Self {
+        Translations {
+            translations: definitions.into_iter().map(|t| (t.from, t.to)).collect(),
+            rules: Vec::new(),
+        }
+    }
+}
+
+impl RemapImports {
+    pub fn new(translations: Translations) -> Self {
+        RemapImports { translations }
+    }
+}
+
+/// Options accepted by `RemapImports::with_config`. Explicit translations and rules are applied
+/// on top of the preset, and inline ones on top of those loaded from a file.
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Config {
+    preset: Option<String>,
+    translations: Option<Vec<TranslationDefinition>>,
+    rules: Option<Vec<RuleDefinition>>,
+    /// Path to a translations file.
+    translations_file: Option<String>,
+}
+
+impl ModuleConfig for RemapImports {
+    fn with_config(config: &Value) -> Result<Self, ModuleError> {
+        let config: Config = parse_config(config)?;
+        if config.preset.is_none()
+            && config.translations.is_none()
+            && config.rules.is_none()
+            && config.translations_file.is_none()
+        {
+            return Err(ModuleError::Custom(
+                "Either a preset, translations or rules must be set".to_string(),
+            ));
+        }
+
+        let mut translations = match config.preset {
+            Some(preset) => {
+                Translations::with_preset(&preset).map_err(|_| invalid_preset(&preset))?
+            }
+            None => Translations::new(),
+        };
+        if let Some(path) = config.translations_file {
+            translations.extend(Translations::from_file(path)?);
+        }
+        let mut inline = Translations::from(config.translations.unwrap_or_default());
+        for rule in config.rules.unwrap_or_default() {
+            inline.push_rule(rule.into_rule()?);
+        }
+        translations.extend(inline);
+        Ok(RemapImports::new(translations))
+    }
+}
+
 impl ModuleTranslator for RemapImports {
     fn translate_inplace(&self, module: &mut Module) -> Result<bool, ModuleError> {
         Ok(rename_imports(module, &self.translations))
@@ -232,11 +372,7 @@ fn rename_imports(module: &mut Module, translations: &Translations) -> bool {
                 translations.get(&ImportPair::new(entry.module(), entry.field()))
             {
                 ret = true;
-                *entry = ImportEntry::new(
-                    replacement.module.clone(),
-                    replacement.field.clone(),
-                    *entry.external(),
-                )
+                *entry = ImportEntry::new(replacement.module, replacement.field, *entry.external())
             }
         }
     }
@@ -382,4 +518,218 @@ mod tests {
 
         assert_eq!(verified, true);
     }
+
+    #[test]
+    fn remap_with_config() {
+        // wast:
+        // (module
+        //   (import "env" "ethereum_useGas" (func (param i64)))
+        //   (memory 1)
+        //   (export "main" (func $main))
+        //   (export "memory" (memory 0))
+        //
+        //   (func $main)
+        // )
+        let wasm: Vec<u8> = vec![
+            0x00, 0x61, 0x73, 0x6d, 0x01, 0x00, 0x00, 0x00, 0x01, 0x08, 0x02, 0x60, 0x01, 0x7e,
+            0x00, 0x60, 0x00, 0x00, 0x02, 0x17, 0x01, 0x03, 0x65, 0x6e, 0x76, 0x0f, 0x65, 0x74,
+            0x68, 0x65, 0x72, 0x65, 0x75, 0x6d, 0x5f, 0x75, 0x73, 0x65, 0x47, 0x61, 0x73, 0x00,
+            0x00, 0x03, 0x02, 0x01, 0x01, 0x05, 0x03, 0x01, 0x00, 0x01, 0x07, 0x11, 0x02, 0x04,
+            0x6d, 0x61, 0x69, 0x6e, 0x00, 0x01, 0x06, 0x6d, 0x65, 0x6d, 0x6f, 0x72, 0x79, 0x02,
+            0x00, 0x0a, 0x04, 0x01, 0x02, 0x00, 0x0b,
+        ];
+
+        let module: Module = parity_wasm::deserialize_buffer(&wasm).unwrap();
+
+        // Inline translations override the preset.
+        let config = serde_yaml::from_str(
+            "
+preset: ewasm
+translations:
+  - from: {namespace: env, field: ethereum_useGas}
+    to: {namespace: host, field: gas}
+",
+        )
+        .unwrap();
+        let new = RemapImports::with_config(&config)
+            .unwrap()
+            .translate(&module)
+            .unwrap()
+            .expect("module was not modified");
+        let import = &new.import_section().unwrap().entries()[0];
+        assert_eq!(import.module(), "host");
+        assert_eq!(import.field(), "gas");
+
+        // Translations without a preset.
+        let config = serde_yaml::from_str(
+            "
+translations:
+  - from: {namespace: env, field: foo}
+    to: {namespace: host, field: foo}
+",
+        )
+        .unwrap();
+        let new = RemapImports::with_config(&config)
+            .unwrap()
+            .translate(&module)
+            .unwrap();
+        assert!(new.is_none());
+    }
+
+    #[test]
+    fn translations_file() {
+        let path = std::env::temp_dir().join("chisel_remapimports_translations.json");
+        std::fs::write(
+            &path,
+            r#"{"translations": [{"from": {"namespace": "env", "field": "a"}, "to": {"namespace": "b", "field": "c"}}]}"#,
+        )
+        .unwrap();
+
+        let translations = Translations::from_file(&path).unwrap();
+        assert_eq!(
+            translations.get(&ImportPair::new("env", "a")),
+            Some(ImportPair::new("b", "c"))
+        );
+
+        let mut config = serde_yaml::Mapping::new();
+        config.insert(
+            Value::String("translations_file".to_string()),
+            Value::String(path.to_str().unwrap().to_string()),
+        );
+        assert!(RemapImports::with_config(&Value::Mapping(config)).is_ok());
+
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn translations_preset() {
+        let translations = Translations::with_preset("ewasm").unwrap();
+        assert_eq!(
+            translations.get(&ImportPair::new("env", "ethereum_useGas")),
+            Some(ImportPair::new("ethereum", "useGas"))
+        );
+        assert_eq!(translations.get(&ImportPair::new("env", "memory")), None);
+        assert!(Translations::with_preset("foo").is_err());
+    }
+
+    #[test]
+    fn invalid_config() {
+        let config = serde_yaml::from_str("{}").unwrap();
+        assert!(RemapImports::with_config(&config).is_err());
+
+        let config = serde_yaml::from_str("{preset: foo}").unwrap();
+        assert!(RemapImports::with_config(&config).is_err());
+
+        let config =
+            serde_yaml::from_str("{translations: [{from: {namespace: env, field: a}}]}").unwrap();
+        assert!(RemapImports::with_config(&config).is_err());
+    }
+
+    #[test]
+    fn translation_rules() {
+        let translations = Translations::from_definition(
+            r#"
+translations:
+  - from: {namespace: env, field: ethereum_finish}
+    to: {namespace: host, field: finish}
+rules:
+  - kind: prefix
+    namespace: env
+    prefix: ethereum_
+    to_namespace: ethereum
+  - kind: prefix
+    prefix: debug_
+    to_namespace: debug
+    strip_prefix: false
+  - kind: namespace
+    namespace: wasi_unstable
+    to_namespace: wasi
+  - kind: regex
+    namespace: "^(.*)_env$"
+    field: "^get_(.*)$"
+    to_namespace: "$1"
+    to_field: "${1}Get"
+"#,
+        )
+        .unwrap();
+
+        let get = |module, field| translations.get(&ImportPair::new(module, field));
+        // Explicit translations take precedence over rules.
+        assert_eq!(
+            get("env", "ethereum_finish"),
+            Some(ImportPair::new("host", "finish"))
+        );
+        assert_eq!(
+            get("env", "ethereum_useGas"),
+            Some(ImportPair::new("ethereum", "useGas"))
+        );
+        assert_eq!(get("other", "ethereum_useGas"), None);
+        assert_eq!(
+            get("env", "debug_print32"),
+            Some(ImportPair::new("debug", "debug_print32"))
+        );
+        assert_eq!(
+            get("wasi_unstable", "fd_write"),
+            Some(ImportPair::new("wasi", "fd_write"))
+        );
+        assert_eq!(
+            get("foo_env", "get_bar"),
+            Some(ImportPair::new("foo", "barGet"))
+        );
+        assert_eq!(get("foo_env", "set_bar"), None);
+    }
+
+    #[test]
+    fn remap_with_rules_config() {
+        // wast:
+        // (module
+        //   (import "env" "ethereum_useGas" (func (param i64)))
+        //   (memory 1)
+        //   (export "main" (func $main))
+        //   (export "memory" (memory 0))
+        //
+        //   (func $main)
+        // )
+        let wasm: Vec<u8> = vec![
+            0x00, 0x61, 0x73, 0x6d, 0x01, 0x00, 0x00, 0x00, 0x01, 0x08, 0x02, 0x60, 0x01, 0x7e,
+            0x00, 0x60, 0x00, 0x00, 0x02, 0x17, 0x01, 0x03, 0x65, 0x6e, 0x76, 0x0f, 0x65, 0x74,
+            0x68, 0x65, 0x72, 0x65, 0x75, 0x6d, 0x5f, 0x75, 0x73, 0x65, 0x47, 0x61, 0x73, 0x00,
+            0x00, 0x03, 0x02, 0x01, 0x01, 0x05, 0x03, 0x01, 0x00, 0x01, 0x07, 0x11, 0x02, 0x04,
+            0x6d, 0x61, 0x69, 0x6e, 0x00, 0x01, 0x06, 0x6d, 0x65, 0x6d, 0x6f, 0x72, 0x79, 0x02,
+            0x00, 0x0a, 0x04, 0x01, 0x02, 0x00, 0x0b,
+        ];
+
+        let module: Module = parity_wasm::deserialize_buffer(&wasm).unwrap();
+
+        // Inline rules are tried before the preset rule.
+        let config = serde_yaml::from_str(
+            "
+preset: ewasm
+rules:
+  - kind: regex
+    namespace: ^env$
+    field: ^ethereum_(.*)$
+    to_namespace: eth
+    to_field: $1
+",
+        )
+        .unwrap();
+        let new = RemapImports::with_config(&config)
+            .unwrap()
+            .translate(&module)
+            .unwrap()
+            .expect("module was not modified");
+        let import = &new.import_section().unwrap().entries()[0];
+        assert_eq!(import.module(), "eth");
+        assert_eq!(import.field(), "useGas");
+
+        let config = serde_yaml::from_str(
+            "{rules: [{kind: regex, namespace: '(', field: '.*', to_namespace: a, to_field: b}]}",
+        )
+        .unwrap();
+        assert!(RemapImports::with_config(&config).is_err());
+
+        let config = serde_yaml::from_str("{rules: [{kind: prefix, to_namespace: a}]}").unwrap();
+        assert!(RemapImports::with_config(&config).is_err());
+    }
 }
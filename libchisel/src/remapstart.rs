@@ -1,27 +1,90 @@
 use parity_wasm::elements::*;
 
-use super::{ModuleError, ModulePreset, ModuleTranslator};
+use super::utils::{
+    add_function, export_function, function_signature, invalid_preset, parse_config,
+};
+use super::{ModuleConfig, ModuleError, ModulePreset, ModuleTranslator};
+use serde_yaml::Value;
+
+/// Struct on which ModuleTranslator is implemented. Replaces the start function with an exported
+/// entry point.
+pub struct RemapStart {
+    /// Name under which the start function is exported.
+    export_name: String,
+    /// Name under which an existing export with `export_name` is kept, instead of being replaced.
+    keep_existing_as: Option<String>,
+    /// Whether to export a new function calling the start function and then the existing entry
+    /// point, so that neither is lost.
+    wrap: bool,
+}
+
+impl RemapStart {
+    pub fn new(export_name: &str) -> Self {
+        RemapStart {
+            export_name: export_name.to_string(),
+            keep_existing_as: None,
+            wrap: false,
+        }
+    }
 
-pub struct RemapStart;
+    pub fn set_keep_existing_as(&mut self, arg: Option<String>) {
+        self.keep_existing_as = arg;
+    }
+
+    pub fn set_wrap(&mut self, arg: bool) {
+        self.wrap = arg;
+    }
+}
 
 impl ModulePreset for RemapStart {
     fn with_preset(preset: &str) -> Result<Self, ()> {
         match preset {
-            // TODO refactor this later
-            "ewasm" => Ok(RemapStart {}),
+            "ewasm" => Ok(RemapStart::new("main")),
+            "pwasm" => Ok(RemapStart::new("_call")),
             _ => Err(()),
         }
     }
 }
 
-impl ModuleTranslator for RemapStart {
-    fn translate_inplace(&self, module: &mut Module) -> Result<bool, ModuleError> {
-        Ok(remap_start(module))
+/// Options accepted by `RemapStart::with_config`.
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Config {
+    preset: Option<String>,
+    /// Overrides the export name of the preset.
+    export_name: Option<String>,
+    keep_existing_as: Option<String>,
+    #[serde(default)]
+    wrap: bool,
+}
+
+impl ModuleConfig for RemapStart {
+    fn with_config(config: &Value) -> Result<Self, ModuleError> {
+        let config: Config = parse_config(config)?;
+        let mut ret = match (&config.preset, &config.export_name) {
+            (Some(preset), _) => {
+                RemapStart::with_preset(preset).map_err(|_| invalid_preset(preset))?
+            }
+            (None, Some(export_name)) => RemapStart::new(export_name),
+            (None, None) => {
+                return Err(ModuleError::Custom(
+                    "Either a preset or an export name must be set".to_string(),
+                ))
+            }
+        };
+        if let Some(export_name) = config.export_name {
+            ret.export_name = export_name;
+        }
+        ret.keep_existing_as = config.keep_existing_as;
+        ret.wrap = config.wrap;
+        Ok(ret)
     }
+}
 
+impl ModuleTranslator for RemapStart {
     fn translate(&self, module: &Module) -> Result<Option<Module>, ModuleError> {
         let mut ret = module.clone();
-        if remap_start(&mut ret) {
+        if self.remap_start(&mut ret)? {
             Ok(Some(ret))
         } else {
             Ok(None)
@@ -29,75 +92,76 @@ impl ModuleTranslator for RemapStart {
     }
 }
 
-// NOTE: This seems to be exported properly in later versions of parity-wasm.
-// TODO: When updated, use the proper method instead.
-fn section_order(s: &Section) -> u8 {
-    match s {
-        Section::Custom(_) => 0x00,
-        Section::Unparsed { .. } => 0x00,
-        Section::Type(_) => 0x1,
-        Section::Import(_) => 0x2,
-        Section::Function(_) => 0x3,
-        Section::Table(_) => 0x4,
-        Section::Memory(_) => 0x5,
-        Section::Global(_) => 0x6,
-        Section::Export(_) => 0x7,
-        Section::Start(_) => 0x8,
-        Section::Element(_) => 0x9,
-        Section::Code(_) => 0x0a,
-        Section::Data(_) => 0x0b,
-        Section::Name(_) => 0x00,
-        Section::Reloc(_) => 0x00,
-    }
-}
-
-/// Replace an exported function with another function, or export if unexported.
-fn remap_or_export_main(module: &mut Module, export_name: &str, func_idx: u32) {
-    let new_func_export = ExportEntry::new(export_name.to_string(), Internal::Function(func_idx));
-
-    if let Some(export_section) = module.export_section_mut() {
-        let export_section = export_section.entries_mut();
-        // If we find an export named `export_name`, replace it. Otherwise, append an entry to the
-        // section with the supplied func index.
-        if let Some(main_export_loc) = export_section
-            .iter_mut()
-            .position(|e| *e.field() == export_name.to_string())
-        {
-            export_section[main_export_loc] = new_func_export;
-        } else {
-            export_section.push(new_func_export);
-        }
-    } else {
-        let sections = module.sections_mut();
-        let new_export_section =
-            Section::Export(ExportSection::with_entries(vec![new_func_export]));
-
-        // If we can find a section that is supposed to be after exports, insert the new section at its position (shifts other elements to the right).
-        // Otherwise, append it at the end.
-        // NOTE: Assumes that the ordering of sections is otherwise correct.
-        if let Some(exports_loc) = sections.iter().position(|sec| section_order(&sec) > 0x7) {
-            sections.insert(exports_loc, new_export_section);
-        } else {
-            sections.push(new_export_section);
+impl RemapStart {
+    fn remap_start(&self, module: &mut Module) -> Result<bool, ModuleError> {
+        let start_func_idx = match module.start_section() {
+            Some(idx) => idx,
+            None => return Ok(false),
+        };
+        let existing = module.export_section().and_then(|section| {
+            section
+                .entries()
+                .iter()
+                .find(|e| e.field() == self.export_name)
+                .cloned()
+        });
+
+        if let (Some(existing), Some(name)) = (&existing, &self.keep_existing_as) {
+            let section = module
+                .export_section_mut()
+                .expect("an export was just found");
+            if section.entries().iter().any(|e| e.field() == name) {
+                return Err(ModuleError::Custom(format!(
+                    "Cannot keep export {} as {}, which is already exported",
+                    self.export_name, name
+                )));
+            }
+            section
+                .entries_mut()
+                .push(ExportEntry::new(name.clone(), *existing.internal()));
         }
-    }
-}
-
-fn remap_start(module: &mut Module) -> bool {
-    if let Some(start_func_idx) = module.start_section() {
-        // Look for an export "main". If found, replace it with an export of the function to
-        // which the start section points.
-        remap_or_export_main(module, "main", start_func_idx);
 
-        // Remove the start section, leaving the "main" export as the entry point.
+        let entry_func_idx = match (&existing, self.wrap) {
+            (Some(existing), true) => match existing.internal() {
+                Internal::Function(main_idx) => wrap(module, start_func_idx, *main_idx)?,
+                _ => {
+                    return Err(ModuleError::Custom(format!(
+                        "Cannot wrap export {}, which is not a function",
+                        self.export_name
+                    )))
+                }
+            },
+            _ => start_func_idx,
+        };
+
+        // Look for an export with the name. If found, replace it with an export of the entry
+        // point, which is the start function unless wrapped.
+        export_function(module, &self.export_name, entry_func_idx);
+
+        // Remove the start section, leaving the export as the entry point.
         module.clear_start_section();
 
-        true
-    } else {
-        false
+        Ok(true)
     }
 }
 
+/// Adds a function with the signature of the entry point, which calls the start function and
+/// then forwards its parameters to the entry point. Returns the index of the new function.
+fn wrap(module: &mut Module, start_func_idx: u32, main_idx: u32) -> Result<u32, ModuleError> {
+    let sig = function_signature(module, main_idx)
+        .cloned()
+        .ok_or_else(|| ModuleError::Custom(format!("Invalid function index: {}", main_idx)))?;
+    let mut code = vec![Instruction::Call(start_func_idx)];
+    code.extend((0..sig.params().len() as u32).map(Instruction::GetLocal));
+    code.push(Instruction::Call(main_idx));
+    code.push(Instruction::End);
+    Ok(add_function(
+        module,
+        sig,
+        FuncBody::new(Vec::new(), Instructions::new(code)),
+    ))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -301,4 +365,121 @@ mod tests {
             .find(|e| e.field() == "main")
             .is_some());
     }
+
+    //wat:
+    //(module
+    //    (import "env" "ethereum_useGas" (func (param i64)))
+    //    (memory 1)
+    //    (export "main" (func $main))
+    //    (export "memory" (memory 0))
+    //    (func $main)
+    //    (func $main2)
+    //    (start $main2)
+    //)
+    fn start_module() -> Module {
+        let wasm: Vec<u8> = FromHex::from_hex(
+            "0061736d0100000001080260017e0060
+000002170103656e760f657468657265756d5f75736547617300000303020101050301000107110
+2046d61696e0001066d656d6f727902000801020a070202000b02000b0020046e616d65010e0201
+046d61696e02056d61696e320209030001000001000200",
+        )
+        .unwrap();
+        Module::from_slice(&wasm)
+    }
+
+    fn exports(module: &Module) -> Vec<(&str, Internal)> {
+        module
+            .export_section()
+            .unwrap()
+            .entries()
+            .iter()
+            .map(|e| (e.field(), *e.internal()))
+            .collect()
+    }
+
+    #[test]
+    fn remapstart_export_name() {
+        let new = RemapStart::new("deploy")
+            .translate(&start_module())
+            .unwrap()
+            .unwrap();
+        assert!(new.start_section().is_none());
+        assert_eq!(
+            exports(&new),
+            vec![
+                ("main", Internal::Function(1)),
+                ("memory", Internal::Memory(0)),
+                ("deploy", Internal::Function(2)),
+            ]
+        );
+    }
+
+    #[test]
+    fn remapstart_keep_existing() {
+        let mut remapper = RemapStart::with_preset("ewasm").unwrap();
+        remapper.set_keep_existing_as(Some("old_main".to_string()));
+        let new = remapper.translate(&start_module()).unwrap().unwrap();
+        assert_eq!(
+            exports(&new),
+            vec![
+                ("main", Internal::Function(2)),
+                ("memory", Internal::Memory(0)),
+                ("old_main", Internal::Function(1)),
+            ]
+        );
+
+        // Keeping the export under an existing name would replace another export.
+        remapper.set_keep_existing_as(Some("memory".to_string()));
+        let mut module = start_module();
+        assert!(remapper.translate_inplace(&mut module).is_err());
+        assert!(module.start_section().is_some());
+    }
+
+    #[test]
+    fn remapstart_wrap() {
+        let mut remapper = RemapStart::with_preset("ewasm").unwrap();
+        remapper.set_wrap(true);
+        let new = remapper.translate(&start_module()).unwrap().unwrap();
+        assert!(new.start_section().is_none());
+        assert_eq!(
+            exports(&new),
+            vec![
+                ("main", Internal::Function(3)),
+                ("memory", Internal::Memory(0)),
+            ]
+        );
+        let bodies = new.code_section().unwrap().bodies();
+        assert_eq!(bodies.len(), 3);
+        assert_eq!(
+            bodies[2].code().elements(),
+            &[Instruction::Call(2), Instruction::Call(1), Instruction::End]
+        );
+        assert_eq!(new.function_section().unwrap().entries()[2].type_ref(), 1);
+
+        // Without an existing entry point, the start function is exported as is.
+        let mut remapper = RemapStart::new("deploy");
+        remapper.set_wrap(true);
+        let new = remapper.translate(&start_module()).unwrap().unwrap();
+        assert_eq!(new.code_section().unwrap().bodies().len(), 2);
+        assert_eq!(exports(&new)[2], ("deploy", Internal::Function(2)));
+    }
+
+    #[test]
+    fn remapstart_with_config() {
+        let config =
+            serde_yaml::from_str("{export_name: deploy, keep_existing_as: old, wrap: true}")
+                .unwrap();
+        let remapper = RemapStart::with_config(&config).unwrap();
+        assert_eq!(remapper.export_name, "deploy");
+        assert_eq!(remapper.keep_existing_as, Some("old".to_string()));
+        assert!(remapper.wrap);
+
+        let config = serde_yaml::from_str("{preset: pwasm}").unwrap();
+        let remapper = RemapStart::with_config(&config).unwrap();
+        assert_eq!(remapper.export_name, "_call");
+        assert!(!remapper.wrap);
+
+        let config = serde_yaml::from_str("{wrap: true}").unwrap();
+        assert!(RemapStart::with_config(&config).is_err());
+    }
 }
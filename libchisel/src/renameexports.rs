@@ -0,0 +1,222 @@
+use std::collections::HashMap;
+
+use super::{
+    utils::{invalid_preset, parse_config},
+    ModuleConfig, ModuleError, ModulePreset, ModuleTranslator,
+};
+use parity_wasm::elements::Module;
+use serde_yaml::Value;
+
+/// Struct on which ModuleTranslator is implemented. Renames exports according to a mapping from
+/// their current names to new ones, e.g. to match the entry point expected by a chain.
+pub struct RenameExports {
+    renames: HashMap<String, String>,
+}
+
+impl RenameExports {
+    pub fn new(renames: HashMap<String, String>) -> Self {
+        RenameExports { renames }
+    }
+
+    /// Returns the name an export has after renaming.
+    fn renamed<'a>(&'a self, field: &'a str) -> &'a str {
+        self.renames.get(field).map_or(field, String::as_str)
+    }
+}
+
+impl ModulePreset for RenameExports {
+    fn with_preset(preset: &str) -> Result<Self, ()> {
+        let (from, to) = match preset {
+            // Exposes a pwasm entry point as an ewasm one, and vice versa.
+            "ewasm" => ("_call", "main"),
+            "pwasm" => ("main", "_call"),
+            _ => return Err(()),
+        };
+        let mut renames = HashMap::new();
+        renames.insert(from.to_string(), to.to_string());
+        Ok(RenameExports::new(renames))
+    }
+}
+
+/// Options accepted by `RenameExports::with_config`.
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Config {
+    preset: Option<String>,
+    /// Map of current export names to new ones, applied after the preset.
+    renames: Option<HashMap<String, String>>,
+}
+
+impl ModuleConfig for RenameExports {
+    fn with_config(config: &Value) -> Result<Self, ModuleError> {
+        let config: Config = parse_config(config)?;
+        let mut ret = match (&config.preset, &config.renames) {
+            (Some(preset), _) => {
+                RenameExports::with_preset(preset).map_err(|_| invalid_preset(preset))?
+            }
+            (None, Some(_)) => RenameExports::new(HashMap::new()),
+            (None, None) => {
+                return Err(ModuleError::Custom(
+                    "Either a preset or renames must be set".to_string(),
+                ))
+            }
+        };
+        if let Some(renames) = config.renames {
+            ret.renames.extend(renames);
+        }
+        Ok(ret)
+    }
+}
+
+impl ModuleTranslator for RenameExports {
+    /// Renames all exports at once, so that names can be swapped. Fails if two exports would end
+    /// up with the same name, whether both are renamed or one of them already has it.
+    fn translate(&self, module: &Module) -> Result<Option<Module>, ModuleError> {
+        let entries = match module.export_section() {
+            Some(section) => section.entries(),
+            None => return Ok(None),
+        };
+
+        let mut fields: HashMap<&str, &str> = HashMap::new();
+        for entry in entries {
+            let renamed = self.renamed(entry.field());
+            if let Some(other) = fields.insert(renamed, entry.field()) {
+                return Err(ModuleError::Custom(format!(
+                    "Exports {} and {} would both be named {}",
+                    other,
+                    entry.field(),
+                    renamed
+                )));
+            }
+        }
+        if entries
+            .iter()
+            .all(|entry| self.renamed(entry.field()) == entry.field())
+        {
+            return Ok(None);
+        }
+
+        let mut ret = module.clone();
+        if let Some(section) = ret.export_section_mut() {
+            for entry in section.entries_mut() {
+                let renamed = self.renamed(entry.field()).to_string();
+                *entry.field_mut() = renamed;
+            }
+        }
+        Ok(Some(ret))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use parity_wasm::builder;
+
+    fn sample_module() -> Module {
+        builder::module()
+            .function()
+            .signature()
+            .build()
+            .body()
+            .build()
+            .build()
+            .export()
+            .field("main")
+            .internal()
+            .func(0)
+            .build()
+            .export()
+            .field("memory")
+            .internal()
+            .memory(0)
+            .build()
+            .export()
+            .field("deploy")
+            .internal()
+            .func(0)
+            .build()
+            .build()
+    }
+
+    fn export_names(module: &Module) -> Vec<&str> {
+        module
+            .export_section()
+            .unwrap()
+            .entries()
+            .iter()
+            .map(|entry| entry.field())
+            .collect()
+    }
+
+    fn renames(pairs: &[(&str, &str)]) -> RenameExports {
+        RenameExports::new(
+            pairs
+                .iter()
+                .map(|(from, to)| (from.to_string(), to.to_string()))
+                .collect(),
+        )
+    }
+
+    #[test]
+    fn rename_preset() {
+        let mut module = sample_module();
+        let renamer = RenameExports::with_preset("pwasm").unwrap();
+        assert!(renamer.translate_inplace(&mut module).unwrap());
+        assert_eq!(export_names(&module), vec!["_call", "memory", "deploy"]);
+
+        let renamer = RenameExports::with_preset("ewasm").unwrap();
+        assert!(renamer.translate_inplace(&mut module).unwrap());
+        assert_eq!(export_names(&module), vec!["main", "memory", "deploy"]);
+    }
+
+    #[test]
+    fn rename_swap() {
+        let renamed = renames(&[("main", "deploy"), ("deploy", "main")])
+            .translate(&sample_module())
+            .unwrap()
+            .unwrap();
+        assert_eq!(export_names(&renamed), vec!["deploy", "memory", "main"]);
+    }
+
+    #[test]
+    fn rename_nothing() {
+        let renamer = renames(&[("call", "main"), ("memory", "memory")]);
+        assert!(renamer.translate(&sample_module()).unwrap().is_none());
+        assert!(renamer
+            .translate(&builder::module().build())
+            .unwrap()
+            .is_none());
+    }
+
+    #[test]
+    fn rename_collision() {
+        let mut module = sample_module();
+        match renames(&[("deploy", "main")]).translate_inplace(&mut module) {
+            Err(ModuleError::Custom(message)) => {
+                assert_eq!(message, "Exports main and deploy would both be named main")
+            }
+            _ => panic!("expected a collision"),
+        }
+        // The module is left untouched.
+        assert_eq!(module, sample_module());
+
+        assert!(renames(&[("main", "call"), ("deploy", "call")])
+            .translate(&module)
+            .is_err());
+    }
+
+    #[test]
+    fn with_config() {
+        let config = serde_yaml::from_str("{preset: ewasm, renames: {deploy: call}}").unwrap();
+        let renamer = RenameExports::with_config(&config).unwrap();
+        assert_eq!(renamer.renamed("_call"), "main");
+        assert_eq!(renamer.renamed("deploy"), "call");
+        assert_eq!(renamer.renamed("main"), "main");
+
+        let config = serde_yaml::from_str("{}").unwrap();
+        assert!(RenameExports::with_config(&config).is_err());
+
+        let config = serde_yaml::from_str("{preset: foo}").unwrap();
+        assert!(RenameExports::with_config(&config).is_err());
+    }
+}
@@ -1,6 +1,8 @@
-use super::{ModuleError, ModuleTranslator};
+use super::utils::parse_config;
+use super::{ModuleConfig, ModuleError, ModuleTranslator};
 use parity_wasm::builder;
 use parity_wasm::elements::*;
+use serde_yaml::Value;
 
 pub struct Repack;
 
@@ -10,6 +12,22 @@ impl Repack {
     }
 }
 
+/// Options accepted by `Repack::with_config`. Repack has no options; a preset is accepted (and
+/// ignored) for compatibility with configurations which specify one for every module.
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Config {
+    #[allow(dead_code)]
+    preset: Option<String>,
+}
+
+impl ModuleConfig for Repack {
+    fn with_config(config: &Value) -> Result<Self, ModuleError> {
+        let _config: Config = parse_config(config)?;
+        Ok(Repack::new())
+    }
+}
+
 impl ModuleTranslator for Repack {
     fn translate_inplace(&self, module: &mut Module) -> Result<bool, ModuleError> {
         Err(ModuleError::NotSupported)
@@ -0,0 +1,277 @@
+//! Structured results of module validation.
+
+use std::fmt;
+
+/// Severity of a finding. Only errors cause a module to be considered invalid.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Severity {
+    Error,
+    Warning,
+    /// Purely informational, e.g. the result of an audit.
+    Info,
+}
+
+/// Enum representing what a finding is about.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FindingKind {
+    MissingImport,
+    MalformedImport,
+    UnlistedImport,
+    MissingExport,
+    MalformedExport,
+    UnlistedExport,
+    MissingStartFunction,
+    UnexpectedStartFunction,
+    ReachableImport,
+    ForbiddenImport,
+    FloatInstruction,
+    FloatType,
+    ExcessiveMemory,
+    UnboundedMemory,
+    LimitExceeded,
+}
+
+/// A single problem found by a validator.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize)]
+pub struct Finding {
+    pub kind: FindingKind,
+    pub severity: Severity,
+    /// Namespace of the offending import, if any.
+    pub namespace: Option<String>,
+    /// Field name of the offending import or export, if any.
+    pub field: Option<String>,
+    /// What the validator expected to find, e.g. a function signature.
+    pub expected: Option<String>,
+    /// What was actually found in the module.
+    pub actual: Option<String>,
+    /// Where in the module the finding applies, e.g. `export main`.
+    pub location: Option<String>,
+    /// Index of the function the finding applies to, in the function index space.
+    pub function: Option<u32>,
+    /// Index of the offending instruction within the function body, counted in instructions
+    /// rather than bytes.
+    pub instruction: Option<usize>,
+}
+
+/// List of findings returned by `ModuleValidator::report`.
+#[derive(Clone, Debug, Default, Eq, PartialEq, Serialize)]
+pub struct ValidationReport {
+    findings: Vec<Finding>,
+}
+
+impl Finding {
+    pub fn new(kind: FindingKind, severity: Severity) -> Self {
+        Finding {
+            kind,
+            severity,
+            namespace: None,
+            field: None,
+            expected: None,
+            actual: None,
+            location: None,
+            function: None,
+            instruction: None,
+        }
+    }
+
+    pub fn error(kind: FindingKind) -> Self {
+        Finding::new(kind, Severity::Error)
+    }
+
+    pub fn warning(kind: FindingKind) -> Self {
+        Finding::new(kind, Severity::Warning)
+    }
+
+    pub fn info(kind: FindingKind) -> Self {
+        Finding::new(kind, Severity::Info)
+    }
+
+    pub fn with_namespace(mut self, namespace: &str) -> Self {
+        self.namespace = Some(namespace.to_string());
+        self
+    }
+
+    pub fn with_field(mut self, field: &str) -> Self {
+        self.field = Some(field.to_string());
+        self
+    }
+
+    pub fn with_expected(mut self, expected: String) -> Self {
+        self.expected = Some(expected);
+        self
+    }
+
+    pub fn with_actual(mut self, actual: String) -> Self {
+        self.actual = Some(actual);
+        self
+    }
+
+    pub fn with_location(mut self, location: String) -> Self {
+        self.location = Some(location);
+        self
+    }
+
+    pub fn with_function(mut self, function: u32) -> Self {
+        self.function = Some(function);
+        self
+    }
+
+    pub fn with_instruction(mut self, instruction: usize) -> Self {
+        self.instruction = Some(instruction);
+        self
+    }
+
+    pub fn is_error(&self) -> bool {
+        self.severity == Severity::Error
+    }
+}
+
+impl ValidationReport {
+    pub fn new() -> Self {
+        ValidationReport {
+            findings: Vec::new(),
+        }
+    }
+
+    pub fn push(&mut self, finding: Finding) {
+        self.findings.push(finding);
+    }
+
+    pub fn findings(&self) -> &[Finding] {
+        &self.findings
+    }
+
+    /// Returns true if the report contains no errors. Warnings do not invalidate a module.
+    pub fn is_valid(&self) -> bool {
+        !self.findings.iter().any(Finding::is_error)
+    }
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{}",
+            match self {
+                Severity::Error => "error",
+                Severity::Warning => "warning",
+                Severity::Info => "info",
+            }
+        )
+    }
+}
+
+impl fmt::Display for FindingKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{}",
+            match self {
+                FindingKind::MissingImport => "missing import",
+                FindingKind::MalformedImport => "malformed import",
+                FindingKind::UnlistedImport => "unlisted import",
+                FindingKind::MissingExport => "missing export",
+                FindingKind::MalformedExport => "malformed export",
+                FindingKind::UnlistedExport => "unlisted export",
+                FindingKind::MissingStartFunction => "missing start function",
+                FindingKind::UnexpectedStartFunction => "unexpected start function",
+                FindingKind::ReachableImport => "reachable import",
+                FindingKind::ForbiddenImport => "forbidden import",
+                FindingKind::FloatInstruction => "floating-point instruction",
+                FindingKind::FloatType => "floating-point type",
+                FindingKind::ExcessiveMemory => "memory limit exceeded",
+                FindingKind::UnboundedMemory => "unbounded memory",
+                FindingKind::LimitExceeded => "limit exceeded",
+            }
+        )
+    }
+}
+
+impl fmt::Display for Finding {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}: {}", self.severity, self.kind)?;
+        match (&self.namespace, &self.field) {
+            (Some(namespace), Some(field)) => write!(f, " {}.{}", namespace, field)?,
+            (None, Some(field)) => write!(f, " {}", field)?,
+            _ => (),
+        }
+        if let Some(location) = &self.location {
+            write!(f, " in {}", location)?;
+        }
+        if let Some(function) = self.function {
+            write!(f, " in function {}", function)?;
+        }
+        if let Some(instruction) = self.instruction {
+            write!(f, " at instruction {}", instruction)?;
+        }
+        if let Some(expected) = &self.expected {
+            write!(f, ", expected {}", expected)?;
+        }
+        if let Some(actual) = &self.actual {
+            write!(f, ", found {}", actual)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn empty_report_is_valid() {
+        let report = ValidationReport::new();
+        assert!(report.is_valid());
+        assert!(report.findings().is_empty());
+    }
+
+    #[test]
+    fn warnings_keep_report_valid() {
+        let mut report = ValidationReport::new();
+        report.push(Finding::warning(FindingKind::UnlistedExport).with_field("foo"));
+        assert!(report.is_valid());
+
+        report.push(Finding::error(FindingKind::MissingExport).with_field("main"));
+        assert!(!report.is_valid());
+        assert_eq!(report.findings().len(), 2);
+    }
+
+    #[test]
+    fn fmt_finding() {
+        let finding = Finding::error(FindingKind::MalformedImport)
+            .with_namespace("ethereum")
+            .with_field("useGas")
+            .with_expected("(func (param i64))".to_string())
+            .with_actual("(func (param i32))".to_string());
+        assert_eq!(
+            "error: malformed import ethereum.useGas, expected (func (param i64)), found (func (param i32))",
+            format!("{}", finding)
+        );
+
+        let finding = Finding::warning(FindingKind::UnexpectedStartFunction);
+        assert_eq!("warning: unexpected start function", format!("{}", finding));
+
+        let finding = Finding::info(FindingKind::ReachableImport)
+            .with_namespace("ethereum")
+            .with_field("finish")
+            .with_location("export main".to_string());
+        assert_eq!(
+            "info: reachable import ethereum.finish in export main",
+            format!("{}", finding)
+        );
+    }
+
+    #[test]
+    fn serialize_finding() {
+        let finding = Finding::error(FindingKind::MissingExport)
+            .with_field("main")
+            .with_expected("(func)".to_string());
+        let json = serde_json::to_value(&finding).unwrap();
+        assert_eq!(json["kind"], "missing_export");
+        assert_eq!(json["severity"], "error");
+        assert_eq!(json["field"], "main");
+        assert_eq!(json["namespace"], serde_json::Value::Null);
+    }
+}
@@ -0,0 +1,785 @@
+use std::collections::{BTreeMap, HashMap};
+use std::fs::read;
+use std::path::Path;
+
+use super::checkfloats::{is_float_instruction, CheckFloats};
+use super::depgraph::{DepGraph, DepGraphBuilder};
+use super::utils::{
+    add_function_imports, add_type, fmt_func_type, function_signature, function_type_indices,
+    parse_config, remap,
+};
+use super::{ModuleConfig, ModuleError, ModuleTranslator, ModuleValidator};
+use parity_wasm::elements::{
+    deserialize_buffer, BlockType, External, Func, FunctionType, GlobalType, ImportCountType,
+    ImportEntry, Instruction, Internal, Local, Module, Type, ValueType,
+};
+use serde_yaml::Value;
+
+/// Namespace from which the helper functions are imported by default.
+pub const DEFAULT_NAMESPACE: &str = "softfloat";
+
+/// Where the software floating-point helpers come from.
+pub enum HelperSource {
+    /// Imported from the given namespace.
+    Import(String),
+    /// Copied from the exports of a library module.
+    Library(Module),
+}
+
+/// Struct on which ModuleTranslator is implemented. Rewrites a module so that it no longer uses
+/// floating-point types or instructions: f32 and f64 values are carried as their bit patterns in
+/// i32 and i64 values, and arithmetic is done by calling software floating-point helpers.
+pub struct SoftFloat {
+    source: HelperSource,
+}
+
+/// A software floating-point helper replacing one instruction. Floating-point operands and results
+/// are passed as their bit patterns.
+struct Helper {
+    name: &'static str,
+    params: &'static [ValueType],
+    result: ValueType,
+}
+
+impl Helper {
+    fn signature(&self) -> FunctionType {
+        FunctionType::new(self.params.to_vec(), Some(self.result))
+    }
+}
+
+impl SoftFloat {
+    pub fn new(source: HelperSource) -> Self {
+        SoftFloat { source }
+    }
+
+    pub fn with_namespace(namespace: &str) -> Self {
+        SoftFloat::new(HelperSource::Import(namespace.to_string()))
+    }
+
+    pub fn with_library(library: Module) -> Self {
+        SoftFloat::new(HelperSource::Library(library))
+    }
+
+    /// Loads the library of helpers from a Wasm binary.
+    pub fn with_library_file<P: AsRef<Path>>(path: P) -> Result<Self, ModuleError> {
+        let bytes = read(&path).map_err(|e| {
+            ModuleError::Custom(format!(
+                "Failed to read soft-float library {}: {}",
+                path.as_ref().display(),
+                e
+            ))
+        })?;
+        Ok(SoftFloat::with_library(deserialize_buffer::<Module>(
+            &bytes,
+        )?))
+    }
+}
+
+/// Options accepted by `SoftFloat::with_config`.
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Config {
+    namespace: Option<String>,
+    /// Path to a Wasm binary exporting the helpers.
+    library: Option<String>,
+}
+
+impl ModuleConfig for SoftFloat {
+    fn with_config(config: &Value) -> Result<Self, ModuleError> {
+        let config: Config = parse_config(config)?;
+        match (config.namespace, config.library) {
+            (None, None) => Ok(SoftFloat::with_namespace(DEFAULT_NAMESPACE)),
+            (Some(namespace), None) => Ok(SoftFloat::with_namespace(&namespace)),
+            (None, Some(path)) => SoftFloat::with_library_file(path),
+            _ => Err(ModuleError::Custom(
+                "At most one of namespace and library must be set".to_string(),
+            )),
+        }
+    }
+}
+
+impl ModuleTranslator for SoftFloat {
+    fn translate(&self, module: &Module) -> Result<Option<Module>, ModuleError> {
+        if CheckFloats::new(true).report(module)?.is_valid() {
+            return Ok(None);
+        }
+        check_imports(module)?;
+        check_exports(module)?;
+
+        let mut ret = module.clone();
+        let helpers = required_helpers(&ret)?;
+        let indices = match &self.source {
+            HelperSource::Import(namespace) => import_helpers(&mut ret, namespace, &helpers)?,
+            HelperSource::Library(library) => link_helpers(&mut ret, library, &helpers)?,
+        };
+        rewrite_types(&mut ret);
+        rewrite_globals(&mut ret);
+        rewrite_code(&mut ret, &indices);
+        Ok(Some(ret))
+    }
+}
+
+/// Fails if the host would have to provide or accept floating-point values.
+fn check_imports(module: &Module) -> Result<(), ModuleError> {
+    let types = match module.type_section() {
+        Some(section) => section.types(),
+        None => &[],
+    };
+    if let Some(section) = module.import_section() {
+        for entry in section.entries() {
+            let uses_float = match entry.external() {
+                External::Function(idx) => match types.get(*idx as usize) {
+                    Some(Type::Function(sig)) => is_float_signature(sig),
+                    None => false,
+                },
+                External::Global(global_type) => is_float_type(&global_type.content_type()),
+                _ => false,
+            };
+            if uses_float {
+                return Err(ModuleError::Custom(format!(
+                    "Cannot rewrite floating-point import {}.{}",
+                    entry.module(),
+                    entry.field()
+                )));
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Fails if the host would have to call an exported function or read an exported global with
+/// floating-point types, as rewriting them would silently change the interface of the module.
+fn check_exports(module: &Module) -> Result<(), ModuleError> {
+    let globals_len = module.import_count(ImportCountType::Global);
+    let globals = match module.global_section() {
+        Some(section) => section.entries(),
+        None => &[],
+    };
+    if let Some(section) = module.export_section() {
+        for entry in section.entries() {
+            let uses_float = match entry.internal() {
+                Internal::Function(idx) => match function_signature(module, *idx) {
+                    Some(sig) => is_float_signature(sig),
+                    None => false,
+                },
+                // Imported globals are already checked by `check_imports`.
+                Internal::Global(idx) => match (*idx as usize)
+                    .checked_sub(globals_len)
+                    .and_then(|idx| globals.get(idx))
+                {
+                    Some(global) => is_float_type(&global.global_type().content_type()),
+                    None => false,
+                },
+                _ => false,
+            };
+            if uses_float {
+                return Err(ModuleError::Custom(format!(
+                    "Cannot rewrite floating-point export {}",
+                    entry.field()
+                )));
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Returns the helpers called by the rewritten module, by name. Fails on floating-point
+/// instructions which cannot be rewritten.
+fn required_helpers(module: &Module) -> Result<BTreeMap<&'static str, Helper>, ModuleError> {
+    let mut ret = BTreeMap::new();
+    if let Some(section) = module.code_section() {
+        for body in section.bodies() {
+            for instr in body.code().elements() {
+                if let Some(helper) = helper(instr) {
+                    ret.insert(helper.name, helper);
+                } else if is_float_instruction(instr) && rewrite_instruction(instr).is_none() {
+                    return Err(ModuleError::Custom(format!(
+                        "Unsupported floating-point instruction: {}",
+                        instr
+                    )));
+                }
+            }
+        }
+    }
+    Ok(ret)
+}
+
+fn import_helpers(
+    module: &mut Module,
+    namespace: &str,
+    helpers: &BTreeMap<&'static str, Helper>,
+) -> Result<HashMap<&'static str, u32>, ModuleError> {
+    let entries: Vec<ImportEntry> = helpers
+        .values()
+        .map(|helper| {
+            let type_idx = add_type(module, helper.signature());
+            ImportEntry::new(
+                namespace.to_string(),
+                helper.name.to_string(),
+                External::Function(type_idx),
+            )
+        })
+        .collect();
+    let first = add_function_imports(module, entries)?;
+    Ok(helpers
+        .keys()
+        .enumerate()
+        .map(|(idx, name)| (*name, first + idx as u32))
+        .collect())
+}
+
+/// Copies the helpers, and the functions they call, from the library to the end of the module.
+fn link_helpers(
+    module: &mut Module,
+    library: &Module,
+    helpers: &BTreeMap<&'static str, Helper>,
+) -> Result<HashMap<&'static str, u32>, ModuleError> {
+    if helpers.is_empty() {
+        return Ok(HashMap::new());
+    }
+    let has_imports = match library.import_section() {
+        Some(section) => !section.entries().is_empty(),
+        None => false,
+    };
+    if has_imports
+        || library.memory_section().is_some()
+        || library.table_section().is_some()
+        || library.global_section().is_some()
+    {
+        return Err(ModuleError::Custom(
+            "The soft-float library must not have imports, memories, tables or globals".to_string(),
+        ));
+    }
+
+    let types = match library.type_section() {
+        Some(section) => section.types(),
+        None => &[],
+    };
+    let type_indices = function_type_indices(library);
+    let signature = |idx: u32| match type_indices
+        .get(idx as usize)
+        .and_then(|type_idx| types.get(*type_idx as usize))
+    {
+        Some(Type::Function(sig)) => Ok(sig.clone()),
+        _ => Err(ModuleError::Custom(format!(
+            "Invalid soft-float library function: {}",
+            idx
+        ))),
+    };
+
+    let mut roots = Vec::new();
+    for helper in helpers.values() {
+        let idx = library
+            .export_section()
+            .and_then(|section| {
+                section
+                    .entries()
+                    .iter()
+                    .find(|entry| entry.field() == helper.name)
+            })
+            .and_then(|entry| match entry.internal() {
+                Internal::Function(idx) => Some(*idx),
+                _ => None,
+            })
+            .ok_or_else(|| {
+                ModuleError::Custom(format!(
+                    "The soft-float library does not export {}",
+                    helper.name
+                ))
+            })?;
+        let sig = signature(idx)?;
+        if sig != helper.signature() {
+            return Err(ModuleError::Custom(format!(
+                "Soft-float helper {} has signature {}, expected {}",
+                helper.name,
+                fmt_func_type(&sig),
+                fmt_func_type(&helper.signature())
+            )));
+        }
+        roots.push(idx);
+    }
+
+    let graph = DepGraph::build_from_roots(library, &roots).map_err(|_| {
+        ModuleError::Custom("Failed to build the soft-float library call graph".to_string())
+    })?;
+    let linked = graph.reachable();
+    let base = module.functions_space() as u32;
+    let mut mapping = vec![None; type_indices.len()];
+    for (pos, idx) in linked.iter().enumerate() {
+        mapping[*idx as usize] = Some(base + pos as u32);
+    }
+
+    let bodies = match library.code_section() {
+        Some(section) => section.bodies(),
+        None => &[],
+    };
+    for idx in linked.iter() {
+        let mut body = bodies[*idx as usize].clone();
+        for instr in body.code_mut().elements_mut() {
+            if is_float_instruction(instr) {
+                return Err(ModuleError::Custom(format!(
+                    "The soft-float library uses floating-point instruction {}",
+                    instr
+                )));
+            }
+            if let Instruction::Call(callee) = instr {
+                *callee = remap(&mapping, *callee)?;
+            }
+        }
+        let type_idx = add_type(module, signature(*idx)?);
+        if let Some(section) = module.function_section_mut() {
+            section.entries_mut().push(Func::new(type_idx));
+        }
+        if let Some(section) = module.code_section_mut() {
+            section.bodies_mut().push(body);
+        }
+    }
+
+    roots
+        .iter()
+        .zip(helpers.keys())
+        .map(|(idx, name)| Ok((*name, remap(&mapping, *idx)?)))
+        .collect()
+}
+
+fn rewrite_types(module: &mut Module) {
+    if let Some(section) = module.type_section_mut() {
+        for Type::Function(sig) in section.types_mut() {
+            for param in sig.params_mut() {
+                *param = soft_type(*param);
+            }
+            if let Some(result) = sig.return_type_mut() {
+                *result = soft_type(*result);
+            }
+        }
+    }
+}
+
+fn rewrite_globals(module: &mut Module) {
+    if let Some(section) = module.global_section_mut() {
+        for entry in section.entries_mut() {
+            let global_type = *entry.global_type();
+            *entry.global_type_mut() = GlobalType::new(
+                soft_type(global_type.content_type()),
+                global_type.is_mutable(),
+            );
+            for instr in entry.init_expr_mut().code_mut() {
+                if let Some(new_instr) = rewrite_instruction(instr) {
+                    *instr = new_instr;
+                }
+            }
+        }
+    }
+}
+
+fn rewrite_code(module: &mut Module, helpers: &HashMap<&'static str, u32>) {
+    if let Some(section) = module.code_section_mut() {
+        for body in section.bodies_mut() {
+            for local in body.locals_mut() {
+                *local = Local::new(local.count(), soft_type(local.value_type()));
+            }
+            for instr in body.code_mut().elements_mut() {
+                if let Some(helper) = helper(instr) {
+                    *instr = Instruction::Call(helpers[helper.name]);
+                } else if let Some(new_instr) = rewrite_instruction(instr) {
+                    *instr = new_instr;
+                }
+            }
+        }
+    }
+}
+
+fn is_float_type(value_type: &ValueType) -> bool {
+    matches!(value_type, ValueType::F32 | ValueType::F64)
+}
+
+fn is_float_signature(sig: &FunctionType) -> bool {
+    sig.params().iter().any(is_float_type)
+        || matches!(
+            sig.return_type(),
+            Some(ValueType::F32) | Some(ValueType::F64)
+        )
+}
+
+/// Returns the integer type carrying the bit pattern of a floating-point type.
+fn soft_type(value_type: ValueType) -> ValueType {
+    match value_type {
+        ValueType::F32 => ValueType::I32,
+        ValueType::F64 => ValueType::I64,
+        other => other,
+    }
+}
+
+/// Rewrites the floating-point instructions which do not need a helper: blocks, loads, stores and
+/// constants work on the bit pattern, and reinterpretations become no-ops.
+fn rewrite_instruction(instruction: &Instruction) -> Option<Instruction> {
+    use parity_wasm::elements::Instruction::*;
+    match instruction {
+        Block(BlockType::Value(value_type)) if is_float_type(value_type) => {
+            Some(Block(BlockType::Value(soft_type(*value_type))))
+        }
+        Loop(BlockType::Value(value_type)) if is_float_type(value_type) => {
+            Some(Loop(BlockType::Value(soft_type(*value_type))))
+        }
+        If(BlockType::Value(value_type)) if is_float_type(value_type) => {
+            Some(If(BlockType::Value(soft_type(*value_type))))
+        }
+        F32Load(align, offset) => Some(I32Load(*align, *offset)),
+        F64Load(align, offset) => Some(I64Load(*align, *offset)),
+        F32Store(align, offset) => Some(I32Store(*align, *offset)),
+        F64Store(align, offset) => Some(I64Store(*align, *offset)),
+        F32Const(bits) => Some(I32Const(*bits as i32)),
+        F64Const(bits) => Some(I64Const(*bits as i64)),
+        I32ReinterpretF32 | I64ReinterpretF64 | F32ReinterpretI32 | F64ReinterpretI64 => Some(Nop),
+        _ => None,
+    }
+}
+
+/// Returns the helper replacing a floating-point arithmetic, comparison or conversion instruction.
+/// Helpers are named after the instruction they replace.
+fn helper(instruction: &Instruction) -> Option<Helper> {
+    use parity_wasm::elements::Instruction::*;
+    use parity_wasm::elements::ValueType::{I32, I64};
+    let (name, params, result): (_, &'static [ValueType], _) = match instruction {
+        F32Eq => ("f32_eq", &[I32, I32], I32),
+        F32Ne => ("f32_ne", &[I32, I32], I32),
+        F32Lt => ("f32_lt", &[I32, I32], I32),
+        F32Gt => ("f32_gt", &[I32, I32], I32),
+        F32Le => ("f32_le", &[I32, I32], I32),
+        F32Ge => ("f32_ge", &[I32, I32], I32),
+        F64Eq => ("f64_eq", &[I64, I64], I32),
+        F64Ne => ("f64_ne", &[I64, I64], I32),
+        F64Lt => ("f64_lt", &[I64, I64], I32),
+        F64Gt => ("f64_gt", &[I64, I64], I32),
+        F64Le => ("f64_le", &[I64, I64], I32),
+        F64Ge => ("f64_ge", &[I64, I64], I32),
+        F32Abs => ("f32_abs", &[I32], I32),
+        F32Neg => ("f32_neg", &[I32], I32),
+        F32Ceil => ("f32_ceil", &[I32], I32),
+        F32Floor => ("f32_floor", &[I32], I32),
+        F32Trunc => ("f32_trunc", &[I32], I32),
+        F32Nearest => ("f32_nearest", &[I32], I32),
+        F32Sqrt => ("f32_sqrt", &[I32], I32),
+        F32Add => ("f32_add", &[I32, I32], I32),
+        F32Sub => ("f32_sub", &[I32, I32], I32),
+        F32Mul => ("f32_mul", &[I32, I32], I32),
+        F32Div => ("f32_div", &[I32, I32], I32),
+        F32Min => ("f32_min", &[I32, I32], I32),
+        F32Max => ("f32_max", &[I32, I32], I32),
+        F32Copysign => ("f32_copysign", &[I32, I32], I32),
+        F64Abs => ("f64_abs", &[I64], I64),
+        F64Neg => ("f64_neg", &[I64], I64),
+        F64Ceil => ("f64_ceil", &[I64], I64),
+        F64Floor => ("f64_floor", &[I64], I64),
+        F64Trunc => ("f64_trunc", &[I64], I64),
+        F64Nearest => ("f64_nearest", &[I64], I64),
+        F64Sqrt => ("f64_sqrt", &[I64], I64),
+        F64Add => ("f64_add", &[I64, I64], I64),
+        F64Sub => ("f64_sub", &[I64, I64], I64),
+        F64Mul => ("f64_mul", &[I64, I64], I64),
+        F64Div => ("f64_div", &[I64, I64], I64),
+        F64Min => ("f64_min", &[I64, I64], I64),
+        F64Max => ("f64_max", &[I64, I64], I64),
+        F64Copysign => ("f64_copysign", &[I64, I64], I64),
+        I32TruncSF32 => ("i32_trunc_f32_s", &[I32], I32),
+        I32TruncUF32 => ("i32_trunc_f32_u", &[I32], I32),
+        I32TruncSF64 => ("i32_trunc_f64_s", &[I64], I32),
+        I32TruncUF64 => ("i32_trunc_f64_u", &[I64], I32),
+        I64TruncSF32 => ("i64_trunc_f32_s", &[I32], I64),
+        I64TruncUF32 => ("i64_trunc_f32_u", &[I32], I64),
+        I64TruncSF64 => ("i64_trunc_f64_s", &[I64], I64),
+        I64TruncUF64 => ("i64_trunc_f64_u", &[I64], I64),
+        F32ConvertSI32 => ("f32_convert_i32_s", &[I32], I32),
+        F32ConvertUI32 => ("f32_convert_i32_u", &[I32], I32),
+        F32ConvertSI64 => ("f32_convert_i64_s", &[I64], I32),
+        F32ConvertUI64 => ("f32_convert_i64_u", &[I64], I32),
+        F32DemoteF64 => ("f32_demote_f64", &[I64], I32),
+        F64ConvertSI32 => ("f64_convert_i32_s", &[I32], I64),
+        F64ConvertUI32 => ("f64_convert_i32_u", &[I32], I64),
+        F64ConvertSI64 => ("f64_convert_i64_s", &[I64], I64),
+        F64ConvertUI64 => ("f64_convert_i64_u", &[I64], I64),
+        F64PromoteF32 => ("f64_promote_f32", &[I32], I64),
+        _ => return None,
+    };
+    Some(Helper {
+        name,
+        params,
+        result,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::function_names;
+    use parity_wasm::elements::deserialize_buffer;
+    use rustc_hex::FromHex;
+
+    // wast:
+    // (module
+    //   (import "ethereum" "finish" (func $finish (param i32 i32)))
+    //   (global $scale (mut f64) (f64.const 1.5))
+    //   (memory 1)
+    //   (export "main" (func $main))
+    //   (export "memory" (memory 0))
+    //   (func $main
+    //     (call $finish (i32.const 0) (call $half (i32.const 7))))
+    //   (func $half (param i32) (result i32)
+    //     (local f32)
+    //     (local.set 1 (f32.div (f32.convert_i32_s (local.get 0)) (f32.const 2)))
+    //     (i32.trunc_f32_s (local.get 1)))
+    // )
+    fn float_module() -> Module {
+        let wasm: Vec<u8> = FromHex::from_hex(
+            "
+            0061736d01000000010e0360027f7f0060000060017f017f021301086574
+            68657265756d0666696e697368000003030201020503010001060d017c01
+            44000000000000f83f0b071102046d61696e0001066d656d6f727902000a
+            1f020a0041004107100210000b1201017d2000b243000000409521012001
+            a80b0026046e616d65011503000666696e69736801046d61696e02046861
+            6c6607080100057363616c65
+        ",
+        )
+        .unwrap();
+        deserialize_buffer::<Module>(&wasm).unwrap()
+    }
+
+    // wast:
+    // (module
+    //   (export "f32_div" (func $div))
+    //   (export "f32_convert_i32_s" (func $convert))
+    //   (export "i32_trunc_f32_s" (func $trunc))
+    //   (func $div (param i32 i32) (result i32) (call $shift (local.get 0)))
+    //   (func $convert (param i32) (result i32) (local.get 0))
+    //   (func $trunc (param i32) (result i32) (local.get 0))
+    //   (func $shift (param i32) (result i32) (i32.shr_u (local.get 0) (i32.const 1)))
+    // )
+    fn library_module() -> Module {
+        let wasm: Vec<u8> = FromHex::from_hex(
+            "
+            0061736d01000000010c0260027f7f017f60017f017f0305040001010107
+            3103076633325f6469760000116633325f636f6e766572745f6933325f73
+            00010f6933325f7472756e635f6633325f7300020a1a040600200010030b
+            040020000b040020000b070020004101760b0024046e616d65011d040003
+            6469760107636f6e7665727402057472756e6303057368696674
+        ",
+        )
+        .unwrap();
+        deserialize_buffer::<Module>(&wasm).unwrap()
+    }
+
+    fn code(module: &Module, idx: usize) -> Vec<Instruction> {
+        module.code_section().unwrap().bodies()[idx]
+            .code()
+            .elements()
+            .to_vec()
+    }
+
+    #[test]
+    fn import_helpers() {
+        let module = SoftFloat::with_namespace("softfloat")
+            .translate(&float_module())
+            .unwrap()
+            .unwrap();
+        assert!(CheckFloats::new(true).validate(&module).unwrap());
+
+        let imports: Vec<(&str, &str)> = module
+            .import_section()
+            .unwrap()
+            .entries()
+            .iter()
+            .map(|entry| (entry.module(), entry.field()))
+            .collect();
+        assert_eq!(
+            imports,
+            vec![
+                ("ethereum", "finish"),
+                ("softfloat", "f32_convert_i32_s"),
+                ("softfloat", "f32_div"),
+                ("softfloat", "i32_trunc_f32_s"),
+            ]
+        );
+
+        // Defined functions are shifted by the three new imports.
+        let export = &module.export_section().unwrap().entries()[0];
+        assert_eq!(export.internal(), &Internal::Function(4));
+        assert_eq!(
+            code(&module, 0),
+            vec![
+                Instruction::I32Const(0),
+                Instruction::I32Const(7),
+                Instruction::Call(5),
+                Instruction::Call(0),
+                Instruction::End,
+            ]
+        );
+        assert_eq!(
+            code(&module, 1),
+            vec![
+                Instruction::GetLocal(0),
+                Instruction::Call(1),
+                Instruction::I32Const(0x4000_0000),
+                Instruction::Call(2),
+                Instruction::SetLocal(1),
+                Instruction::GetLocal(1),
+                Instruction::Call(3),
+                Instruction::End,
+            ]
+        );
+        let body = &module.code_section().unwrap().bodies()[1];
+        assert_eq!(body.locals(), &[Local::new(1, ValueType::I32)]);
+
+        let global = &module.global_section().unwrap().entries()[0];
+        assert_eq!(global.global_type(), &GlobalType::new(ValueType::I64, true));
+        assert_eq!(
+            global.init_expr().code(),
+            &[
+                Instruction::I64Const(0x3ff8_0000_0000_0000),
+                Instruction::End
+            ]
+        );
+
+        let names = function_names(&module);
+        assert_eq!(names.get(&4).map(String::as_str), Some("main"));
+        assert_eq!(names.get(&5).map(String::as_str), Some("half"));
+    }
+
+    #[test]
+    fn link_library() {
+        let module = SoftFloat::with_library(library_module())
+            .translate(&float_module())
+            .unwrap()
+            .unwrap();
+        assert!(CheckFloats::new(true).validate(&module).unwrap());
+        assert_eq!(module.import_section().unwrap().entries().len(), 1);
+        assert_eq!(module.function_section().unwrap().entries().len(), 6);
+
+        // The helpers and the function they call are appended after the existing functions.
+        assert_eq!(
+            code(&module, 1),
+            vec![
+                Instruction::GetLocal(0),
+                Instruction::Call(4),
+                Instruction::I32Const(0x4000_0000),
+                Instruction::Call(3),
+                Instruction::SetLocal(1),
+                Instruction::GetLocal(1),
+                Instruction::Call(5),
+                Instruction::End,
+            ]
+        );
+        assert_eq!(
+            code(&module, 2),
+            vec![
+                Instruction::GetLocal(0),
+                Instruction::Call(6),
+                Instruction::End
+            ]
+        );
+    }
+
+    #[test]
+    fn library_errors() {
+        let mut library = library_module();
+        library
+            .export_section_mut()
+            .unwrap()
+            .entries_mut()
+            .remove(0);
+        assert!(SoftFloat::with_library(library)
+            .translate(&float_module())
+            .is_err());
+
+        // The helpers must take and return the bit patterns.
+        let mut library = library_module();
+        library.type_section_mut().unwrap().types_mut()[1] = Type::Function(FunctionType::new(
+            vec![ValueType::I64],
+            Some(ValueType::I32),
+        ));
+        assert!(SoftFloat::with_library(library)
+            .translate(&float_module())
+            .is_err());
+    }
+
+    #[test]
+    fn float_import_rejected() {
+        // wast:
+        // (module
+        //   (import "env" "scale" (func $scale (param f64) (result f64)))
+        // )
+        let wasm: Vec<u8> = vec![
+            0x00, 0x61, 0x73, 0x6d, 0x01, 0x00, 0x00, 0x00, 0x01, 0x06, 0x01, 0x60, 0x01, 0x7c,
+            0x01, 0x7c, 0x02, 0x0d, 0x01, 0x03, 0x65, 0x6e, 0x76, 0x05, 0x73, 0x63, 0x61, 0x6c,
+            0x65, 0x00, 0x00,
+        ];
+        let module = deserialize_buffer::<Module>(&wasm).unwrap();
+        assert!(SoftFloat::with_namespace("softfloat")
+            .translate(&module)
+            .is_err());
+    }
+
+    #[test]
+    fn float_export_rejected() {
+        // wast:
+        // (module
+        //   (export "scale" (func $scale))
+        //   (func $scale (param f64) (result f64) (local.get 0))
+        // )
+        let wasm: Vec<u8> = FromHex::from_hex(
+            "
+            0061736d0100000001060160017c017c03020100070901057363616c6500
+            000a0601040020000b000f046e616d6501080100057363616c65
+        ",
+        )
+        .unwrap();
+        let module = deserialize_buffer::<Module>(&wasm).unwrap();
+        match SoftFloat::with_namespace("softfloat").translate(&module) {
+            Err(ModuleError::Custom(message)) => {
+                assert_eq!(message, "Cannot rewrite floating-point export scale")
+            }
+            _ => panic!("expected the export to be rejected"),
+        }
+
+        // wast:
+        // (module
+        //   (global $g f64 (f64.const 1.5))
+        //   (export "g" (global $g))
+        // )
+        let wasm: Vec<u8> = FromHex::from_hex(
+            "
+            0061736d01000000060d017c0044000000000000f83f0b07050101670300
+            000b046e616d65070401000167
+        ",
+        )
+        .unwrap();
+        let module = deserialize_buffer::<Module>(&wasm).unwrap();
+        assert!(SoftFloat::with_namespace("softfloat")
+            .translate(&module)
+            .is_err());
+    }
+
+    #[test]
+    fn no_floats() {
+        let module = library_module();
+        assert!(SoftFloat::with_namespace("softfloat")
+            .translate(&module)
+            .unwrap()
+            .is_none());
+    }
+
+    #[test]
+    fn with_config() {
+        let config = serde_yaml::from_str("{}").unwrap();
+        let translator = SoftFloat::with_config(&config).unwrap();
+        assert!(matches!(translator.source, HelperSource::Import(ref ns) if ns == "softfloat"));
+
+        let config = serde_yaml::from_str("{namespace: env}").unwrap();
+        assert!(SoftFloat::with_config(&config).is_ok());
+
+        let config = serde_yaml::from_str("{library: /nonexistent/softfloat.wasm}").unwrap();
+        assert!(SoftFloat::with_config(&config).is_err());
+
+        let config = serde_yaml::from_str("{namespace: env, library: softfloat.wasm}").unwrap();
+        assert!(SoftFloat::with_config(&config).is_err());
+    }
+}
@@ -1,65 +1,29 @@
-use super::{ModuleError, ModulePreset, ModuleTranslator};
+use super::exports::{describe_export, ExportList};
+use super::utils::{invalid_preset, parse_config};
+use super::{ModuleConfig, ModuleError, ModulePreset, ModuleTranslator};
 use parity_wasm::elements::*;
-
-/// Struct containing a list of valid exports.
-struct ExportWhitelist {
-    pub entries: Vec<ExportEntry>,
-}
+use serde_yaml::Value;
 
 /// Wrapper struct implementing ModuleTranslator.
 /// Removes any exports that are noncompliant with a specified interface.
 pub struct TrimExports {
-    whitelist: ExportWhitelist,
-}
-
-/// Helper that compares the enum variant of two WebAssembly exports.
-fn cmp_internal_variant(a: &Internal, b: &Internal) -> bool {
-    std::mem::discriminant(a) == std::mem::discriminant(b)
-}
-
-impl ModulePreset for ExportWhitelist {
-    fn with_preset(preset: &str) -> Result<Self, ()> {
-        match preset {
-            "ewasm" => Ok(ExportWhitelist {
-                entries: vec![
-                    //NOTE: function signatures are not checked yet
-                    ExportEntry::new("main".to_string(), Internal::Function(0)),
-                    ExportEntry::new("memory".to_string(), Internal::Memory(0)),
-                ],
-            }),
-            "pwasm" => Ok(ExportWhitelist {
-                entries: vec![ExportEntry::new("_call".to_string(), Internal::Function(0))],
-            }),
-            _ => Err(()),
-        }
-    }
-}
-
-impl ExportWhitelist {
-    /// Constructs an empty whitelist. Mostly useless.
-    fn new() -> Self {
-        ExportWhitelist {
-            entries: Vec::new(),
-        }
-    }
-
-    /// Looks up a given export entry in the whitelist and returns true if it is valid.
-    fn lookup(&self, export: &ExportEntry) -> bool {
-        self.entries
-            .iter()
-            .find(|matched_export| {
-                export.field() == matched_export.field()
-                    && cmp_internal_variant(export.internal(), matched_export.internal())
-            })
-            .is_some()
-    }
+    whitelist: ExportList<'static>,
+    /// Whether exports with a mismatched signature fail the translation instead of being removed.
+    reject_mismatched: bool,
 }
 
 impl TrimExports {
     /// Constructs an empty `trimexports` context.
     pub fn new() -> Self {
+        TrimExports::with_whitelist(ExportList::new())
+    }
+
+    /// Constructs a context keeping the exports of an interface. Whether they are required or
+    /// optional does not matter.
+    pub fn with_whitelist(whitelist: ExportList<'static>) -> Self {
         TrimExports {
-            whitelist: ExportWhitelist::new(),
+            whitelist,
+            reject_mismatched: false,
         }
     }
 
@@ -67,49 +31,103 @@ impl TrimExports {
     /// corresponding whitelist.
     pub fn with_preset(preset: &str) -> Result<Self, ()> {
         match preset {
-            "ewasm" => Ok(TrimExports {
-                whitelist: ExportWhitelist::with_preset("ewasm").unwrap(),
-            }),
-            "pwasm" => Ok(TrimExports {
-                whitelist: ExportWhitelist::with_preset("pwasm").unwrap(),
-            }),
+            "ewasm" | "pwasm" => Ok(TrimExports::with_whitelist(ExportList::with_preset(
+                preset,
+            )?)),
             _ => Err(()),
         }
     }
 
+    pub fn set_reject_mismatched(&mut self, arg: bool) {
+        self.reject_mismatched = arg;
+    }
+
     /// Iterates over the export section, if there is one, and removes
-    /// unnecessary entries.
-    fn trim_exports(&self, module: &mut Module) -> bool {
-        if let Some(section) = module.export_section_mut() {
-            let new_section = ExportSection::with_entries(
-                section
-                    .entries()
-                    .iter()
-                    .cloned()
-                    .filter(|entry| self.whitelist.lookup(entry))
-                    .collect(),
-            );
-
-            if new_section.entries().len() < section.entries().len() {
-                *section = new_section;
-                return true;
+    /// unnecessary entries. Function exports whose signature does not match the whitelist are
+    /// removed too, or fail the translation if `reject_mismatched` is set.
+    fn trim_exports(&self, module: &mut Module) -> Result<bool, ModuleError> {
+        let entries = match module.export_section() {
+            Some(section) => section.entries(),
+            None => return Ok(false),
+        };
+
+        let mut kept = Vec::new();
+        for entry in entries {
+            let expected = match self.whitelist.lookup(entry) {
+                Some(listed) => listed.export(),
+                None => continue,
+            };
+            if !expected.matches(module, entry) {
+                if self.reject_mismatched {
+                    return Err(ModuleError::Custom(format!(
+                        "Export {} has signature {}, expected {}",
+                        entry.field(),
+                        describe_export(module, entry),
+                        expected.describe()
+                    )));
+                }
+                continue;
             }
+            kept.push(entry.clone());
+        }
+
+        if kept.len() == entries.len() {
+            return Ok(false);
+        }
+        if let Some(section) = module.export_section_mut() {
+            *section = ExportSection::with_entries(kept);
+        }
+        Ok(true)
+    }
+}
+
+/// Options accepted by `TrimExports::with_config`.
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Config {
+    preset: Option<String>,
+    /// Path to an interface definition file, whose exports are kept in addition to the preset.
+    interface: Option<String>,
+    /// Exports kept in addition to the preset and the interface.
+    exports: Option<ExportList<'static>>,
+    reject_mismatched: Option<bool>,
+}
 
-            false
-        } else {
-            false
+impl ModuleConfig for TrimExports {
+    fn with_config(config: &Value) -> Result<Self, ModuleError> {
+        let config: Config = parse_config(config)?;
+        let mut ret = match (&config.preset, &config.interface, &config.exports) {
+            (Some(preset), _, _) => {
+                TrimExports::with_preset(preset).map_err(|_| invalid_preset(preset))?
+            }
+            (None, None, None) => {
+                return Err(ModuleError::Custom(
+                    "Either a preset, an interface or exports must be set".to_string(),
+                ))
+            }
+            (None, _, _) => TrimExports::new(),
+        };
+        if let Some(path) = config.interface {
+            ret.whitelist.concatenate(ExportList::from_file(path)?);
+        }
+        if let Some(exports) = config.exports {
+            ret.whitelist.concatenate(exports);
+        }
+        if let Some(reject_mismatched) = config.reject_mismatched {
+            ret.reject_mismatched = reject_mismatched;
         }
+        Ok(ret)
     }
 }
 
 impl ModuleTranslator for TrimExports {
     fn translate_inplace(&self, module: &mut Module) -> Result<bool, ModuleError> {
-        Ok(self.trim_exports(module))
+        self.trim_exports(module)
     }
 
     fn translate(&self, module: &Module) -> Result<Option<Module>, ModuleError> {
         let mut ret = module.clone();
-        let modified = self.trim_exports(&mut ret);
+        let modified = self.trim_exports(&mut ret)?;
         if modified {
             return Ok(Some(ret));
         }
@@ -216,4 +234,100 @@ mod tests {
         let did_change = trimmer.translate_inplace(&mut module).unwrap();
         assert_eq!(false, did_change);
     }
+
+    fn module_with_main(sig: FunctionType) -> Module {
+        builder::module()
+            .function()
+            .signature()
+            .with_params(sig.params().to_vec())
+            .with_return_type(sig.return_type())
+            .build()
+            .body()
+            .build()
+            .build()
+            .export()
+            .field("main")
+            .internal()
+            .func(0)
+            .build()
+            .export()
+            .field("memory")
+            .internal()
+            .memory(0)
+            .build()
+            .build()
+    }
+
+    #[test]
+    fn mismatched_signature_dropped_ewasm() {
+        let mut module = module_with_main(FunctionType::new(vec![ValueType::I32], None));
+
+        let trimmer = TrimExports::with_preset("ewasm").unwrap();
+        assert!(trimmer.translate_inplace(&mut module).unwrap());
+        let exports = module.export_section().unwrap().entries();
+        assert_eq!(exports.len(), 1);
+        assert_eq!(exports[0].field(), "memory");
+    }
+
+    #[test]
+    fn mismatched_signature_rejected_ewasm() {
+        let module = module_with_main(FunctionType::new(vec![], Some(ValueType::I64)));
+
+        let mut trimmer = TrimExports::with_preset("ewasm").unwrap();
+        trimmer.set_reject_mismatched(true);
+        match trimmer.translate(&module) {
+            Err(ModuleError::Custom(message)) => assert_eq!(
+                message,
+                "Export main has signature (func (result i64)), expected (func)"
+            ),
+            _ => panic!("expected the signature mismatch to be rejected"),
+        }
+
+        let module = module_with_main(FunctionType::default());
+        assert!(trimmer.translate(&module).unwrap().is_none());
+    }
+
+    #[test]
+    fn with_config() {
+        let config = serde_yaml::from_str("{preset: ewasm, reject_mismatched: true}").unwrap();
+        let trimmer = TrimExports::with_config(&config).unwrap();
+        assert!(trimmer.reject_mismatched);
+
+        let config = serde_yaml::from_str("{preset: ewasm}").unwrap();
+        assert!(!TrimExports::with_config(&config).unwrap().reject_mismatched);
+
+        let config = serde_yaml::from_str("{preset: foo}").unwrap();
+        assert!(TrimExports::with_config(&config).is_err());
+    }
+
+    #[test]
+    fn custom_exports_with_config() {
+        let mut module = module_with_main(FunctionType::new(vec![], Some(ValueType::I32)));
+
+        let config = serde_yaml::from_str(
+            "{exports: [{kind: function, field: main, result: i32}, {kind: global, field: memory}]}",
+        )
+        .unwrap();
+        let trimmer = TrimExports::with_config(&config).unwrap();
+        assert!(trimmer.translate_inplace(&mut module).unwrap());
+        let exports = module.export_section().unwrap().entries();
+        assert_eq!(exports.len(), 1);
+        assert_eq!(exports[0].field(), "main");
+
+        let config = serde_yaml::from_str("{reject_mismatched: true}").unwrap();
+        assert!(TrimExports::with_config(&config).is_err());
+    }
+
+    #[test]
+    fn custom_exports_override_preset() {
+        let mut module = module_with_main(FunctionType::new(vec![ValueType::I32], None));
+
+        let config = serde_yaml::from_str(
+            "{preset: ewasm, exports: [{kind: function, field: main, params: [i32]}]}",
+        )
+        .unwrap();
+        let trimmer = TrimExports::with_config(&config).unwrap();
+        assert!(!trimmer.translate_inplace(&mut module).unwrap());
+        assert_eq!(module.export_section().unwrap().entries().len(), 2);
+    }
 }
@@ -0,0 +1,288 @@
+use std::collections::BTreeSet;
+use std::mem;
+
+use super::depgraph::{DepGraph, DepGraphBuilder};
+use super::utils::{
+    parse_config, remap, renumber_functions, renumber_names_section, NAME_TYPE_TYPE,
+};
+use super::{ModuleConfig, ModuleError, ModuleTranslator};
+use parity_wasm::elements::*;
+use serde_yaml::Value;
+
+/// Removes every function which is unreachable from the entry points of the module: exported
+/// functions, the start function and functions in element segments. Function indices are
+/// renumbered and types which are no longer used are removed as well. Imports are kept.
+#[derive(Default)]
+pub struct TrimFunctions;
+
+impl TrimFunctions {
+    pub fn new() -> Self {
+        TrimFunctions {}
+    }
+}
+
+/// Options accepted by `TrimFunctions::with_config`. There are none.
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Config {}
+
+impl ModuleConfig for TrimFunctions {
+    fn with_config(config: &Value) -> Result<Self, ModuleError> {
+        let _config: Config = parse_config(config)?;
+        Ok(TrimFunctions::new())
+    }
+}
+
+impl ModuleTranslator for TrimFunctions {
+    fn translate(&self, module: &Module) -> Result<Option<Module>, ModuleError> {
+        let mut ret = module.clone();
+        let trimmed_functions = trim_functions(&mut ret)?;
+        let trimmed_types = trim_types(&mut ret)?;
+        if trimmed_functions || trimmed_types {
+            Ok(Some(ret))
+        } else {
+            Ok(None)
+        }
+    }
+}
+
+/// Builds a mapping from old to new indices, where only the kept indices are numbered.
+fn build_mapping<F>(len: u32, keep: F) -> Vec<Option<u32>>
+where
+    F: Fn(u32) -> bool,
+{
+    let mut next = 0;
+    (0..len)
+        .map(|idx| {
+            if keep(idx) {
+                next += 1;
+                Some(next - 1)
+            } else {
+                None
+            }
+        })
+        .collect()
+}
+
+/// Removes the elements of a section whose index (offset by `base`) is not kept.
+fn retain_mapped<T>(entries: &mut Vec<T>, mapping: &[Option<u32>], base: usize) {
+    *entries = mem::take(entries)
+        .into_iter()
+        .enumerate()
+        .filter(|(idx, _)| mapping[base + idx].is_some())
+        .map(|(_, entry)| entry)
+        .collect();
+}
+
+fn trim_functions(module: &mut Module) -> Result<bool, ModuleError> {
+    let imports_len = module.import_count(ImportCountType::Function) as u32;
+    let funcs_len = module.functions_space() as u32;
+    if module.code_section().is_none() {
+        return Ok(false);
+    }
+
+    let graph = DepGraph::build_from_entry_points(module)
+        .map_err(|_| ModuleError::Custom("Failed to build the call graph".to_string()))?;
+    let reachable: BTreeSet<u32> = graph.reachable().into_iter().collect();
+    let mapping = build_mapping(funcs_len, |idx| {
+        idx < imports_len || reachable.contains(&idx)
+    });
+    if mapping.iter().all(Option::is_some) {
+        return Ok(false);
+    }
+
+    if let Some(section) = module.function_section_mut() {
+        retain_mapped(section.entries_mut(), &mapping, imports_len as usize);
+    }
+    if let Some(section) = module.code_section_mut() {
+        retain_mapped(section.bodies_mut(), &mapping, imports_len as usize);
+    }
+    renumber_functions(module, &mapping)?;
+
+    Ok(true)
+}
+
+/// Removes the types which are not referred to by any function or `call_indirect`.
+fn trim_types(module: &mut Module) -> Result<bool, ModuleError> {
+    let types_len = match module.type_section() {
+        Some(section) => section.types().len() as u32,
+        None => return Ok(false),
+    };
+
+    let mut used: BTreeSet<u32> = BTreeSet::new();
+    if let Some(section) = module.import_section() {
+        for entry in section.entries() {
+            if let External::Function(idx) = entry.external() {
+                used.insert(*idx);
+            }
+        }
+    }
+    if let Some(section) = module.function_section() {
+        used.extend(section.entries().iter().map(Func::type_ref));
+    }
+    if let Some(section) = module.code_section() {
+        for body in section.bodies() {
+            for instr in body.code().elements() {
+                if let Instruction::CallIndirect(idx, _) = instr {
+                    used.insert(*idx);
+                }
+            }
+        }
+    }
+
+    let mapping = build_mapping(types_len, |idx| used.contains(&idx));
+    if mapping.iter().all(Option::is_some) {
+        return Ok(false);
+    }
+
+    if let Some(section) = module.type_section_mut() {
+        retain_mapped(section.types_mut(), &mapping, 0);
+    }
+    if let Some(section) = module.import_section_mut() {
+        for entry in section.entries_mut() {
+            if let External::Function(idx) = entry.external_mut() {
+                *idx = remap(&mapping, *idx)?;
+            }
+        }
+    }
+    if let Some(section) = module.function_section_mut() {
+        for entry in section.entries_mut() {
+            *entry.type_ref_mut() = remap(&mapping, entry.type_ref())?;
+        }
+    }
+    if let Some(section) = module.code_section_mut() {
+        for body in section.bodies_mut() {
+            for instr in body.code_mut().elements_mut() {
+                if let Instruction::CallIndirect(idx, _) = instr {
+                    *idx = remap(&mapping, *idx)?;
+                }
+            }
+        }
+    }
+    renumber_names_section(module, NAME_TYPE_TYPE, &mapping)?;
+
+    Ok(true)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rustc_hex::FromHex;
+
+    #[test]
+    fn unreachable_removed() {
+        // wast:
+        // (module
+        //   (type $unused (func (param i64)))
+        //   (type $t (func))
+        //   (import "env" "a" (func $imp (param i32)))
+        //   (table 1 anyfunc)
+        //   (elem (i32.const 0) $elem)
+        //   (export "main" (func $main))
+        //   (start $start)
+        //   (func $dead1 (call $dead2 (i64.const 0)))
+        //   (func $main (call $used) (call_indirect (type $t) (i32.const 0)))
+        //   (func $dead2 (type $unused))
+        //   (func $used (call $imp (i32.const 1)))
+        //   (func $start)
+        //   (func $elem)
+        // )
+        let wasm: Vec<u8> = FromHex::from_hex(
+            "
+            0061736d01000000010c0360017e0060000060017f0002090103656e7601
+            610002030706010100010101040401700001070801046d61696e00020801
+            050907010041000b01060a22060600420010030b0900100441001101000b
+            02000b0600410110000b02000b02000b0042046e616d65012d070003696d
+            700105646561643102046d61696e03056465616432040475736564050573
+            746172740604656c656d040c020006756e75736564010174
+        ",
+        )
+        .unwrap();
+        let module = deserialize_buffer::<Module>(&wasm).unwrap();
+
+        let new = TrimFunctions::new()
+            .translate(&module)
+            .unwrap()
+            .expect("module was not modified");
+
+        // $imp, $main, $used, $start and $elem remain.
+        assert_eq!(new.functions_space(), 5);
+        match new.export_section().unwrap().entries()[0].internal() {
+            Internal::Function(idx) => assert_eq!(*idx, 1),
+            _ => panic!("main is not a function"),
+        }
+        assert_eq!(new.start_section(), Some(3));
+        assert_eq!(new.elements_section().unwrap().entries()[0].members(), &[4]);
+
+        // Only `(func)` and `(func (param i32))` are used anymore.
+        assert_eq!(new.type_section().unwrap().types().len(), 2);
+        match new.import_section().unwrap().entries()[0].external() {
+            External::Function(idx) => assert_eq!(*idx, 1),
+            _ => panic!("import is not a function"),
+        }
+        let main = &new.code_section().unwrap().bodies()[0];
+        assert_eq!(main.code().elements()[0], Instruction::Call(2));
+        assert_eq!(main.code().elements()[2], Instruction::CallIndirect(0, 0));
+
+        // The name of the removed type is dropped as well.
+        let names = new.custom_sections().find(|s| s.name() == "name").unwrap();
+        assert!(!names.payload().windows(6).any(|w| w == b"unused"));
+
+        let new = new.parse_names().unwrap();
+        match new.names_section() {
+            Some(NameSection::Function(names)) => {
+                let names: Vec<(u32, &str)> = names
+                    .names()
+                    .iter()
+                    .map(|(idx, name)| (idx, name.as_str()))
+                    .collect();
+                assert_eq!(
+                    names,
+                    vec![
+                        (0, "imp"),
+                        (1, "main"),
+                        (2, "used"),
+                        (3, "start"),
+                        (4, "elem")
+                    ]
+                );
+            }
+            _ => panic!("function names are missing"),
+        }
+
+        // The result must be a valid module.
+        let serialized = serialize(new).unwrap();
+        assert!(deserialize_buffer::<Module>(&serialized).is_ok());
+    }
+
+    #[test]
+    fn all_reachable() {
+        // wast:
+        // (module
+        //   (export "main" (func $main))
+        //   (func $main (call $a))
+        //   (func $a)
+        // )
+        let wasm: Vec<u8> = FromHex::from_hex(
+            "
+            0061736d010000000104016000000303020000070801046d61696e00000a
+            0902040010010b02000b0011046e616d65010a0200046d61696e010161
+        ",
+        )
+        .unwrap();
+        let mut module = deserialize_buffer::<Module>(&wasm).unwrap();
+
+        let trimmer = TrimFunctions::new();
+        assert_eq!(trimmer.translate(&module).unwrap(), None);
+        assert!(!trimmer.translate_inplace(&mut module).unwrap());
+    }
+
+    #[test]
+    fn with_config() {
+        let config = serde_yaml::from_str("{}").unwrap();
+        assert!(TrimFunctions::with_config(&config).is_ok());
+
+        let config = serde_yaml::from_str("{preset: ewasm}").unwrap();
+        assert!(TrimFunctions::with_config(&config).is_err());
+    }
+}
@@ -1,5 +1,7 @@
-use super::{ModuleError, ModulePreset, ModuleTranslator};
+use super::utils::{invalid_preset, parse_config, PresetConfig};
+use super::{ModuleConfig, ModuleError, ModulePreset, ModuleTranslator};
 use parity_wasm::elements::*;
+use serde_yaml::Value;
 
 pub struct TrimStartFunc;
 
@@ -23,6 +25,13 @@ impl ModulePreset for TrimStartFunc {
     }
 }
 
+impl ModuleConfig for TrimStartFunc {
+    fn with_config(config: &Value) -> Result<Self, ModuleError> {
+        let config: PresetConfig = parse_config(config)?;
+        TrimStartFunc::with_preset(&config.preset).map_err(|_| invalid_preset(&config.preset))
+    }
+}
+
 impl ModuleTranslator for TrimStartFunc {
     fn translate_inplace(&self, module: &mut Module) -> Result<bool, ModuleError> {
         Ok(self.trim_startfunc(module))
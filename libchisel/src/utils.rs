@@ -1,7 +1,19 @@
 //! These are helpers to be used internally.
 
+use std::collections::HashMap;
+use std::io::Cursor;
+use std::mem;
+
 use super::ModuleError;
-use parity_wasm::elements::{deserialize_buffer, serialize, Module};
+use parity_wasm::elements::{
+    deserialize_buffer, serialize, CodeSection, Deserialize, Error, ExportEntry, ExportSection,
+    External, Func, FuncBody, FunctionSection, FunctionType, GlobalEntry, GlobalSection,
+    GlobalType, ImportCountType, ImportEntry, ImportSection, IndexMap, Instruction, Internal,
+    Module, NameSection, ResizableLimits, Section, Serialize, Type, TypeSection, ValueType,
+    VarUint32, VarUint7,
+};
+use serde::de::DeserializeOwned;
+use serde_yaml::Value;
 
 pub trait HasNamesSection {
     /// Returns true if the module has a NamesSection.
@@ -37,6 +49,455 @@ impl SerializationHelpers for Module {
     }
 }
 
+/// Formats a function signature in the WebAssembly text format, e.g. `(func (param i32) (result i64))`.
+pub fn fmt_func_type(sig: &FunctionType) -> String {
+    let mut ret = String::from("(func");
+    if !sig.params().is_empty() {
+        ret.push_str(" (param");
+        for param in sig.params() {
+            ret.push_str(&format!(" {}", param));
+        }
+        ret.push(')');
+    }
+    if let Some(result) = sig.return_type() {
+        ret.push_str(&format!(" (result {})", result));
+    }
+    ret.push(')');
+    ret
+}
+
+/// Returns the type index of every function in the function index space, starting with imported
+/// functions.
+pub fn function_type_indices(module: &Module) -> Vec<u32> {
+    let mut ret: Vec<u32> = match module.import_section() {
+        Some(section) => section
+            .entries()
+            .iter()
+            .filter_map(|entry| match entry.external() {
+                External::Function(type_idx) => Some(*type_idx),
+                _ => None,
+            })
+            .collect(),
+        None => Vec::new(),
+    };
+    if let Some(section) = module.function_section() {
+        ret.extend(section.entries().iter().map(|func| func.type_ref()));
+    }
+    ret
+}
+
+/// Resolves the signature of a function from its index in the function index space.
+pub fn function_signature(module: &Module, func_idx: u32) -> Option<&FunctionType> {
+    let type_idx = *function_type_indices(module).get(func_idx as usize)?;
+    match module.type_section()?.types().get(type_idx as usize)? {
+        Type::Function(sig) => Some(sig),
+    }
+}
+
+/// Identifiers of the names subsections which refer to function or type indices.
+pub const NAME_TYPE_FUNCTION: u8 = 1;
+pub const NAME_TYPE_LOCAL: u8 = 2;
+pub const NAME_TYPE_TYPE: u8 = 4;
+
+/// Splits the payload of an unparsed names section into its subsections.
+pub fn names_subsections(payload: &[u8]) -> Result<Vec<(u8, &[u8])>, ModuleError> {
+    let mut rdr = Cursor::new(payload);
+    let mut ret = Vec::new();
+    while (rdr.position() as usize) < payload.len() {
+        let name_type: u8 = VarUint7::deserialize(&mut rdr)?.into();
+        let size: u32 = VarUint32::deserialize(&mut rdr)?.into();
+        let start = rdr.position() as usize;
+        let end = start + size as usize;
+        if end > payload.len() {
+            return Err(ModuleError::Custom("Malformed names section".to_string()));
+        }
+        ret.push((name_type, &payload[start..end]));
+        rdr.set_position(end as u64);
+    }
+    Ok(ret)
+}
+
+/// Returns the function names from the names section, whether it was parsed or not. A malformed
+/// names section is ignored.
+pub fn function_names(module: &Module) -> HashMap<u32, String> {
+    let mut ret = HashMap::new();
+    for section in module.sections() {
+        match section {
+            Section::Custom(custom) if custom.name() == "name" => {
+                let subsections = names_subsections(custom.payload()).unwrap_or_default();
+                for (name_type, subsection) in subsections {
+                    if name_type == NAME_TYPE_FUNCTION {
+                        // Keep the names read before an error.
+                        let _ = read_name_map(subsection, &mut ret);
+                    }
+                }
+            }
+            Section::Name(NameSection::Function(names)) => {
+                ret.extend(names.names().iter().map(|(idx, name)| (idx, name.clone())));
+            }
+            _ => (),
+        }
+    }
+    ret
+}
+
+fn read_name_map(payload: &[u8], names: &mut HashMap<u32, String>) -> Result<(), ModuleError> {
+    let mut rdr = Cursor::new(payload);
+    let count: u32 = VarUint32::deserialize(&mut rdr)?.into();
+    for _ in 0..count {
+        let idx: u32 = VarUint32::deserialize(&mut rdr)?.into();
+        names.insert(idx, String::deserialize(&mut rdr)?);
+    }
+    Ok(())
+}
+
+/// Looks up the new index of a function or type. Fails if it was removed, which can only happen
+/// with an invalid module.
+pub fn remap(mapping: &[Option<u32>], idx: u32) -> Result<u32, ModuleError> {
+    match mapping.get(idx as usize) {
+        Some(Some(new_idx)) => Ok(*new_idx),
+        _ => Err(ModuleError::Custom(format!("Invalid index: {}", idx))),
+    }
+}
+
+/// Renumbers every reference to a function: calls, exports, element segments, the start function
+/// and the names section. The mapping must cover the whole function index space.
+pub fn renumber_functions(module: &mut Module, mapping: &[Option<u32>]) -> Result<(), ModuleError> {
+    if let Some(section) = module.code_section_mut() {
+        for body in section.bodies_mut() {
+            for instr in body.code_mut().elements_mut() {
+                if let Instruction::Call(idx) = instr {
+                    *idx = remap(mapping, *idx)?;
+                }
+            }
+        }
+    }
+    if let Some(section) = module.export_section_mut() {
+        for entry in section.entries_mut() {
+            if let Internal::Function(idx) = entry.internal_mut() {
+                *idx = remap(mapping, *idx)?;
+            }
+        }
+    }
+    if let Some(section) = module.elements_section_mut() {
+        for entry in section.entries_mut() {
+            for idx in entry.members_mut() {
+                *idx = remap(mapping, *idx)?;
+            }
+        }
+    }
+    if let Some(idx) = module.start_section() {
+        module.set_start_section(remap(mapping, idx)?);
+    }
+    renumber_names_section(module, NAME_TYPE_FUNCTION, mapping)?;
+    renumber_names_section(module, NAME_TYPE_LOCAL, mapping)
+}
+
+/// Returns the index of a function type, adding it to the type section if it is not present yet.
+pub fn add_type(module: &mut Module, sig: FunctionType) -> u32 {
+    if module.type_section().is_none() {
+        insert_section(module, Section::Type(TypeSection::with_types(Vec::new())));
+    }
+    let types = module
+        .type_section_mut()
+        .expect("type section was just inserted")
+        .types_mut();
+    match types
+        .iter()
+        .position(|ty| matches!(ty, Type::Function(existing) if *existing == sig))
+    {
+        Some(idx) => idx as u32,
+        None => {
+            types.push(Type::Function(sig));
+            (types.len() - 1) as u32
+        }
+    }
+}
+
+/// Appends function imports to the import section and renumbers the functions defined in the
+/// module, which are shifted by the number of new imports. Returns the index of the first new
+/// import.
+pub fn add_function_imports(
+    module: &mut Module,
+    entries: Vec<ImportEntry>,
+) -> Result<u32, ModuleError> {
+    let imports_len = module.import_count(ImportCountType::Function) as u32;
+    let added = entries.len() as u32;
+    if added == 0 {
+        return Ok(imports_len);
+    }
+    let mapping: Vec<Option<u32>> = (0..module.functions_space() as u32)
+        .map(|idx| Some(if idx < imports_len { idx } else { idx + added }))
+        .collect();
+    renumber_functions(module, &mapping)?;
+
+    if module.import_section().is_none() {
+        insert_section(
+            module,
+            Section::Import(ImportSection::with_entries(Vec::new())),
+        );
+    }
+    module
+        .import_section_mut()
+        .expect("import section was just inserted")
+        .entries_mut()
+        .extend(entries);
+    Ok(imports_len)
+}
+
+/// Appends a global to the global section and returns its index in the global index space.
+pub fn add_global(module: &mut Module, entry: GlobalEntry) -> u32 {
+    let imports_len = module.import_count(ImportCountType::Global) as u32;
+    if module.global_section().is_none() {
+        insert_section(
+            module,
+            Section::Global(GlobalSection::with_entries(Vec::new())),
+        );
+    }
+    let entries = module
+        .global_section_mut()
+        .expect("global section was just inserted")
+        .entries_mut();
+    entries.push(entry);
+    imports_len + entries.len() as u32 - 1
+}
+
+/// Appends a function to the module and returns its index in the function index space.
+pub fn add_function(module: &mut Module, sig: FunctionType, body: FuncBody) -> u32 {
+    let imports_len = module.import_count(ImportCountType::Function) as u32;
+    let type_idx = add_type(module, sig);
+    if module.function_section().is_none() {
+        insert_section(
+            module,
+            Section::Function(FunctionSection::with_entries(Vec::new())),
+        );
+    }
+    if module.code_section().is_none() {
+        insert_section(module, Section::Code(CodeSection::with_bodies(Vec::new())));
+    }
+    module
+        .function_section_mut()
+        .expect("function section was just inserted")
+        .entries_mut()
+        .push(Func::new(type_idx));
+    let bodies = module
+        .code_section_mut()
+        .expect("code section was just inserted")
+        .bodies_mut();
+    bodies.push(body);
+    imports_len + bodies.len() as u32 - 1
+}
+
+/// Position of a known section in a module. Custom sections may appear anywhere.
+fn section_order(section: &Section) -> Option<u8> {
+    match section {
+        Section::Type(_) => Some(1),
+        Section::Import(_) => Some(2),
+        Section::Function(_) => Some(3),
+        Section::Table(_) => Some(4),
+        Section::Memory(_) => Some(5),
+        Section::Global(_) => Some(6),
+        Section::Export(_) => Some(7),
+        Section::Start(_) => Some(8),
+        Section::Element(_) => Some(9),
+        Section::Code(_) => Some(10),
+        Section::Data(_) => Some(11),
+        _ => None,
+    }
+}
+
+/// Inserts a known section before the first section which must follow it.
+fn insert_section(module: &mut Module, section: Section) {
+    let order = section_order(&section);
+    let sections = module.sections_mut();
+    let position = sections
+        .iter()
+        .position(|existing| section_order(existing) > order)
+        .unwrap_or(sections.len());
+    sections.insert(position, section);
+}
+
+/// Exports a function under the given name, replacing any export with that name. The export
+/// section is created if missing.
+pub fn export_function(module: &mut Module, field: &str, func_idx: u32) {
+    let entry = ExportEntry::new(field.to_string(), Internal::Function(func_idx));
+    match module.export_section_mut() {
+        Some(section) => {
+            let entries = section.entries_mut();
+            match entries.iter().position(|e| e.field() == field) {
+                Some(idx) => entries[idx] = entry,
+                None => entries.push(entry),
+            }
+        }
+        None => insert_section(
+            module,
+            Section::Export(ExportSection::with_entries(vec![entry])),
+        ),
+    }
+}
+
+/// Renumbers the indices in a subsection of the names section, whether it was parsed or not.
+/// Names of removed items are dropped.
+pub fn renumber_names_section(
+    module: &mut Module,
+    name_type: u8,
+    mapping: &[Option<u32>],
+) -> Result<(), ModuleError> {
+    for section in module.sections_mut() {
+        match (section, name_type) {
+            (Section::Custom(custom), _) if custom.name() == "name" => {
+                let payload = renumber_names_payload(custom.payload(), name_type, mapping)?;
+                *custom.payload_mut() = payload;
+            }
+            (Section::Name(NameSection::Function(names)), NAME_TYPE_FUNCTION) => {
+                renumber_index_map(names.names_mut(), mapping)
+            }
+            (Section::Name(NameSection::Local(names)), NAME_TYPE_LOCAL) => {
+                renumber_index_map(names.local_names_mut(), mapping)
+            }
+            _ => (),
+        }
+    }
+    Ok(())
+}
+
+fn renumber_index_map<T>(map: &mut IndexMap<T>, mapping: &[Option<u32>]) {
+    *map = mem::replace(map, IndexMap::with_capacity(0))
+        .into_iter()
+        .filter_map(|(idx, value)| match mapping.get(idx as usize) {
+            Some(Some(new_idx)) => Some((*new_idx, value)),
+            _ => None,
+        })
+        .collect();
+}
+
+/// Renumbers one subsection of an unparsed names section. Other subsections are copied as-is.
+fn renumber_names_payload(
+    payload: &[u8],
+    name_type: u8,
+    mapping: &[Option<u32>],
+) -> Result<Vec<u8>, ModuleError> {
+    let mut ret = Vec::new();
+    for (subsection_type, subsection) in names_subsections(payload)? {
+        let subsection = if subsection_type != name_type {
+            subsection.to_vec()
+        } else if name_type == NAME_TYPE_LOCAL {
+            renumber_name_entries(subsection, mapping, skip_name_map)?
+        } else {
+            renumber_name_entries(subsection, mapping, skip_name)?
+        };
+        VarUint7::from(subsection_type).serialize(&mut ret)?;
+        VarUint32::from(subsection.len()).serialize(&mut ret)?;
+        ret.extend(subsection);
+    }
+    Ok(ret)
+}
+
+/// Renumbers a list of entries keyed by function or type index. The values are copied as-is, using
+/// `skip_value` to find where each of them ends.
+fn renumber_name_entries(
+    subsection: &[u8],
+    mapping: &[Option<u32>],
+    skip_value: fn(&mut Cursor<&[u8]>) -> Result<(), Error>,
+) -> Result<Vec<u8>, ModuleError> {
+    let mut rdr = Cursor::new(subsection);
+    let count: u32 = VarUint32::deserialize(&mut rdr)?.into();
+    let mut entries: Vec<(u32, &[u8])> = Vec::new();
+    for _ in 0..count {
+        let idx: u32 = VarUint32::deserialize(&mut rdr)?.into();
+        let start = rdr.position() as usize;
+        skip_value(&mut rdr)?;
+        if let Some(Some(new_idx)) = mapping.get(idx as usize) {
+            entries.push((*new_idx, &subsection[start..rdr.position() as usize]));
+        }
+    }
+
+    let mut ret = Vec::new();
+    VarUint32::from(entries.len()).serialize(&mut ret)?;
+    for (idx, value) in entries {
+        VarUint32::from(idx).serialize(&mut ret)?;
+        ret.extend_from_slice(value);
+    }
+    Ok(ret)
+}
+
+fn skip_name(rdr: &mut Cursor<&[u8]>) -> Result<(), Error> {
+    String::deserialize(rdr).map(|_| ())
+}
+
+fn skip_name_map(rdr: &mut Cursor<&[u8]>) -> Result<(), Error> {
+    let count: u32 = VarUint32::deserialize(rdr)?.into();
+    for _ in 0..count {
+        VarUint32::deserialize(rdr)?;
+        skip_name(rdr)?;
+    }
+    Ok(())
+}
+
+/// Deserializes module options into a typed configuration structure.
+pub fn parse_config<T>(config: &Value) -> Result<T, ModuleError>
+where
+    T: DeserializeOwned,
+{
+    Ok(serde_yaml::from_value(config.clone())?)
+}
+
+/// Formats a global type in the WebAssembly text format, e.g. `(global (mut i32))`.
+pub fn fmt_global_type(global_type: &GlobalType) -> String {
+    if global_type.is_mutable() {
+        format!("(global (mut {}))", global_type.content_type())
+    } else {
+        format!("(global {})", global_type.content_type())
+    }
+}
+
+/// Formats the limits of a memory or table in the WebAssembly text format, e.g. `(memory 1 2)`.
+pub fn fmt_limits(kind: &str, limits: &ResizableLimits) -> String {
+    match limits.maximum() {
+        Some(maximum) => format!("({} {} {})", kind, limits.initial(), maximum),
+        None => format!("({} {})", kind, limits.initial()),
+    }
+}
+
+/// Value type, as written in definition files.
+#[derive(Deserialize, Clone, Copy)]
+#[serde(rename_all = "lowercase")]
+pub enum ValueTypeDefinition {
+    I32,
+    I64,
+    F32,
+    F64,
+}
+
+impl From<ValueTypeDefinition> for ValueType {
+    fn from(definition: ValueTypeDefinition) -> Self {
+        match definition {
+            ValueTypeDefinition::I32 => ValueType::I32,
+            ValueTypeDefinition::I64 => ValueType::I64,
+            ValueTypeDefinition::F32 => ValueType::F32,
+            ValueTypeDefinition::F64 => ValueType::F64,
+        }
+    }
+}
+
+/// Options of modules which are only configured through a preset.
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PresetConfig {
+    pub preset: String,
+}
+
+/// Returns the error used when a configuration names an unknown preset.
+pub fn invalid_preset(preset: &str) -> ModuleError {
+    ModuleError::Custom(format!("Invalid preset: {}", preset))
+}
+
+impl From<serde_yaml::Error> for ModuleError {
+    fn from(a: serde_yaml::Error) -> Self {
+        ModuleError::Custom(format!("Invalid configuration: {}", a))
+    }
+}
+
 impl From<parity_wasm::SerializationError> for ModuleError {
     fn from(a: parity_wasm::SerializationError) -> Self {
         use std::error::Error;
@@ -86,6 +547,20 @@ mod tests {
         assert_eq!(module.has_names_section(), false);
     }
 
+    #[test]
+    fn fmt_signatures() {
+        use parity_wasm::elements::ValueType;
+
+        assert_eq!(fmt_func_type(&FunctionType::default()), "(func)");
+        assert_eq!(
+            fmt_func_type(&FunctionType::new(
+                vec![ValueType::I32, ValueType::I32],
+                Some(ValueType::I64)
+            )),
+            "(func (param i32 i32) (result i64))"
+        );
+    }
+
     fn try_serialize(module: Module) -> Result<Vec<u8>, ModuleError> {
         Ok(serialize::<Module>(module)?)
     }
@@ -1,24 +1,17 @@
-use super::{ModuleError, ModulePreset, ModuleValidator};
-use parity_wasm::elements::{
-    ExportSection, External, FunctionSection, FunctionType, ImportSection, Internal, Module, Type,
+use super::{
+    exports::{describe_export, ExportList},
+    report::{Finding, FindingKind, ValidationReport},
+    utils::{invalid_preset, parse_config},
+    ModuleConfig, ModuleError, ModulePreset, ModuleValidator,
 };
+use parity_wasm::elements::{ExportEntry, Module};
+use serde_yaml::Value;
 
-/// Enum representing a type of export and any extra data to check.
-pub enum ExportType<'a> {
-    Function(&'a str, FunctionType),
-    Global(&'a str),
-    Memory(&'a str),
-    Table(&'a str),
-}
-
-/// Trait over ExportType that lets a caller check if it is exported in a given module.
-trait IsExported {
-    fn is_exported(&self, module: &Module) -> bool;
-}
+pub use super::exports::ExportType;
 
 /// Struct on which ModuleValidator is implemented.
 pub struct VerifyExports<'a> {
-    entries: Vec<ExportType<'a>>,
+    list: ExportList<'a>,
     allow_unlisted: bool,
 }
 
@@ -26,10 +19,7 @@ impl<'a> ModulePreset for VerifyExports<'a> {
     fn with_preset(preset: &str) -> Result<Self, ()> {
         match preset {
             "ewasm" => Ok(VerifyExports {
-                entries: vec![
-                    ExportType::Function("main", FunctionType::default()),
-                    ExportType::Memory("memory"),
-                ],
+                list: ExportList::with_preset(preset)?,
                 allow_unlisted: false,
             }),
             _ => Err(()),
@@ -37,166 +27,112 @@ impl<'a> ModulePreset for VerifyExports<'a> {
     }
 }
 
-impl<'a> ModuleValidator for VerifyExports<'a> {
-    fn validate(&self, module: &Module) -> Result<bool, ModuleError> {
-        // FIXME: This validating algorithm runs in O(n^2). Needs to be optimized
-        let required_exports_not_found = self
-            .entries
-            .iter()
-            .map(|e| e.is_exported(module))
-            .find(|e| *e == false)
-            .is_some();
-
-        if required_exports_not_found {
-            return Ok(false);
-        }
-
-        let module_export_count = if let Some(section) = module.export_section() {
-            section.entries().len()
-        } else {
-            0
-        };
-
-        if self.entries.len() != module_export_count {
-            Ok(self.allow_unlisted)
-        } else {
-            Ok(true)
+impl<'a> VerifyExports<'a> {
+    pub fn new(list: ExportList<'a>, allow_unlisted: bool) -> Self {
+        VerifyExports {
+            list,
+            allow_unlisted,
         }
     }
-}
 
-impl<'a> IsExported for ExportType<'a> {
-    fn is_exported(&self, module: &Module) -> bool {
-        if let Some(section) = module.export_section() {
-            match self {
-                ExportType::Function(field, sig) => has_func_export(module, field, sig),
-                ExportType::Global(field) => has_global_export(section, field),
-                ExportType::Memory(field) => has_memory_export(section, field),
-                ExportType::Table(field) => has_table_export(section, field),
-            }
-        } else {
-            false
-        }
+    pub fn set_allow_unlisted(&mut self, arg: bool) {
+        self.allow_unlisted = arg;
     }
 }
 
-// NOTE: has_*_export is implemented with repeating code because you can't implement a trait for
-// enum variants, as they are not types. Furthermore, having one helper for non-func exports would
-// be ugly because information about the export type must still be passed down to check that an
-// export is of the correct kind.
-
-/// Checks if a global is exported with the given name.
-fn has_global_export(section: &ExportSection, field: &str) -> bool {
-    if let Some(ref export) = section.entries().iter().find(|e| e.field() == field) {
-        match export.internal() {
-            Internal::Global(_index) => true,
-            _ => false,
-        }
-    } else {
-        false
-    }
+/// Options accepted by `VerifyExports::with_config`.
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Config {
+    preset: Option<String>,
+    /// Path to an interface definition file, which is checked in addition to the preset.
+    interface: Option<String>,
+    /// Exports checked in addition to the preset and the interface.
+    exports: Option<ExportList<'static>>,
+    allow_unlisted: Option<bool>,
 }
 
-/// Checks if a memory is exported with the given name.
-fn has_memory_export(section: &ExportSection, field: &str) -> bool {
-    if let Some(ref export) = section.entries().iter().find(|e| e.field() == field) {
-        match export.internal() {
-            Internal::Memory(_index) => true,
-            _ => false,
+impl<'a> ModuleConfig for VerifyExports<'a> {
+    fn with_config(config: &Value) -> Result<Self, ModuleError> {
+        let config: Config = parse_config(config)?;
+        let mut ret = match (&config.preset, &config.interface, &config.exports) {
+            (Some(preset), _, _) => {
+                VerifyExports::with_preset(preset).map_err(|_| invalid_preset(preset))?
+            }
+            (None, None, None) => {
+                return Err(ModuleError::Custom(
+                    "Either a preset, an interface or exports must be set".to_string(),
+                ))
+            }
+            (None, _, _) => VerifyExports::new(ExportList::new(), false),
+        };
+        if let Some(path) = config.interface {
+            ret.list.concatenate(ExportList::from_file(path)?);
+        }
+        if let Some(exports) = config.exports {
+            ret.list.concatenate(exports);
         }
-    } else {
-        false
+        if let Some(allow_unlisted) = config.allow_unlisted {
+            ret.allow_unlisted = allow_unlisted;
+        }
+        Ok(ret)
     }
 }
 
-/// Checks if a table is exported with the given name.
-fn has_table_export(section: &ExportSection, field: &str) -> bool {
-    if let Some(ref export) = section.entries().iter().find(|e| e.field() == field) {
-        match export.internal() {
-            Internal::Table(_index) => true,
-            _ => false,
+impl<'a> ModuleValidator for VerifyExports<'a> {
+    fn report(&self, module: &Module) -> Result<ValidationReport, ModuleError> {
+        let mut report = ValidationReport::new();
+
+        // FIXME: This validating algorithm runs in O(n^2). Needs to be optimized
+        for entry in self.list.entries() {
+            let expected = entry.export();
+            // If an export with the same name exists, it is of the wrong kind or signature.
+            let finding = match find_export(module, expected.field()) {
+                Some(export) if expected.matches(module, export) => continue,
+                Some(export) => Finding::error(FindingKind::MalformedExport)
+                    .with_actual(describe_export(module, export)),
+                None if entry.is_required() => Finding::error(FindingKind::MissingExport),
+                None => continue,
+            };
+            report.push(
+                finding
+                    .with_field(expected.field())
+                    .with_expected(expected.describe()),
+            );
         }
-    } else {
-        false
-    }
-}
 
-// NOTE: this is kind of hacked on. It works, but a refactor would make it more in line with the other
-// helpers.
-/// Checks if a function is exported with the given name.
-fn has_func_export(module: &Module, field: &str, sig: &FunctionType) -> bool {
-    if let Some(section) = module.export_section() {
-        match func_export_index_by_name(section, field) {
-            Some(index) => {
-                if let Some(resolved) = func_sig_by_index(module, index) {
-                    *sig == *resolved
+        if let Some(section) = module.export_section() {
+            for export in section.entries().iter().filter(|export| {
+                !self
+                    .list
+                    .entries()
+                    .iter()
+                    .any(|e| e.export().field() == export.field())
+            }) {
+                let finding = if self.allow_unlisted {
+                    Finding::warning(FindingKind::UnlistedExport)
                 } else {
-                    false
-                }
+                    Finding::error(FindingKind::UnlistedExport)
+                };
+                report.push(
+                    finding
+                        .with_field(export.field())
+                        .with_actual(describe_export(module, export)),
+                );
             }
-            None => false,
         }
-    } else {
-        false
-    }
-}
 
-/// Resolves a function's signature from its internal index.
-fn func_sig_by_index(module: &Module, index: u32) -> Option<&FunctionType> {
-    if let Some(func_section) = module.function_section() {
-        match (module.type_section(), module.import_section()) {
-            // If we have function imports in the section, subtract them from the function's index
-            // because their signatures are not in the types section.
-            (Some(type_section), Some(import_section)) => match type_section.types()[func_type_ref(
-                &func_section,
-                index - func_import_section_len(import_section),
-            )] {
-                Type::Function(ref ret) => Some(ret),
-                _ => None,
-            },
-            // If no function imports are present, no need to subtract them.
-            (Some(type_section), None) => {
-                match type_section.types()[func_type_ref(&func_section, index)] {
-                    Type::Function(ref ret) => Some(ret),
-                    _ => None,
-                }
-            }
-            (None, Some(import_section)) => None,
-            (None, None) => None,
-        }
-    } else {
-        None
+        Ok(report)
     }
 }
 
-/// Returns the internal reference to a function's type signature.
-fn func_type_ref(funcs: &FunctionSection, func_index: u32) -> usize {
-    funcs.entries()[func_index as usize].type_ref() as usize
-}
-
-/// Returns the number of functions in the function section that are imported.
-fn func_import_section_len(imports: &ImportSection) -> u32 {
-    imports
+/// Finds an export entry by name.
+fn find_export<'m>(module: &'m Module, field: &str) -> Option<&'m ExportEntry> {
+    module
+        .export_section()?
         .entries()
         .iter()
-        .filter(|e| match e.external() {
-            &External::Function(_) => true,
-            _ => false,
-        })
-        .count() as u32
-}
-
-/// Resolves a function export's index by name. Can be trivially adjusted for
-/// all types of exports.
-fn func_export_index_by_name(exports: &ExportSection, field_str: &str) -> Option<u32> {
-    if let Some(entry) = exports.entries().iter().find(|e| e.field() == field_str) {
-        match entry.internal() {
-            Internal::Function(index) => Some(*index),
-            _ => None,
-        }
-    } else {
-        None
-    }
+        .find(|e| e.field() == field)
 }
 
 #[cfg(test)]
@@ -433,14 +369,144 @@ mod tests {
         ];
 
         let module = deserialize_buffer::<Module>(&wasm).unwrap();
-        let checker = VerifyExports {
-            entries: vec![
-                ExportType::Function("main", FunctionType::default()),
-                ExportType::Memory("memory"),
-            ],
-            allow_unlisted: true,
-        };
+        let checker = VerifyExports::new(ExportList::with_preset("ewasm").unwrap(), true);
         let result = checker.validate(&module).unwrap();
         assert_eq!(true, result);
     }
+
+    #[test]
+    fn report_mem_export_points_to_main_ewasm() {
+        // wast:
+        // (module
+        //   (memory 1)
+        //   (export "main" (func $main))
+        //   (export "memory" (func $main))
+        //   (func $main)
+        // )
+        let wasm: Vec<u8> = vec![
+            0x00, 0x61, 0x73, 0x6d, 0x01, 0x00, 0x00, 0x00, 0x01, 0x04, 0x01, 0x60, 0x00, 0x00,
+            0x03, 0x02, 0x01, 0x00, 0x05, 0x03, 0x01, 0x00, 0x01, 0x07, 0x11, 0x02, 0x06, 0x6d,
+            0x65, 0x6d, 0x6f, 0x72, 0x79, 0x00, 0x00, 0x04, 0x6d, 0x61, 0x69, 0x6e, 0x00, 0x00,
+            0x0a, 0x04, 0x01, 0x02, 0x00, 0x0b,
+        ];
+
+        let module = deserialize_buffer::<Module>(&wasm).unwrap();
+        let checker = VerifyExports::with_preset("ewasm").unwrap();
+        let report = checker.report(&module).unwrap();
+        assert_eq!(
+            report.findings(),
+            &[Finding::error(FindingKind::MalformedExport)
+                .with_field("memory")
+                .with_expected("memory".to_string())
+                .with_actual("(func)".to_string())]
+        );
+    }
+
+    #[test]
+    fn report_missing_and_unlisted_ewasm() {
+        // wast:
+        // (module
+        //   (memory 1)
+        //   (export "man" (func $main))
+        //   (export "memory" (memory 0))
+        //   (func $main)
+        // )
+        let wasm: Vec<u8> = vec![
+            0x00, 0x61, 0x73, 0x6d, 0x01, 0x00, 0x00, 0x00, 0x01, 0x04, 0x01, 0x60, 0x00, 0x00,
+            0x03, 0x02, 0x01, 0x00, 0x05, 0x03, 0x01, 0x00, 0x01, 0x07, 0x10, 0x02, 0x06, 0x6d,
+            0x65, 0x6d, 0x6f, 0x72, 0x79, 0x02, 0x00, 0x03, 0x6d, 0x61, 0x6e, 0x00, 0x00, 0x0a,
+            0x04, 0x01, 0x02, 0x00, 0x0b,
+        ];
+
+        let module = deserialize_buffer::<Module>(&wasm).unwrap();
+        let checker = VerifyExports::with_preset("ewasm").unwrap();
+        let report = checker.report(&module).unwrap();
+        assert_eq!(
+            report.findings(),
+            &[
+                Finding::error(FindingKind::MissingExport)
+                    .with_field("main")
+                    .with_expected("(func)".to_string()),
+                Finding::error(FindingKind::UnlistedExport)
+                    .with_field("man")
+                    .with_actual("(func)".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn extra_export_allowed_with_config() {
+        // wast:
+        // (module
+        //   (memory 1)
+        //   (export "main" (func $main))
+        //   (export "memory" (memory 0))
+        //   (export "foo" (func $main))
+        //   (func $main)
+        // )
+        let wasm: Vec<u8> = vec![
+            0x00, 0x61, 0x73, 0x6d, 0x01, 0x00, 0x00, 0x00, 0x01, 0x04, 0x01, 0x60, 0x00, 0x00,
+            0x03, 0x02, 0x01, 0x00, 0x05, 0x03, 0x01, 0x00, 0x01, 0x07, 0x1a, 0x03, 0x06, 0x6d,
+            0x65, 0x6d, 0x6f, 0x72, 0x79, 0x02, 0x00, 0x04, 0x6d, 0x61, 0x69, 0x6e, 0x00, 0x00,
+            0x06, 0x66, 0x6f, 0x6f, 0x62, 0x61, 0x72, 0x00, 0x00, 0x0a, 0x04, 0x01, 0x02, 0x00,
+            0x0b,
+        ];
+
+        let module = deserialize_buffer::<Module>(&wasm).unwrap();
+        let config = serde_yaml::from_str("{preset: ewasm, allow_unlisted: true}").unwrap();
+        let checker = VerifyExports::with_config(&config).unwrap();
+        let result = checker.validate(&module).unwrap();
+        assert!(result);
+
+        let config = serde_yaml::from_str("{preset: ewasm, allow_extra: true}").unwrap();
+        assert!(VerifyExports::with_config(&config).is_err());
+    }
+
+    #[test]
+    fn custom_exports_with_config() {
+        // wast:
+        // (module
+        //   (memory 1)
+        //   (export "main" (func $main))
+        //   (export "memory" (memory 0))
+        //   (func $main)
+        // )
+        let wasm: Vec<u8> = vec![
+            0x00, 0x61, 0x73, 0x6d, 0x01, 0x00, 0x00, 0x00, 0x01, 0x04, 0x01, 0x60, 0x00, 0x00,
+            0x03, 0x02, 0x01, 0x00, 0x05, 0x03, 0x01, 0x00, 0x01, 0x07, 0x11, 0x02, 0x04, 0x6d,
+            0x61, 0x69, 0x6e, 0x00, 0x00, 0x06, 0x6d, 0x65, 0x6d, 0x6f, 0x72, 0x79, 0x02, 0x00,
+            0x0a, 0x04, 0x01, 0x02, 0x00, 0x0b,
+        ];
+
+        let module = deserialize_buffer::<Module>(&wasm).unwrap();
+        let config = serde_yaml::from_str(
+            "{exports: [
+                {kind: function, field: main, result: i32},
+                {kind: global, field: version, optional: true},
+                {kind: table, field: table},
+              ],
+              allow_unlisted: true}",
+        )
+        .unwrap();
+        let checker = VerifyExports::with_config(&config).unwrap();
+        let report = checker.report(&module).unwrap();
+        assert_eq!(
+            report.findings(),
+            &[
+                Finding::error(FindingKind::MalformedExport)
+                    .with_field("main")
+                    .with_expected("(func (result i32))".to_string())
+                    .with_actual("(func)".to_string()),
+                Finding::error(FindingKind::MissingExport)
+                    .with_field("table")
+                    .with_expected("table".to_string()),
+                Finding::warning(FindingKind::UnlistedExport)
+                    .with_field("memory")
+                    .with_actual("memory".to_string()),
+            ]
+        );
+
+        let config = serde_yaml::from_str("{allow_unlisted: true}").unwrap();
+        assert!(VerifyExports::with_config(&config).is_err());
+    }
 }
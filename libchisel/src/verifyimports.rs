@@ -1,8 +1,13 @@
 use super::{
     imports::{ImportList, ImportType},
-    ModuleError, ModulePreset, ModuleValidator,
+    report::{Finding, FindingKind, ValidationReport},
+    utils::{fmt_func_type, fmt_global_type, fmt_limits, invalid_preset, parse_config},
+    ModuleConfig, ModuleError, ModulePreset, ModuleValidator,
 };
-use parity_wasm::elements::{External, FunctionType, ImportSection, Module, Type, ValueType};
+use parity_wasm::elements::{
+    External, FunctionType, ImportEntry, Module, ResizableLimits, Type, ValueType,
+};
+use serde_yaml::Value;
 
 /// Enum representing the state of an import in a module.
 #[derive(PartialEq)]
@@ -12,12 +17,6 @@ pub enum ImportStatus {
     Malformed,
 }
 
-/// Trait over ImportType that lets a caller check if it is imported in a given module, and
-/// verifies its type signature is correct.
-trait IsImported {
-    fn is_imported(&self, module: &Module) -> bool;
-}
-
 /// Trait over ImportType that checks an import's type signature in the case that it is imported.
 trait ImportCheck {
     fn check(&self, module: &Module) -> ImportStatus;
@@ -34,13 +33,25 @@ pub struct VerifyImports<'a> {
     allow_unlisted: bool,
 }
 
+impl<'a> VerifyImports<'a> {
+    /// Constructs a validator checking against an arbitrary list of imports, e.g. one loaded with
+    /// `ImportList::from_file`.
+    pub fn new(list: ImportList<'a>, require_all: bool, allow_unlisted: bool) -> Self {
+        VerifyImports {
+            list,
+            require_all,
+            allow_unlisted,
+        }
+    }
+}
+
 impl<'a> ModulePreset for VerifyImports<'a> {
     fn with_preset(preset: &str) -> Result<Self, ()> {
         let mut import_set = ImportList::new();
         let presets: String = preset.chars().filter(|c| *c != ' ' && *c != '_').collect();
 
         for preset_individual in presets.split(',') {
-            let to_append = ImportList::with_preset(preset_individual).expect("Invalid preset");
+            let to_append = ImportList::with_preset(preset_individual)?;
             import_set.concatenate(to_append);
         }
 
@@ -52,6 +63,44 @@ impl<'a> ModulePreset for VerifyImports<'a> {
     }
 }
 
+/// Options accepted by `VerifyImports::with_config`.
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Config {
+    preset: Option<String>,
+    /// Path to an interface definition file, which is checked in addition to the preset.
+    interface: Option<String>,
+    require_all: Option<bool>,
+    allow_unlisted: Option<bool>,
+}
+
+impl<'a> ModuleConfig for VerifyImports<'a> {
+    fn with_config(config: &Value) -> Result<Self, ModuleError> {
+        let config: Config = parse_config(config)?;
+        let mut ret = match (&config.preset, &config.interface) {
+            (Some(preset), _) => {
+                VerifyImports::with_preset(preset).map_err(|_| invalid_preset(preset))?
+            }
+            (None, Some(_)) => VerifyImports::new(ImportList::new(), false, false),
+            (None, None) => {
+                return Err(ModuleError::Custom(
+                    "Either a preset or an interface must be set".to_string(),
+                ))
+            }
+        };
+        if let Some(path) = config.interface {
+            ret.list.concatenate(ImportList::from_file(path)?);
+        }
+        if let Some(require_all) = config.require_all {
+            ret.require_all = require_all;
+        }
+        if let Some(allow_unlisted) = config.allow_unlisted {
+            ret.allow_unlisted = allow_unlisted;
+        }
+        Ok(ret)
+    }
+}
+
 // Utility functions used in tests to get more coverage
 #[cfg(test)]
 impl<'a> VerifyImports<'a> {
@@ -65,140 +114,107 @@ impl<'a> VerifyImports<'a> {
 }
 
 impl<'a> ModuleValidator for VerifyImports<'a> {
-    fn validate(&self, module: &Module) -> Result<bool, ModuleError> {
-        let import_section_len = if let Some(section) = module.import_section() {
-            section.entries().len()
-        } else {
-            0
-        };
-
-        Ok(match (self.require_all, self.allow_unlisted) {
-            // Check that all listed imports exist and are correct.
-            (true, true) => self
-                .list
-                .entries()
-                .iter()
-                .map(|e| e.is_imported(module))
-                .find(|e| *e == false)
-                .is_none(),
-            // Check that all listed imports exist, are correct, and are the only imports in the
-            // module.
-            (true, false) => {
-                self.list
-                    .entries()
-                    .iter()
-                    .map(|e| e.is_imported(module))
-                    .find(|e| *e == false)
-                    .is_none()
-                    && (self.list.entries().len() == import_section_len)
+    fn report(&self, module: &Module) -> Result<ValidationReport, ModuleError> {
+        let mut report = ValidationReport::new();
+
+        // Check the listed imports. Missing imports are only an error if the entire interface is
+        // required.
+        for entry in self.list.entries().iter() {
+            match entry.check(module) {
+                ImportStatus::Good => (),
+                ImportStatus::NotFound => {
+                    if self.require_all {
+                        report.push(entry.finding(FindingKind::MissingImport));
+                    }
+                }
+                ImportStatus::Malformed => {
+                    // A malformed import always exists in the module.
+                    let import = find_import(module, entry.namespace(), entry.field())
+                        .expect("malformed import not found");
+                    report.push(
+                        entry
+                            .finding(FindingKind::MalformedImport)
+                            .with_actual(describe_import(module, import)),
+                    );
+                }
             }
-            // Check that the imports which are both listed and imported are of correct type.
-            (false, true) => self
-                .list
-                .entries()
-                .iter()
-                .map(|e| e.check(module))
-                .find(|e| *e == ImportStatus::Malformed)
-                .is_none(),
-            (false, false) => {
-                // Check that all existent imports are listed and correct.
-                let mut checklist: Vec<ImportStatus> = self
+        }
+
+        // Check for imports which are not on the list.
+        if let Some(section) = module.import_section() {
+            for import in section.entries().iter().filter(|import| {
+                !self
                     .list
                     .entries()
                     .iter()
-                    .map(|e| e.check(module))
-                    .collect();
-                let valid_entries_count = checklist
-                    .iter()
-                    .filter(|e| **e == ImportStatus::Good)
-                    .count();
-
-                // Proof: If the number of valid entries is equal to the number of existing entries, all
-                // entries are valid.
-                //
-                // If an import entry is valid, it exists; If the number of existent imports is
-                // equal to the number of valid imports, all existent imports are valid; If all
-                // existent imports are valid, there are no existent invalid imports; qed
-                valid_entries_count == import_section_len
+                    .any(|e| e.namespace() == import.module() && e.field() == import.field())
+            }) {
+                let finding = if self.allow_unlisted {
+                    Finding::warning(FindingKind::UnlistedImport)
+                } else {
+                    Finding::error(FindingKind::UnlistedImport)
+                };
+                report.push(
+                    finding
+                        .with_namespace(import.module())
+                        .with_field(import.field())
+                        .with_actual(describe_import(module, import)),
+                );
             }
-        })
+        }
+
+        Ok(report)
     }
 }
 
-impl<'a> IsImported for ImportType<'a> {
-    fn is_imported(&self, module: &Module) -> bool {
-        if let Some(section) = module.import_section() {
-            match self {
-                ImportType::Function(namespace, field, sig) => {
-                    has_func_import(module, namespace, field, sig)
-                }
-                ImportType::Global(namespace, field) => {
-                    has_global_import(section, namespace, field)
-                }
-                ImportType::Memory(namespace, field) => {
-                    has_memory_import(section, namespace, field)
-                }
-                ImportType::Table(namespace, field) => has_table_import(section, namespace, field),
-            }
-        } else {
-            false
+impl<'a> ImportType<'a> {
+    /// Returns a human-readable description of the expected import.
+    fn describe(&self) -> String {
+        match self {
+            ImportType::Function(_, _, sig) => fmt_func_type(sig),
+            ImportType::Global(_, _, Some(global_type)) => fmt_global_type(global_type),
+            ImportType::Global(_, _, None) => "global".to_string(),
+            ImportType::Memory(_, _, Some(limits)) => fmt_limits("memory", limits),
+            ImportType::Memory(_, _, None) => "memory".to_string(),
+            ImportType::Table(_, _, Some(limits)) => fmt_limits("table", limits),
+            ImportType::Table(_, _, None) => "table".to_string(),
         }
     }
+
+    /// Creates a finding about this listed import.
+    fn finding(&self, kind: FindingKind) -> Finding {
+        Finding::error(kind)
+            .with_namespace(self.namespace())
+            .with_field(self.field())
+            .with_expected(self.describe())
+    }
 }
 
 impl<'a> ImportCheck for ImportType<'a> {
     fn check(&self, module: &Module) -> ImportStatus {
-        // Destructure self here so that it is easier to manipulate individual fields later.
-        let (module_str, field_str, func_sig) = match self {
-            ImportType::Function(namespace, field, sig) => (namespace, field, Some(sig)),
-            ImportType::Global(namespace, field) => (namespace, field, None),
-            ImportType::Memory(namespace, field) => (namespace, field, None),
-            ImportType::Table(namespace, field) => (namespace, field, None),
-        };
-
-        if let Some(section) = module.import_section() {
-            // Find an entry that matches self. If the name matches, check the namespace and/or
-            // signature.
-            if let Some(entry) = section
-                .entries()
-                .iter()
-                .find(|e| e.field() == *field_str && *module_str == e.module())
-            {
-                match entry.external() {
-                    External::Function(idx) => {
-                        let sig = func_sig.expect("Function entry missing signature!");
-                        if *sig == imported_func_sig_by_index(module, *idx as usize) {
-                            ImportStatus::Good
-                        } else {
-                            ImportStatus::Malformed
-                        }
-                    }
-                    // NOTE: There may be a better way to do mappings between enum variants.
-                    // Just check import variant here.
-                    External::Global(_idx) => {
-                        if let ImportType::Global(_n, _f) = self {
-                            ImportStatus::Good
-                        } else {
-                            ImportStatus::Malformed
-                        }
-                    }
-                    External::Memory(_idx) => {
-                        if let ImportType::Memory(_n, _f) = self {
-                            ImportStatus::Good
-                        } else {
-                            ImportStatus::Malformed
-                        }
-                    }
-                    External::Table(_idx) => {
-                        if let ImportType::Table(_n, _f) = self {
-                            ImportStatus::Good
-                        } else {
-                            ImportStatus::Malformed
-                        }
+        // Find an entry that matches self. If the name matches, check the kind and/or signature.
+        if let Some(entry) = find_import(module, self.namespace(), self.field()) {
+            match (entry.external(), self) {
+                (External::Function(idx), ImportType::Function(_, _, sig)) => {
+                    if *sig == imported_func_sig_by_index(module, *idx as usize) {
+                        ImportStatus::Good
+                    } else {
+                        ImportStatus::Malformed
                     }
                 }
-            } else {
-                ImportStatus::NotFound
+                // Globals, memories and tables are only checked further if a type or limits are
+                // listed.
+                (External::Global(actual), ImportType::Global(_, _, expected)) => match expected {
+                    Some(expected) if *expected != *actual => ImportStatus::Malformed,
+                    _ => ImportStatus::Good,
+                },
+                (External::Memory(actual), ImportType::Memory(_, _, expected)) => {
+                    check_limits(actual.limits(), expected)
+                }
+                (External::Table(actual), ImportType::Table(_, _, expected)) => {
+                    check_limits(actual.limits(), expected)
+                }
+                _ => ImportStatus::Malformed,
             }
         } else {
             ImportStatus::NotFound
@@ -206,69 +222,36 @@ impl<'a> ImportCheck for ImportType<'a> {
     }
 }
 
-fn has_global_import(section: &ImportSection, namespace: &str, field: &str) -> bool {
-    if let Some(import) = section
-        .entries()
-        .iter()
-        .find(|e| e.module() == namespace && e.field() == field)
-    {
-        match import.external() {
-            External::Global(_globaltype) => true,
-            _ => false,
-        }
-    } else {
-        false
-    }
-}
-
-fn has_memory_import(section: &ImportSection, namespace: &str, field: &str) -> bool {
-    if let Some(import) = section
-        .entries()
-        .iter()
-        .find(|e| e.module() == namespace && e.field() == field)
-    {
-        match import.external() {
-            External::Memory(_memorytype) => true,
-            _ => false,
+/// Compares the limits of an imported memory or table against the expected limits, if any.
+fn check_limits(actual: &ResizableLimits, expected: &Option<ResizableLimits>) -> ImportStatus {
+    match expected {
+        Some(expected)
+            if expected.initial() != actual.initial() || expected.maximum() != actual.maximum() =>
+        {
+            ImportStatus::Malformed
         }
-    } else {
-        false
+        _ => ImportStatus::Good,
     }
 }
 
-fn has_table_import(section: &ImportSection, namespace: &str, field: &str) -> bool {
-    if let Some(import) = section
+/// Finds an import entry by namespace and field.
+fn find_import<'m>(module: &'m Module, namespace: &str, field: &str) -> Option<&'m ImportEntry> {
+    module
+        .import_section()?
         .entries()
         .iter()
         .find(|e| e.module() == namespace && e.field() == field)
-    {
-        match import.external() {
-            External::Table(_tabletype) => true,
-            _ => false,
-        }
-    } else {
-        false
-    }
 }
 
-fn has_func_import(module: &Module, namespace: &str, field: &str, sig: &FunctionType) -> bool {
-    if let Some(section) = module.import_section() {
-        if let Some(import) = section
-            .entries()
-            .iter()
-            .find(|e| e.module() == namespace && e.field() == field)
-        {
-            match import.external() {
-                External::Function(index) => {
-                    imported_func_sig_by_index(module, *index as usize) == *sig
-                }
-                _ => false,
-            }
-        } else {
-            false
+/// Returns a human-readable description of an import entry found in the module.
+fn describe_import(module: &Module, import: &ImportEntry) -> String {
+    match import.external() {
+        External::Function(idx) => {
+            fmt_func_type(&imported_func_sig_by_index(module, *idx as usize))
         }
-    } else {
-        false
+        External::Global(global_type) => fmt_global_type(global_type),
+        External::Memory(memory_type) => fmt_limits("memory", memory_type.limits()),
+        External::Table(table_type) => fmt_limits("table", table_type.limits()),
     }
 }
 
@@ -285,6 +268,8 @@ pub fn imported_func_sig_by_index(module: &Module, index: usize) -> FunctionType
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::report::Severity;
+    use parity_wasm::builder;
     use parity_wasm::elements::deserialize_buffer;
 
     #[test]
@@ -550,8 +535,8 @@ mod tests {
         let module = deserialize_buffer::<Module>(&wasm).unwrap();
         let checker = VerifyImports {
             list: ImportList::with_entries(vec![ImportType::Function(
-                "ethereum",
-                "storageStore",
+                "ethereum".into(),
+                "storageStore".into(),
                 FunctionType::new(vec![ValueType::I32, ValueType::I32], None),
             )]),
             require_all: true,
@@ -585,8 +570,8 @@ mod tests {
         let module = deserialize_buffer::<Module>(&wasm).unwrap();
         let checker = VerifyImports {
             list: ImportList::with_entries(vec![ImportType::Function(
-                "ethereum",
-                "storageStore",
+                "ethereum".into(),
+                "storageStore".into(),
                 FunctionType::new(vec![ValueType::I32, ValueType::I32], None),
             )]),
             allow_unlisted: true,
@@ -692,4 +677,212 @@ mod tests {
 
         assert_eq!(true, result);
     }
+
+    #[test]
+    fn report_bad_sig_ewasm() {
+        // wast:
+        // (module
+        //   (import "ethereum" "storageStore" (func $storageStore (param i32)))
+        //   (memory 1)
+        //   (export "main" (func $main))
+        //   (export "memory" (memory 0))
+        //   (func $main)
+        // )
+        let wasm: Vec<u8> = vec![
+            0x00, 0x61, 0x73, 0x6d, 0x01, 0x00, 0x00, 0x00, 0x01, 0x08, 0x02, 0x60, 0x01, 0x7f,
+            0x00, 0x60, 0x00, 0x00, 0x02, 0x19, 0x01, 0x08, 0x65, 0x74, 0x68, 0x65, 0x72, 0x65,
+            0x75, 0x6d, 0x0c, 0x73, 0x74, 0x6f, 0x72, 0x61, 0x67, 0x65, 0x53, 0x74, 0x6f, 0x72,
+            0x65, 0x00, 0x00, 0x03, 0x02, 0x01, 0x01, 0x05, 0x03, 0x01, 0x00, 0x01, 0x07, 0x11,
+            0x02, 0x04, 0x6d, 0x61, 0x69, 0x6e, 0x00, 0x01, 0x06, 0x6d, 0x65, 0x6d, 0x6f, 0x72,
+            0x79, 0x02, 0x00, 0x0a, 0x04, 0x01, 0x02, 0x00, 0x0b,
+        ];
+
+        let module = deserialize_buffer::<Module>(&wasm).unwrap();
+        let checker = VerifyImports::with_preset("ewasm").unwrap();
+        let report = checker.report(&module).unwrap();
+
+        assert!(!report.is_valid());
+        assert_eq!(
+            report.findings(),
+            &[Finding::error(FindingKind::MalformedImport)
+                .with_namespace("ethereum")
+                .with_field("storageStore")
+                .with_expected("(func (param i32 i32))".to_string())
+                .with_actual("(func (param i32))".to_string())]
+        );
+    }
+
+    #[test]
+    fn report_unlisted_import_ewasm() {
+        // wast:
+        // (module
+        //   (import "ethereum" "storageStore" (func $storageStore (param i32 i32)))
+        //   (import "ethereum" "foobar" (func $foo))
+        //   (memory 1)
+        //   (export "main" (func $main))
+        //   (export "memory" (memory 0))
+        //   (func $main)
+        // )
+        let wasm: Vec<u8> = vec![
+            0x00, 0x61, 0x73, 0x6d, 0x01, 0x00, 0x00, 0x00, 0x01, 0x09, 0x02, 0x60, 0x02, 0x7f,
+            0x7f, 0x00, 0x60, 0x00, 0x00, 0x02, 0x2b, 0x02, 0x08, 0x65, 0x74, 0x68, 0x65, 0x72,
+            0x65, 0x75, 0x6d, 0x0c, 0x73, 0x74, 0x6f, 0x72, 0x61, 0x67, 0x65, 0x53, 0x74, 0x6f,
+            0x72, 0x65, 0x00, 0x00, 0x08, 0x65, 0x74, 0x68, 0x65, 0x72, 0x65, 0x75, 0x6d, 0x06,
+            0x66, 0x6f, 0x6f, 0x62, 0x61, 0x72, 0x00, 0x01, 0x03, 0x02, 0x01, 0x01, 0x05, 0x03,
+            0x01, 0x00, 0x01, 0x07, 0x11, 0x02, 0x04, 0x6d, 0x61, 0x69, 0x6e, 0x00, 0x02, 0x06,
+            0x6d, 0x65, 0x6d, 0x6f, 0x72, 0x79, 0x02, 0x00, 0x0a, 0x04, 0x01, 0x02, 0x00, 0x0b,
+        ];
+
+        let module = deserialize_buffer::<Module>(&wasm).unwrap();
+        let mut checker = VerifyImports::with_preset("ewasm").unwrap();
+        let report = checker.report(&module).unwrap();
+
+        assert!(!report.is_valid());
+        assert_eq!(report.findings().len(), 1);
+        assert_eq!(report.findings()[0].kind, FindingKind::UnlistedImport);
+        assert_eq!(report.findings()[0].field, Some("foobar".to_string()));
+        assert_eq!(report.findings()[0].actual, Some("(func)".to_string()));
+
+        // Unlisted imports are only reported as warnings when allowed.
+        checker.set_allow_unlisted(true);
+        let report = checker.report(&module).unwrap();
+        assert!(report.is_valid());
+        assert_eq!(report.findings()[0].severity, Severity::Warning);
+    }
+
+    #[test]
+    fn with_config_require_all() {
+        // wast:
+        // (module
+        //   (import "ethereum" "storageStore" (func $storageStore (param i32 i32)))
+        //   (memory 1)
+        //   (export "main" (func $main))
+        //   (export "memory" (memory 0))
+        //   (func $main)
+        // )
+        let wasm: Vec<u8> = vec![
+            0x00, 0x61, 0x73, 0x6d, 0x01, 0x00, 0x00, 0x00, 0x01, 0x09, 0x02, 0x60, 0x02, 0x7f,
+            0x7f, 0x00, 0x60, 0x00, 0x00, 0x02, 0x19, 0x01, 0x08, 0x65, 0x74, 0x68, 0x65, 0x72,
+            0x65, 0x75, 0x6d, 0x0c, 0x73, 0x74, 0x6f, 0x72, 0x61, 0x67, 0x65, 0x53, 0x74, 0x6f,
+            0x72, 0x65, 0x00, 0x00, 0x03, 0x02, 0x01, 0x01, 0x05, 0x03, 0x01, 0x00, 0x01, 0x07,
+            0x11, 0x02, 0x04, 0x6d, 0x61, 0x69, 0x6e, 0x00, 0x01, 0x06, 0x6d, 0x65, 0x6d, 0x6f,
+            0x72, 0x79, 0x02, 0x00, 0x0a, 0x04, 0x01, 0x02, 0x00, 0x0b,
+        ];
+
+        let module = deserialize_buffer::<Module>(&wasm).unwrap();
+        let config = serde_yaml::from_str("{preset: ewasm, require_all: true}").unwrap();
+        let checker = VerifyImports::with_config(&config).unwrap();
+        let result = checker.validate(&module).unwrap();
+        assert!(!result);
+
+        let config = serde_yaml::from_str("{preset: ewasm, require_all: false}").unwrap();
+        let checker = VerifyImports::with_config(&config).unwrap();
+        let result = checker.validate(&module).unwrap();
+        assert!(result);
+    }
+
+    #[test]
+    fn with_config_errors() {
+        let config = serde_yaml::from_str("{preset: ewasm, require: true}").unwrap();
+        assert!(VerifyImports::with_config(&config).is_err());
+
+        let config = serde_yaml::from_str("{preset: ewasm, allow_unlisted: yes please}").unwrap();
+        assert!(VerifyImports::with_config(&config).is_err());
+
+        let config = serde_yaml::from_str("{preset: foo}").unwrap();
+        assert!(VerifyImports::with_config(&config).is_err());
+
+        let config = serde_yaml::from_str("{require_all: true}").unwrap();
+        assert!(VerifyImports::with_config(&config).is_err());
+    }
+
+    #[test]
+    fn custom_interface_global_and_memory() {
+        let module = builder::module()
+            .import()
+            .path("env", "stackTop")
+            .external()
+            .global(ValueType::I32, true)
+            .build()
+            .import()
+            .path("env", "memory")
+            .external()
+            .memory(1, Some(2))
+            .build()
+            .build();
+
+        let list = ImportList::from_definition(
+            "
+imports:
+  - {kind: global, namespace: env, field: stackTop, type: i32, mutable: true}
+  - {kind: memory, namespace: env, field: memory, initial: 1, maximum: 2}
+",
+        )
+        .unwrap();
+        let checker = VerifyImports::new(list, true, false);
+        assert!(checker.validate(&module).unwrap());
+
+        let list = ImportList::from_definition(
+            "
+imports:
+  - {kind: global, namespace: env, field: stackTop, type: i32}
+  - {kind: memory, namespace: env, field: memory, initial: 1}
+",
+        )
+        .unwrap();
+        let checker = VerifyImports::new(list, true, false);
+        let report = checker.report(&module).unwrap();
+        assert_eq!(
+            report.findings(),
+            &[
+                Finding::error(FindingKind::MalformedImport)
+                    .with_namespace("env")
+                    .with_field("stackTop")
+                    .with_expected("(global i32)".to_string())
+                    .with_actual("(global (mut i32))".to_string()),
+                Finding::error(FindingKind::MalformedImport)
+                    .with_namespace("env")
+                    .with_field("memory")
+                    .with_expected("(memory 1)".to_string())
+                    .with_actual("(memory 1 2)".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn with_config_interface_file() {
+        let module = builder::module()
+            .import()
+            .path("env", "memory")
+            .external()
+            .memory(1, None)
+            .build()
+            .build();
+
+        let path = std::env::temp_dir().join("chisel_verifyimports_interface.yml");
+        std::fs::write(
+            &path,
+            "imports: [{kind: memory, namespace: env, field: memory}]",
+        )
+        .unwrap();
+
+        let mut config = serde_yaml::Mapping::new();
+        config.insert(
+            Value::String("interface".to_string()),
+            Value::String(path.to_str().unwrap().to_string()),
+        );
+        let checker = VerifyImports::with_config(&Value::Mapping(config.clone())).unwrap();
+        assert!(checker.validate(&module).unwrap());
+
+        // The ewasm preset is checked in addition to the interface.
+        config.insert(
+            Value::String("preset".to_string()),
+            Value::String("ewasm".to_string()),
+        );
+        config.insert(Value::String("require_all".to_string()), Value::Bool(true));
+        let checker = VerifyImports::with_config(&Value::Mapping(config)).unwrap();
+        assert!(!checker.validate(&module).unwrap());
+
+        std::fs::remove_file(&path).unwrap();
+    }
 }